@@ -1,5 +1,6 @@
 use candid::{candid_method, Principal};
 use candid::CandidType;
+use canister_metrics::{CanisterStats, HttpRequest, HttpResponse};
 use canister_sig_util::signature_map::LABEL_SIG;
 use ic_cdk::{call, id, trap};
 use ic_cdk::api::call::CallResult;
@@ -123,6 +124,23 @@ async fn clean_memory() {
     clean_state();
 }
 
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+#[candid_method(query)]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(
+        ic_cdk::api::canister_balance128(),
+        ic_cdk::api::stable::stable64_size(),
+    );
+    canister_metrics::http_request(&request, ic_cdk::api::time(), stats, |w| {
+        w.encode_gauge(
+            "signature_map_size",
+            state::signature_count() as f64,
+            "Number of delegation signatures in the signature map.",
+        )
+    })
+}
+
 /// Called when the canister starts.
 /// Initializes the application with `InitArgs` parameters and stores them in persistent storage.
 #[init]
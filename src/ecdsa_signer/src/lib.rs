@@ -14,6 +14,9 @@ use ic_cdk::export::{
 use ic_certified_map::{AsHashTree, RbTree};
 use ic_cdk::export::candid::Principal;
 use ic_cdk_macros::*;
+use canister_metrics::CanisterStats;
+use serde_bytes::ByteBuf;
+use root_id_resolver::{resolve_with, ResolverMode, RootIdError};
 
 #[derive(CandidType, Serialize, Debug, Deserialize)]
 pub struct KeyPair {
@@ -40,6 +43,21 @@ struct CertifiedKeyPairResponse {
     witness: Vec<u8>,
 }
 
+#[derive(CandidType, Deserialize, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
 #[derive(Clone, Copy, Debug, CandidType, Deserialize, PartialEq, Serialize, Hash)]
 pub enum StorageVariant {
     #[serde(rename = "ETH")]
@@ -55,7 +73,8 @@ thread_local! {
     static CONFIG: RefCell<Conf> = RefCell::new( Conf {
         controllers: Default::default(),
         storage: None,
-        im_canister: None
+        im_canister: None,
+        test_mode: None,
     });
     static ECDSA_KEYS: RefCell<HashMap<String,KeyPairObject>> = RefCell::new(HashMap::new());
     static TREE: RefCell<RbTree<String, Vec<u8>>> = const { RefCell::new(RbTree::new()) };
@@ -97,11 +116,14 @@ pub struct Conf {
     pub controllers: Option<Vec<Principal>>,
     pub storage: Option<StorageVariant>,
     pub im_canister: Option<String>,
+    /// Treats every caller as its own root when `im_canister` is not set, for local deployments.
+    pub test_mode: Option<bool>,
 }
 
 #[init]
 async fn init(conf: Option<Conf>) -> () {
     if let Some(conf) = conf {
+        configure_resolver(&conf);
         CONFIG.with(|storage| {
             storage.replace(conf);
         });
@@ -111,6 +133,7 @@ async fn init(conf: Option<Conf>) -> () {
 #[update]
 async fn reconfig(conf: Conf) -> () {
     trap_if_not_authenticated_admin();
+    configure_resolver(&conf);
     CONFIG.with(|storage| {
         storage.replace(conf);
     })
@@ -209,6 +232,22 @@ async fn get_principal(payload: Option<String>) -> (String, Option<String>) {
     (principal, payload)
 }
 
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+#[candid_method(query)]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(ic_cdk::api::canister_balance128(), ic_cdk::api::stable::stable64_size());
+    let response = canister_metrics::serve(&request.url, ic_cdk::api::time(), stats, |w| {
+        let keys = ECDSA_KEYS.with(|keys| keys.borrow().len());
+        w.encode_gauge("ecdsa_keys", keys as f64, "Number of stored key pairs.")
+    });
+    HttpResponse {
+        status_code: response.status_code,
+        headers: response.headers,
+        body: ByteBuf::from(response.body),
+    }
+}
+
 #[pre_upgrade]
 fn pre_upgrade() {
     let conf: Conf = CONFIG.with(|c| {
@@ -282,22 +321,25 @@ fn trap_if_not_authenticated_admin() {
     }
 }
 
+fn configure_resolver(conf: &Conf) {
+    root_id_resolver::configure(ResolverMode::from_config(
+        conf.im_canister.clone(),
+        conf.test_mode.unwrap_or(false),
+    ));
+}
+
 async fn get_root_id() -> Option<String> {
-    match CONFIG.with(|c| c.borrow_mut().im_canister.clone()) {
-        None => {
-            Some(caller().to_text())  //DONE FOR TESTING PURPOSES
-        }
-        Some(canister) => {
-            let princ = caller();
-            let im_canister = Principal::from_text(canister)
-                .expect("Unable to obtain Principal from im_canister.");
-
-            let res: Option<String> = match call(im_canister, "get_root_by_principal", (princ.to_text(), 0)).await {
-                Ok((res, )) => res,
-                Err((_, err)) => trap(&format!("failed to request IM: {}", err)),
-            };
-            res
-        }
+    let root = resolve_with(&caller().to_text(), ic_cdk::api::time(), |im_canister, principal| async move {
+        let im_canister = Principal::from_text(im_canister)
+            .map_err(|err| format!("Invalid IM canister: {}", err))?;
+        call::<(String, ), (Option<String>, )>(im_canister, "get_root_by_principal", (principal, )).await
+            .map(|(root, )| root)
+            .map_err(|(_, err)| err)
+    }).await;
+    match root {
+        Ok(root) => Some(root),
+        Err(RootIdError::NotRegistered) => None,
+        Err(err) => trap(&err.to_string()),
     }
 }
 
@@ -322,6 +364,7 @@ fn post_upgrade() {
         Ok(store) => {
             let (post_data, _a): (PersistedData, i32) = store;
             if post_data.conf.is_some() {
+                configure_resolver(post_data.conf.as_ref().expect("The post_data.conf failed after existence check."));
                 CONFIG.with(|storage| {
                     storage.replace(post_data.conf.clone().expect("The post_data.conf failed after existence check."));
                 });
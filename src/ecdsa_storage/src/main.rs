@@ -2,9 +2,12 @@ use std::cell::{Cell, RefCell};
 
 use candid::{candid_method, Principal};
 use candid::CandidType;
+use canister_metrics::{CanisterStats, HttpRequest, HttpResponse};
 use hex::encode;
-use ic_cdk::{call, caller, storage, trap};
-use ic_cdk_macros::{init, post_upgrade, pre_upgrade, update};
+use ic_cdk::storage;
+use ic_cdk_macros::{init, post_upgrade, pre_upgrade, query, update};
+use root_id_resolver::client::caller_root_id_or_trap;
+use root_id_resolver::ResolverMode;
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
 thread_local! {
@@ -50,7 +53,7 @@ fn init(maybe_arg: Option<InitArgs>) {
 
 #[update]
 async fn get_salt() -> String {
-    let root = get_root_id().await;
+    let root = caller_root_id_or_trap().await;
     let ecdsa_salt = STATE.with(|s| s.ecdsa_salt.borrow().clone());
     let salted_key = format!("{}{}", root, ecdsa_salt);
     sha2(&salted_key)
@@ -58,7 +61,7 @@ async fn get_salt() -> String {
 
 #[update]
 async fn get_anon_salt(data: String) -> String {
-    let root = get_root_id().await;
+    let root = caller_root_id_or_trap().await;
     let ecdsa_salt = STATE.with(|s| s.ecdsa_salt.borrow().clone());
     let salt = STATE.with(|s: &State| s.salt.borrow().clone());
     let salted_data = format!("{}{}{}{}", root, data, salt, ecdsa_salt);
@@ -66,6 +69,17 @@ async fn get_anon_salt(data: String) -> String {
 }
 
 
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+#[candid_method(query)]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(
+        ic_cdk::api::canister_balance128(),
+        ic_cdk::api::stable::stable64_size(),
+    );
+    canister_metrics::http_request(&request, ic_cdk::api::time(), stats, |_| Ok(()))
+}
+
 /// Applies changes afterr the canister upgrade.
 #[post_upgrade]
 async fn post_upgrade(_maybe_arg: Option<InitArgs>) {
@@ -85,6 +99,7 @@ pub fn init_im_canister(args: InitArgs) {
         s.ecdsa_salt.replace(args.ecdsa_salt);
         s.salt.replace(args.salt);
     });
+    configure_resolver(Some(args.im_canister));
 }
 
 fn main() {}
@@ -114,6 +129,7 @@ pub async fn init_from_memory() {
         s.ecdsa_salt.replace(mo.ecdsa_salt.unwrap());
         s.salt.replace(mo.salt.unwrap());
     });
+    configure_resolver(mo.im_canister);
 }
 
 pub async fn save_to_temp_memory() {
@@ -132,16 +148,9 @@ fn sha2(value: &str) -> String {
     encode(hasher.finalize()) // Convert bytes to hex string
 }
 
-async fn get_root_id() -> String {
-    match STATE.with(|c| c.im_canister.get()) {
-        None => caller().to_text(), // Return caller for testing purposes when im_canister is None
-        Some(canister) => {
-            let princ = caller();
-            match call(canister, "get_root_by_principal", (princ.to_text(), 0)).await {
-                Ok((Some(root_id), )) => root_id,
-                Ok((None, )) => trap("No root found for this principal"),
-                Err((_, err)) => trap(&format!("Failed to request IM: {}", err)),
-            }
-        }
-    }
+fn configure_resolver(im_canister: Option<Principal>) {
+    root_id_resolver::configure(ResolverMode::from_config(
+        im_canister.map(|canister| canister.to_text()),
+        false,
+    ));
 }
@@ -4,9 +4,12 @@ use std::collections::{HashMap, HashSet};
 
 use candid::{export_service};
 use candid::{CandidType, Nat, Principal};
+use canister_metrics::{CanisterStats, HttpRequest, HttpResponse, MetricsEncoder};
 use ic_cdk::api::call::CallResult;
 use ic_cdk::{call, caller, id, storage, trap};
 use ic_cdk_macros::*;
+use root_id_resolver::client::caller_root_id_or_trap;
+use root_id_resolver::ResolverMode;
 use serde::{Deserialize, Serialize};
 
 use crate::signer::{
@@ -67,6 +70,8 @@ pub struct CanisterStatusResponse {
 pub struct Conf {
     pub im_canister: Option<Principal>,
     pub operator: Option<Principal>,
+    /// Treats every caller as its own root when `im_canister` is not set, for local deployments.
+    pub test_mode: Option<bool>,
 }
 
 #[derive(CandidType, Deserialize, Clone, Serialize, Debug, Eq)]
@@ -285,7 +290,8 @@ enum Icrc2TransferFromResult {
 thread_local! {
      static CONFIG: RefCell<Conf> = const { RefCell::new( Conf {
         im_canister: None,
-        operator: None
+        operator: None,
+        test_mode: None,
     }) };
     pub static ICRC_REGISTRY: RefCell<HashSet<ICRC1>> = RefCell::new(HashSet::default());
     pub static NEURON_REGISTRY: RefCell<HashSet<NeuronData>> = RefCell::new(HashSet::default());
@@ -302,7 +308,7 @@ thread_local! {
 /// Persists a single ICRC1 canister's metadata into the canister's storage.
 #[update]
 pub async fn store_icrc1_canister(request: ICRC1Request) {
-    get_root_id().await;
+    caller_root_id_or_trap().await;
     Principal::from_text(request.ledger.clone()).unwrap_or_else(|_| {
         trap("Invalid ledger principal");
     });
@@ -340,6 +346,7 @@ pub async fn store_icrc1_canister(request: ICRC1Request) {
 #[init]
 pub async fn init(conf: Option<Conf>) {
     if let Some(conf) = conf {
+        configure_resolver(&conf);
         CONFIG.with(|storage| {
             storage.replace(conf);
         });
@@ -431,7 +438,7 @@ fn find_app_for_request(registry: &std::collections::HashSet<DiscoveryApp>, requ
 /// Creates a new DiscoveryApp entry if none exists for the given derivation_origin (or hostname).
 #[update]
 pub async fn store_discovery_app(request: DiscoveryVisitRequest) {
-    let root_id = get_root_id().await;
+    let root_id = caller_root_id_or_trap().await;
 
     let mut app = DISCOVERY_REGISTRY.with(|registry| {
         find_app_for_request(&registry.borrow(), &request)
@@ -550,7 +557,7 @@ pub fn count_discovery_apps() -> u64 {
 /// root id, hence #[update].
 #[update]
 pub async fn get_my_discovery_apps() -> Vec<UserDiscoveryApp> {
-    let root_id = get_root_id().await;
+    let root_id = caller_root_id_or_trap().await;
     DISCOVERY_USER_PRINCIPALS.with(|map| {
         map.borrow()
             .get(&root_id)
@@ -756,6 +763,53 @@ pub fn get_bid_history_paginated(offset: u64, limit: u64) -> Vec<HistoricalBid>
     })
 }
 
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(
+        ic_cdk::api::canister_balance128(),
+        ic_cdk::api::stable::stable64_size(),
+    );
+    canister_metrics::http_request(&request, ic_cdk::api::time(), stats, encode_metrics)
+}
+
+fn encode_metrics(w: &mut MetricsEncoder<Vec<u8>>) -> std::io::Result<()> {
+    let icrc1_canisters = ICRC_REGISTRY.with(|registry| registry.borrow().len());
+    w.encode_gauge(
+        "icrc1_canisters",
+        icrc1_canisters as f64,
+        "Number of ICRC1 canisters in the registry.",
+    )?;
+    let neurons = NEURON_REGISTRY.with(|registry| registry.borrow().len());
+    w.encode_gauge(
+        "neurons",
+        neurons as f64,
+        "Number of neurons in the registry.",
+    )?;
+    let discovery_apps = DISCOVERY_REGISTRY.with(|registry| registry.borrow().len());
+    w.encode_gauge(
+        "discovery_apps",
+        discovery_apps as f64,
+        "Number of discovery apps.",
+    )?;
+    let discovery_visitors: usize =
+        DISCOVERY_VISITORS.with(|visitors| visitors.borrow().values().map(HashSet::len).sum());
+    w.encode_gauge(
+        "discovery_visitors",
+        discovery_visitors as f64,
+        "Number of unique visitors summed over the discovery apps.",
+    )?;
+    let bids = BID_HISTORY.with(|history| history.borrow().len());
+    w.encode_gauge("bids", bids as f64, "Number of bids in the bid history.")?;
+    let featured = FEATURED_SLOT.with(|slot| slot.borrow().is_some());
+    w.encode_gauge(
+        "featured_slot_occupied",
+        if featured { 1.0 } else { 0.0 },
+        "Whether an app holds the featured slot.",
+    )?;
+    Ok(())
+}
+
 /// Sets the operator principal.
 #[update]
 async fn set_operator(operator: Principal) {
@@ -955,6 +1009,7 @@ pub fn stable_save() {
 pub fn stable_restore() {
     let (mo,): (Memory,) = storage::stable_restore()
         .expect("Stable restore exited unexpectedly: unable to restore data from stable memory.");
+    configure_resolver(&mo.config);
     CONFIG.with(|config| {
         let mut config = config.borrow_mut();
         *config = mo.config.clone();
@@ -1096,18 +1151,11 @@ fn export_candid() -> String {
     __export_service()
 }
 
-async fn get_root_id() -> String {
-    match CONFIG.with(|c| c.borrow_mut().im_canister) {
-        None => caller().to_text(), // Return caller for testing purposes when im_canister is None
-        Some(canister) => {
-            let princ = caller();
-            match call(canister, "get_root_by_principal", (princ.to_text(), 0)).await {
-                Ok((Some(root_id),)) => root_id,
-                Ok((None,)) => trap("No root found for this principal"),
-                Err((_, err)) => trap(&format!("Failed to request IM: {}", err)),
-            }
-        }
-    }
+fn configure_resolver(conf: &Conf) {
+    root_id_resolver::configure(ResolverMode::from_config(
+        conf.im_canister.map(|canister| canister.to_text()),
+        conf.test_mode.unwrap_or(false),
+    ));
 }
 
 fn trap_if_not_authenticated_admin() {
@@ -0,0 +1,157 @@
+use candid::{CandidType, Deserialize};
+use std::fmt;
+
+/// Errors returned by the identity manager.
+/// Clients branch on the variant, so variants are never renamed or reused for another meaning.
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub enum IdentityManagerError {
+    AccountNotFound,
+    AnchorNotRegistered,
+    PrincipalNotRegistered,
+    AccountExists,
+    AnonymousCaller,
+    NfidOnly,
+    AccessPointNotFound,
+    AccessPointExists,
+    NotAnAccessPoint,
+    Forbidden,
+    PasskeyRequired,
+    RecoveryPhraseRequired,
+    RecoveryPhraseProtected,
+    DeviceDataRequired,
+    NameRequired,
+    EmailRequired,
+    EmailNotValidated,
+    ChallengeRequired,
+    SolutionRequired,
+    IncorrectCaptchaKey,
+    IncorrectCaptchaSolution,
+    RecoveryPending,
+    NoPendingRecovery,
+    RecoveryNotAllowed,
+    NoRecoveryPhrase,
+    ConfigurationVersionNotFound,
+    IdTokenRequired,
+    InvalidIdToken,
+    AccessPointProofRequired,
+    InvalidAccessPointProof,
+    BackupChecksumMismatch,
+    InvalidBackup,
+    NoPendingRemoval,
+    InvalidAnchorRange,
+    AnchorRangeExhausted,
+    InternetIdentityOnly,
+    StepUpRequired,
+    InvalidStepUpRule,
+    InvalidPublicKey,
+}
+
+impl IdentityManagerError {
+    pub fn status_code(&self) -> u16 {
+        match self {
+            IdentityManagerError::AccountNotFound
+            | IdentityManagerError::AnchorNotRegistered
+            | IdentityManagerError::PrincipalNotRegistered
+            | IdentityManagerError::AccessPointNotFound
+            | IdentityManagerError::NoPendingRecovery
+            | IdentityManagerError::NoPendingRemoval
+            | IdentityManagerError::NoRecoveryPhrase
+            | IdentityManagerError::ConfigurationVersionNotFound => 404,
+            IdentityManagerError::AccountExists
+            | IdentityManagerError::AccessPointExists
+            | IdentityManagerError::RecoveryPending
+            | IdentityManagerError::BackupChecksumMismatch
+            | IdentityManagerError::AnchorRangeExhausted => 409,
+            IdentityManagerError::AnonymousCaller | IdentityManagerError::InvalidIdToken => 401,
+            IdentityManagerError::NotAnAccessPoint
+            | IdentityManagerError::Forbidden
+            | IdentityManagerError::PasskeyRequired
+            | IdentityManagerError::RecoveryPhraseRequired
+            | IdentityManagerError::RecoveryPhraseProtected
+            | IdentityManagerError::InvalidAccessPointProof
+            | IdentityManagerError::StepUpRequired => 403,
+            IdentityManagerError::NfidOnly
+            | IdentityManagerError::InternetIdentityOnly
+            | IdentityManagerError::DeviceDataRequired
+            | IdentityManagerError::NameRequired
+            | IdentityManagerError::EmailRequired
+            | IdentityManagerError::EmailNotValidated
+            | IdentityManagerError::ChallengeRequired
+            | IdentityManagerError::SolutionRequired
+            | IdentityManagerError::IncorrectCaptchaKey
+            | IdentityManagerError::IncorrectCaptchaSolution
+            | IdentityManagerError::RecoveryNotAllowed
+            | IdentityManagerError::IdTokenRequired
+            | IdentityManagerError::AccessPointProofRequired
+            | IdentityManagerError::InvalidBackup
+            | IdentityManagerError::InvalidAnchorRange
+            | IdentityManagerError::InvalidStepUpRule
+            | IdentityManagerError::InvalidPublicKey => 400,
+        }
+    }
+
+    /// The human readable text, kept equal to the messages returned before the error codes were introduced.
+    pub fn message(&self) -> &'static str {
+        match self {
+            IdentityManagerError::AccountNotFound => "Unable to find Account.",
+            IdentityManagerError::AnchorNotRegistered => "Anchor not registered.",
+            IdentityManagerError::PrincipalNotRegistered => "Principal not registered.",
+            IdentityManagerError::AccountExists => {
+                "Impossible to link this II anchor, please try another one."
+            }
+            IdentityManagerError::AnonymousCaller => "User is anonymous",
+            IdentityManagerError::NfidOnly => "Available only for NFID accounts",
+            IdentityManagerError::InternetIdentityOnly => "Available only for Internet Identity accounts",
+            IdentityManagerError::AccessPointNotFound => "Access Point not exists.",
+            IdentityManagerError::AccessPointExists => "Access Point exists.",
+            IdentityManagerError::NotAnAccessPoint => {
+                "Unauthorised: caller is not an access point of the account"
+            }
+            IdentityManagerError::Forbidden => "Forbidden",
+            IdentityManagerError::PasskeyRequired => "Unauthorised: passkey required",
+            IdentityManagerError::RecoveryPhraseRequired => "Unauthorised: seed phrase required",
+            IdentityManagerError::RecoveryPhraseProtected => "Recovery phrase is protected",
+            IdentityManagerError::DeviceDataRequired => "Device Data required",
+            IdentityManagerError::NameRequired => "Name is empty",
+            IdentityManagerError::EmailRequired => "Email is empty",
+            IdentityManagerError::EmailNotValidated => "Email and principal are not valid.",
+            IdentityManagerError::ChallengeRequired => "Challenge solution required",
+            IdentityManagerError::SolutionRequired => "Solution is required",
+            IdentityManagerError::IncorrectCaptchaKey => "Incorrect captcha key",
+            IdentityManagerError::IncorrectCaptchaSolution => "Incorrect captcha solution",
+            IdentityManagerError::RecoveryPending => "Recovery is already pending",
+            IdentityManagerError::NoPendingRecovery => "No pending recovery.",
+            IdentityManagerError::RecoveryNotAllowed => {
+                "Recovery phrase cannot be added through recovery"
+            }
+            IdentityManagerError::NoRecoveryPhrase => {
+                "The user has no recovery phrase in Internet Identity."
+            }
+            IdentityManagerError::ConfigurationVersionNotFound => "Configuration version not found.",
+            IdentityManagerError::IdTokenRequired => "ID token is required",
+            IdentityManagerError::InvalidIdToken => "ID token is not valid.",
+            IdentityManagerError::AccessPointProofRequired => {
+                "A proof that the new key agreed to be added is required"
+            }
+            IdentityManagerError::InvalidAccessPointProof => "Access point proof is not valid.",
+            IdentityManagerError::BackupChecksumMismatch => {
+                "Backup chunk does not continue the imported checksum chain."
+            }
+            IdentityManagerError::InvalidBackup => "Backup chunk cannot be decoded.",
+            IdentityManagerError::NoPendingRemoval => "No pending account removal.",
+            IdentityManagerError::InvalidAnchorRange => "Anchor range must end after its start.",
+            IdentityManagerError::AnchorRangeExhausted => "No anchors left in the configured ranges.",
+            IdentityManagerError::StepUpRequired => "Unauthorised: a stronger access point is required",
+            IdentityManagerError::InvalidStepUpRule => "Step-up rule must allow at least one access point type.",
+            IdentityManagerError::InvalidPublicKey => "Public key is not a valid principal.",
+        }
+    }
+}
+
+impl fmt::Display for IdentityManagerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for IdentityManagerError {}
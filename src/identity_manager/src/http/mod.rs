@@ -1,2 +1,3 @@
+pub mod error;
 pub mod response_mapper;
 pub mod requests;
@@ -1,5 +1,9 @@
 use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
+use serde_bytes::ByteBuf;
+
+use crate::repository::account_event_repo::AccountEvent;
+use crate::repository::configuration_history_repo::ConfigurationChange;
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
 pub struct PrincipalEmailRequest {
@@ -14,13 +18,30 @@ pub struct AccountRequest {
     pub access_point: Option<AccessPointRequest>,
     pub email: Option<String>,
     pub name: Option<String>,
-    pub challenge_attempt: Option<ChallengeAttempt>
+    pub challenge_attempt: Option<ChallengeAttempt>,
+    /// Required for `OpenID` accounts, see `AccessPointRequest::id_token`.
+    pub id_token: Option<String>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
 pub struct Challenge {
     pub png_base64: Option<String>,
     pub challenge_key: ChallengeKey,
+    pub proof_of_work: Option<ProofOfWorkChallenge>,
+}
+
+/// Solved by a string that makes the SHA-256 of `nonce` followed by it start with `difficulty` zero bits.
+/// The string is sent back as the `chars` of the challenge attempt.
+#[derive(Clone, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub struct ProofOfWorkChallenge {
+    pub nonce: String,
+    pub difficulty: u8,
+}
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, Eq, PartialEq)]
+pub enum ChallengeType {
+    Image,
+    ProofOfWork,
 }
 
 pub type ChallengeKey = String;
@@ -62,6 +83,91 @@ pub struct AccountResponse {
     pub wallet: WalletVariant,
     pub is2fa_enabled: bool,
     pub email: Option<String>,
+    pub pending_recovery: Option<PendingRecoveryResponse>,
+    pub email_verified_at: Option<u64>,
+}
+
+/// The account an access point principal belongs to, unset if the principal is not registered.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PrincipalResolution {
+    pub principal: String,
+    pub root: Option<String>,
+    pub anchor: Option<u64>,
+    pub wallet: Option<WalletVariant>,
+}
+
+/// Everything stored about the caller's account, for data subject access requests.
+/// Fields are only ever added, `version` is raised when the meaning of an existing field changes.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PersonalDataExport {
+    pub version: u32,
+    pub exported_at: u64,
+    pub account: PersonalAccountData,
+    pub access_points: Vec<PersonalAccessPointData>,
+    pub personas: Vec<PersonalPersonaData>,
+    /// Principals temporarily resolving to the account right after it was created.
+    pub temp_keys: Vec<TempKeyData>,
+    pub events: Vec<AccountEvent>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PersonalAccountData {
+    pub principal_id: String,
+    pub anchor: u64,
+    pub wallet: WalletVariant,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub email_verified_at: Option<u64>,
+    pub phone_number: Option<String>,
+    pub phone_number_sha2: Option<String>,
+    pub is2fa_enabled: bool,
+    pub openid_subject: Option<String>,
+    pub pending_recovery: Option<PendingRecoveryResponse>,
+    pub ii_anchor: Option<u64>,
+    pub created_date: u64,
+    pub modified_date: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PersonalAccessPointData {
+    pub principal_id: String,
+    pub credential_id: Option<String>,
+    pub icon: Option<String>,
+    pub device: Option<String>,
+    pub browser: Option<String>,
+    pub device_type: DeviceType,
+    pub last_used: Option<u64>,
+    pub created_date: u64,
+    pub modified_date: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PersonalPersonaData {
+    pub domain: String,
+    pub persona_id: String,
+    pub persona_name: Option<String>,
+    pub domain_certified: Option<u64>,
+    pub created_date: u64,
+    pub modified_date: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct TempKeyData {
+    pub principal_id: String,
+    pub expires_at: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PendingRecoveryResponse {
+    pub access_point: AccessPointResponse,
+    pub initiated_at: u64,
+    pub executable_at: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AccountEventsResponse {
+    pub events: Vec<AccountEvent>,
+    pub next: Option<u64>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
@@ -70,12 +176,14 @@ pub enum CredentialVariant {
     PhoneNumber(PhoneNumberCredential),
 }
 
-#[derive(Clone, Copy, Debug, CandidType, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
 pub enum WalletVariant {
     #[serde(rename = "NFID")]
     NFID,
     #[serde(rename = "II")]
     InternetIdentity,
+    #[serde(rename = "OpenID")]
+    OpenId,
 }
 
 #[derive(Clone, Copy, Debug, CandidType, Deserialize, PartialEq, Eq, Serialize, Hash)]
@@ -131,6 +239,21 @@ pub struct ConfigurationRequest {
     pub account_creation_paused: Option<bool>,
     pub max_free_captcha_per_minute: Option<u16>,
     pub test_captcha: Option<bool>,
+    pub recovery_delay: Option<u64>,
+    pub challenge_type: Option<ChallengeType>,
+    pub proof_of_work_difficulty: Option<u8>,
+    pub max_challenge_attempts: Option<u8>,
+    pub account_removal_grace_period: Option<u64>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ConfigurationVersionResponse {
+    pub version: u64,
+    pub caller: Principal,
+    pub timestamp: u64,
+    pub changes: Vec<ConfigurationChange>,
+    pub rollback_of: Option<u64>,
+    pub configuration: ConfigurationResponse,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
@@ -151,6 +274,11 @@ pub struct ConfigurationResponse {
     pub account_creation_paused: Option<bool>,
     pub max_free_captcha_per_minute: Option<u16>,
     pub test_captcha: Option<bool>,
+    pub recovery_delay: Option<u64>,
+    pub challenge_type: Option<ChallengeType>,
+    pub proof_of_work_difficulty: Option<u8>,
+    pub max_challenge_attempts: Option<u8>,
+    pub account_removal_grace_period: Option<u64>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
@@ -172,6 +300,77 @@ pub struct AccessPointRequest {
     pub browser: String,
     pub device_type: DeviceType,
     pub credential_id: Option<String>,
+    /// An OpenID Connect ID token requested with the nonce of the caller,
+    /// which adds the caller to the account bound to the token subject.
+    pub id_token: Option<String>,
+    /// Required to add a key other than the caller to an NFID account.
+    pub proof: Option<AccessPointProof>,
+}
+
+/// Proves that the key of a new access point agreed to be added by the caller.
+/// `signature` is made by `public_key` over the challenge issued to the caller,
+/// or over `delegation` if it is set.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AccessPointProof {
+    /// DER encoded, its self-authenticating principal is the `pub_key` of the access point.
+    pub public_key: ByteBuf,
+    pub signature: ByteBuf,
+    pub delegation: Option<ProofDelegation>,
+}
+
+/// A delegation from the new key to the session key the caller signs its calls with.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ProofDelegation {
+    pub pubkey: ByteBuf,
+    pub expiration: u64,
+    pub targets: Option<Vec<Principal>>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AccessPointChallenge {
+    pub nonce: ByteBuf,
+    pub expires_at: u64,
+}
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Eq, PartialEq)]
+pub enum BackupFormat {
+    Candid,
+    Cbor,
+}
+
+/// Requests the accounts after `cursor`, in principal order.
+/// `checksum` is the one returned with the previous chunk and is left empty for the first chunk.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AccountsExportRequest {
+    pub cursor: Option<String>,
+    pub limit: Option<u32>,
+    pub format: BackupFormat,
+    pub checksum: Option<ByteBuf>,
+}
+
+/// `data` is a `vec Account` encoded in the requested format.
+/// `checksum` is the SHA-256 of the previous checksum followed by `data`, so the last one covers the whole export.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AccountsChunk {
+    pub data: ByteBuf,
+    pub count: u32,
+    pub next_cursor: Option<String>,
+    pub checksum: ByteBuf,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AccountsImportRequest {
+    pub data: ByteBuf,
+    pub format: BackupFormat,
+    pub checksum: ByteBuf,
+    /// Starts a new checksum chain, so the same canister can import another export.
+    pub first_chunk: Option<bool>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AccountsImportResponse {
+    pub imported: u32,
+    pub checksum: ByteBuf,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
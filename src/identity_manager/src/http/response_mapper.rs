@@ -1,5 +1,7 @@
 use candid::{CandidType, Deserialize};
 
+use crate::http::error::IdentityManagerError;
+
 type Error = String;
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
@@ -7,6 +9,7 @@ pub struct HttpResponse<T> {
     pub data: Option<T>,
     pub error: Option<Error>,
     pub status_code: u16,
+    pub error_code: Option<IdentityManagerError>,
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize)]
@@ -28,6 +31,7 @@ pub fn to_error_response<T>(x: &str) -> HttpResponse<T> {
         data: None,
         error: Some(String::from(x)),
         status_code: 404,
+        error_code: None,
     }
 }
 
@@ -50,6 +54,14 @@ pub fn to_success_response<T>(x: T) -> HttpResponse<T> {
         data: Option::from(x),
         error: None,
         status_code: 200,
+        error_code: None,
+    }
+}
+
+pub fn to_result_response<T>(result: Result<T, IdentityManagerError>) -> HttpResponse<T> {
+    match result {
+        Ok(x) => to_success_response(x),
+        Err(err) => HttpResponse::from(err),
     }
 }
 
@@ -59,6 +71,7 @@ impl<T> ErrorResponse<T> for HttpResponse<T> {
             data: None,
             error: Some(String::from(text)),
             status_code,
+            error_code: None,
         }
     }
 }
@@ -69,6 +82,18 @@ impl<T> DataResponse<T> for HttpResponse<T> {
             data: Some(data),
             error: None,
             status_code,
+            error_code: None,
+        }
+    }
+}
+
+impl<T> From<IdentityManagerError> for HttpResponse<T> {
+    fn from(err: IdentityManagerError) -> Self {
+        HttpResponse {
+            data: None,
+            error: Some(err.to_string()),
+            status_code: err.status_code(),
+            error_code: Some(err),
         }
     }
 }
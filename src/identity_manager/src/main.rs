@@ -1,37 +1,67 @@
-use canister_api_macros::{admin, lambda, operator, paused, two_f_a};
+use candid::Principal;
+use canister_metrics::HttpRequest;
+use canister_api_macros::{
+    admin, authenticated, generate_inspect_message, lambda, operator, paused, requires_role,
+    two_f_a,
+};
 use http::response_mapper::DataResponse;
 use ic_cdk::{caller, trap};
 use ic_cdk_macros::*;
-use service::{device_index_service, email_validation_service};
-use std::time::Duration;
+use service::job_service::{Job, JobKind};
+use service::replication_service::ReplicationStatus;
+use service::{
+    access_point_proof_service, audit_service, backup_service, device_index_service,
+    email_validation_service, job_service, personal_data_service, recovery_service,
+    removal_service, replication_service, security_service,
+};
 
 use crate::application_service::ApplicationService;
 use crate::container::container_wrapper::{
     get_access_point_service, get_account_repo, get_account_service, get_application_service,
     get_persona_service,
 };
+use crate::http::error::IdentityManagerError;
 use crate::http::requests;
-use crate::http::requests::{AccountResponse, Challenge, WalletVariant};
+use crate::http::requests::{
+    AccessPointChallenge, AccountEventsResponse, AccountResponse, AccountsChunk,
+    AccountsExportRequest, AccountsImportRequest, AccountsImportResponse, Challenge,
+    ConfigurationVersionResponse, PersonalDataExport, PrincipalResolution, WalletVariant,
+};
 use crate::http::response_mapper;
 use crate::ic_service::get_caller;
+use crate::mapper::configuration_mapper::{
+    configuration_request_to_configuration, configuration_to_configuration_response,
+    configuration_version_to_response,
+};
 use crate::repository::account_repo::{
     Account, AccountRepo, AccountRepoTrait, ACCOUNTS, PRINCIPAL_INDEX,
 };
 use crate::repository::application_repo::{Application, ApplicationRepo};
+use crate::repository::configuration_history_repo::ConfigurationHistoryRepo;
+use crate::repository::anchor_repo::{AnchorAllocator, AnchorRange, AnchorRepo};
+use crate::repository::openid_repo::{OpenIdProvider, OpenIdRepo};
+use crate::repository::step_up_policy_repo::{
+    StepUpOperation, StepUpPolicy, StepUpPolicyRepo, StepUpRule,
+};
 use crate::repository::persona_repo::PersonaRepo;
-use crate::repository::repo::{AdminRepo, Configuration, ConfigurationRepo, ControllersRepo, CONFIGURATION};
+use crate::repository::repo::{AdminRepo, Configuration, ConfigurationRepo, ControllersRepo};
+use crate::repository::role_repo::{Role, RoleAssignment, RoleRepo};
 use crate::requests::{
     AccessPointRemoveRequest, AccessPointRequest, AccessPointResponse, AccountRequest,
-    ConfigurationRequest, ConfigurationResponse, PersonaResponse,
+    AccountUpdateRequest, ConfigurationRequest, ConfigurationResponse, PersonaResponse,
 };
-use crate::response_mapper::{to_success_response, HttpResponse};
+use crate::response_mapper::{to_result_response, to_success_response, HttpResponse};
 use crate::service::access_point_service::AccessPointServiceTrait;
 use crate::service::account_service::AccountServiceTrait;
 use crate::service::application_service::ApplicationServiceTrait;
-use crate::service::certified_service::{get_witness, CertifiedResponse};
+use crate::service::certified_service::{
+    get_batch_witness, get_witness, CertifiedResolutionsResponse, CertifiedResponse,
+};
 use crate::service::persona_service::PersonaServiceTrait;
-use crate::service::security_service::{secure_2fa, secure_principal_2fa};
-use crate::service::{application_service, ic_service};
+use crate::service::security_service::{
+    access_point_type, secure_2fa, secure_operation, secure_principal_2fa,
+};
+use crate::service::{application_service, ic_service, metrics_service};
 use crate::util::captcha::generate_captcha;
 
 mod container;
@@ -43,11 +73,16 @@ mod service;
 mod structure;
 mod util;
 
+const DEFAULT_CONFIG_HISTORY_PAGE_SIZE: u64 = 20;
+const MAX_PRINCIPALS_PER_RESOLUTION: usize = 100;
+
 /// Invoked when the canister starts.
 /// Initializes the application without parameters and saves the caller to storage.
 #[init]
 async fn init() -> () {
     AdminRepo::save(ic_service::get_caller());
+    replication_service::schedule();
+    job_service::schedule_account_counts();
 }
 
 /// Synchronizes controllers from the management canister.
@@ -61,92 +96,26 @@ async fn sync_controllers() -> Vec<String> {
 
 /// Saves the configuration into storage.
 /// This is necessary for applying the global configuration for the canister.
+/// Fields missing from the request are reset to their defaults.
 /// This method can only be called by an administrator.
 #[update]
 #[admin]
 async fn configure(request: ConfigurationRequest) -> () {
-    let default = ConfigurationRepo::get_default_config();
-    let configuration = Configuration {
-        lambda_url: request.lambda_url.unwrap_or(default.lambda_url),
-        lambda: request.lambda.unwrap_or(default.lambda),
-        token_ttl: if request.token_ttl.is_some() {
-            Duration::from_secs(
-                request
-                    .token_ttl
-                    .expect("The request.token_ttl failed after existence check."),
-            )
-        } else {
-            default.token_ttl
-        },
-        token_refresh_ttl: if request.token_ttl.is_some() {
-            Duration::from_secs(
-                request
-                    .token_refresh_ttl
-                    .expect("The request.token_refresh_ttl failed after existence check."),
-            )
-        } else {
-            default.token_refresh_ttl
-        },
-        whitelisted_phone_numbers: if request.whitelisted_phone_numbers.is_some() {
-            request
-                .whitelisted_phone_numbers
-                .expect("The request.whitelisted_phone_numbers failed after existence check.")
-        } else {
-            default.whitelisted_phone_numbers
-        },
-        heartbeat: if request.heartbeat.is_some() {
-            request.heartbeat
-        } else {
-            default.heartbeat
-        },
-        backup_canister_id: if request.backup_canister_id.is_some() {
-            request.backup_canister_id
-        } else {
-            default.backup_canister_id
-        },
-        ii_canister_id: if request.ii_canister_id.is_some() {
-            request
-                .ii_canister_id
-                .expect("The request.ii_canister_id failed after existence check.")
-        } else {
-            default.ii_canister_id
-        },
-        whitelisted_canisters: if request.whitelisted_canisters.is_some() {
-            request.whitelisted_canisters
-        } else {
-            default.whitelisted_canisters
-        },
-        env: if request.env.is_some() {
-            request.env
-        } else {
-            default.env
-        },
-        git_branch: if request.git_branch.is_some() {
-            request.git_branch
-        } else {
-            default.git_branch
-        },
-        commit_hash: if request.commit_hash.is_some() {
-            request.commit_hash
-        } else {
-            default.commit_hash
-        },
-        operator: if request.operator.is_some() {
-            request
-                .operator
-                .expect("The request.operator failed after existence check.")
-        } else {
-            default.operator
-        },
-        account_creation_paused: request
-            .account_creation_paused
-            .unwrap_or(default.account_creation_paused),
-        max_free_captcha_per_minute: request.max_free_captcha_per_minute.unwrap_or(default.max_free_captcha_per_minute),
-        test_captcha: request.test_captcha.unwrap_or(default.test_captcha),
-    };
-    CONFIGURATION.with(|config| {
-        config.replace(configuration);
-    });
+    let configuration = configuration_request_to_configuration(
+        request,
+        ConfigurationRepo::get_default_config(),
+    );
+    ConfigurationRepo::save(configuration);
+}
+
+/// Updates only the configuration fields set in the request, other fields keep their current values.
+/// This method can only be called by an administrator.
+#[update]
+#[admin]
+async fn patch_config(request: ConfigurationRequest) -> ConfigurationVersionResponse {
+    let configuration =
+        configuration_request_to_configuration(request, ConfigurationRepo::get());
+    configuration_version_to_response(ConfigurationRepo::save(configuration))
 }
 
 /// Returns the configuration to the caller.
@@ -154,32 +123,68 @@ async fn configure(request: ConfigurationRequest) -> () {
 /// The configuration contains no sensitive data and is safe to be public.
 #[query]
 async fn get_config() -> ConfigurationResponse {
-    let config = CONFIGURATION.with(|config| config.borrow().clone());
-    ConfigurationResponse {
-        lambda_url: Some(config.lambda_url),
-        lambda: Some(config.lambda),
-        token_ttl: Some(config.token_ttl.as_secs()),
-        token_refresh_ttl: Some(config.token_refresh_ttl.as_secs()),
-        whitelisted_phone_numbers: Some(config.whitelisted_phone_numbers),
-        heartbeat: config.heartbeat,
-        backup_canister_id: config.backup_canister_id,
-        ii_canister_id: Some(config.ii_canister_id),
-        whitelisted_canisters: config.whitelisted_canisters,
-        env: config.env,
-        git_branch: config.git_branch,
-        commit_hash: config.commit_hash,
-        operator: Some(config.operator),
-        account_creation_paused: Some(config.account_creation_paused),
-        max_free_captcha_per_minute: Some(config.max_free_captcha_per_minute),
-        test_captcha: Some(config.test_captcha),
+    configuration_to_configuration_response(ConfigurationRepo::get())
+}
+
+/// Returns saved configuration versions older than `before`, newest first, with the caller and the changed fields.
+/// This method can only be called by an administrator.
+#[query]
+#[admin]
+async fn get_config_history(
+    before: Option<u64>,
+    limit: Option<u64>,
+) -> Vec<ConfigurationVersionResponse> {
+    let limit = limit.unwrap_or(DEFAULT_CONFIG_HISTORY_PAGE_SIZE) as usize;
+    ConfigurationHistoryRepo::get_history(before, limit)
+        .into_iter()
+        .map(configuration_version_to_response)
+        .collect()
+}
+
+/// Restores the configuration of the given version by saving it as a new version.
+/// This method can only be called by an administrator.
+#[update]
+#[admin]
+async fn rollback_config(version: u64) -> HttpResponse<ConfigurationVersionResponse> {
+    match ConfigurationHistoryRepo::get(version) {
+        None => HttpResponse::from(IdentityManagerError::ConfigurationVersionNotFound),
+        Some(entry) => to_success_response(configuration_version_to_response(
+            ConfigurationRepo::save_version(entry.configuration, Some(version)),
+        )),
     }
 }
 
+/// Grants the role to the principal in addition to the principals that already hold it.
+/// Returns false if the principal was already granted the role.
+/// This method can only be called by an administrator.
+#[update]
+#[requires_role(Admin)]
+async fn grant_role(role: Role, principal: Principal) -> bool {
+    RoleRepo::grant(role, principal, ic_service::get_time())
+}
+
+/// Revokes a role granted through `grant_role`.
+/// Admins saved on init, controllers and the configured operator and lambda are not affected.
+/// This method can only be called by an administrator.
+#[update]
+#[requires_role(Admin)]
+async fn revoke_role(role: Role, principal: Principal) -> bool {
+    RoleRepo::revoke(role, principal)
+}
+
+/// Returns the roles granted through `grant_role`, optionally only for the given role.
+/// Accessible to administrators and operators.
+#[query]
+#[requires_role(Admin, Operator)]
+async fn get_role_assignments(role: Option<Role>) -> Vec<RoleAssignment> {
+    RoleRepo::get_assignments(role)
+}
+
 /// Returns a list of access points to the caller based on their principal.
 #[query]
 async fn read_access_points() -> HttpResponse<Vec<AccessPointResponse>> {
     let access_point_service = get_access_point_service();
-    access_point_service.read_access_points()
+    to_result_response(access_point_service.read_access_points())
 }
 
 /// Returns the access point used by the caller.
@@ -189,14 +194,23 @@ async fn read_access_points() -> HttpResponse<Vec<AccessPointResponse>> {
 #[two_f_a]
 async fn use_access_point(browser: Option<String>) -> HttpResponse<AccessPointResponse> {
     let access_point_service = get_access_point_service();
-    access_point_service.use_access_point(browser)
+    to_result_response(access_point_service.use_access_point(browser))
+}
+
+/// Issues a nonce to be signed by the key of a new access point, see `AccessPointProof`.
+/// The nonce expires after 5 minutes and is consumed by the next `create_access_point` call.
+#[update]
+#[authenticated]
+async fn get_access_point_challenge() -> AccessPointChallenge {
+    access_point_proof_service::issue_challenge().await
 }
 
 /// Creates a new access point for the caller.
 /// This is necessary when a user adds a new device for signing in.
 /// Two-factor authentication (2FA) is required if enabled (via passkey).
+/// Keys other than the caller need a proof that they agreed to be added to an NFID or OpenID account.
 #[update]
-#[two_f_a]
+#[two_f_a(CreateAccessPoint, target = Some(access_point_request.device_type))]
 #[paused]
 async fn create_access_point(
     access_point_request: AccessPointRequest,
@@ -205,44 +219,67 @@ async fn create_access_point(
     let response = access_point_service
         .create_access_point(access_point_request.clone())
         .await;
-    response
+    to_result_response(response)
 }
 
 /// Updates the existing access point for the caller.
 /// This is necessary when a user updates a device.
 /// Two-factor authentication (2FA) is required if enabled (via passkey).
 #[update]
-#[two_f_a]
+#[two_f_a(UpdateAccessPoint, target = access_point_type(&access_point.pub_key))]
 #[paused]
 async fn update_access_point(
     access_point: AccessPointRequest,
 ) -> HttpResponse<Vec<AccessPointResponse>> {
     let access_point_service = get_access_point_service();
-    access_point_service.update_access_point(access_point.clone())
+    to_result_response(access_point_service.update_access_point(access_point.clone()))
+}
+
+/// Updates the name and email address of the caller's account.
+/// A new email must be validated by the lambda first; the validation is consumed and the email access point is replaced.
+/// Two-factor authentication (2FA) is required if enabled (via passkey).
+#[update]
+#[two_f_a(UpdateAccount)]
+async fn update_account(
+    account_update_request: AccountUpdateRequest,
+) -> HttpResponse<AccountResponse> {
+    let mut account_service = get_account_service();
+    to_result_response(account_service.update_account(account_update_request))
 }
 
 /// Removes the existing access point for the caller.
 /// This is necessary when a user removes a device.
 /// Two-factor authentication (2FA) is required if enabled (via passkey).
 #[update]
-#[two_f_a]
+#[two_f_a(RemoveAccessPoint, target = access_point_type(&access_point.pub_key))]
 async fn remove_access_point(
     access_point: AccessPointRemoveRequest,
 ) -> HttpResponse<Vec<AccessPointResponse>> {
     let access_point_service = get_access_point_service();
-    access_point_service.remove_access_point(access_point)
+    to_result_response(access_point_service.remove_access_point(access_point))
+}
+
+/// Removes every access point of the caller's account except the caller's own and the recovery phrase.
+/// This is necessary when a user loses a device and wants to sign out everywhere at once.
+/// Two-factor authentication (2FA) is required if enabled (via passkey).
+#[update]
+#[two_f_a(RemoveAccessPoint)]
+async fn remove_other_access_points() -> HttpResponse<Vec<AccessPointResponse>> {
+    let access_point_service = get_access_point_service();
+    to_result_response(access_point_service.remove_other_access_points())
 }
 
 /// Allows the user to create an account.
 /// This is necessary for users to register and subsequently add their access points.
 /// Two-factor authentication (2FA) cannot be enabled before the actual registration process.
 #[update]
+#[authenticated]
 #[paused]
 async fn create_account(account_request: AccountRequest) -> HttpResponse<AccountResponse> {
 
     let mut account_service = get_account_service();
     let response = account_service.create_account(account_request).await;
-    response
+    to_result_response(response)
 }
 
 /// Returns account information for a specified anchor.
@@ -260,7 +297,7 @@ async fn get_account_by_anchor(
         Some(x) => x,
     };
 
-    account_service.get_account_by_anchor(anchor, wv)
+    to_result_response(account_service.get_account_by_anchor(anchor, wv))
 }
 
 /// Pauses account creation to prevent new accounts from being created.
@@ -274,6 +311,78 @@ async fn pause_account_creation(pause: bool) {
     });
 }
 
+/// Registers an OpenID Connect provider, replacing the stored one with the same issuer.
+/// This is how the JWKS keys are rotated.
+/// Accessible only to operators.
+#[update]
+#[operator]
+async fn set_openid_provider(provider: OpenIdProvider) {
+    OpenIdRepo::save_provider(provider);
+}
+
+/// Removes the OpenID Connect provider, its ID tokens are no longer accepted.
+/// Accessible only to operators.
+#[update]
+#[operator]
+async fn remove_openid_provider(issuer: String) -> bool {
+    OpenIdRepo::remove_provider(&issuer)
+}
+
+/// Returns the OpenID Connect providers whose ID tokens are accepted.
+#[query]
+async fn get_openid_providers() -> Vec<OpenIdProvider> {
+    OpenIdRepo::get_providers()
+}
+
+/// Sets the ranges new anchors of the wallet are taken from and the blocks inside them kept for partners or test environments.
+/// Anchors are handed out in increasing order, so ranges below the last handed out anchor are not used.
+/// Accessible only to operators.
+#[update]
+#[operator]
+async fn set_anchor_allocator(
+    wallet: WalletVariant,
+    ranges: Vec<AnchorRange>,
+    reserved: Vec<AnchorRange>,
+) -> HttpResponse<AnchorAllocator> {
+    to_result_response(AnchorRepo::configure(wallet, ranges, reserved))
+}
+
+/// Replaces the step-up policy, the access point types allowed to perform each sensitive operation.
+/// Accessible only to operators.
+#[update]
+#[operator]
+async fn set_step_up_policy(policy: StepUpPolicy) {
+    StepUpPolicyRepo::save(policy);
+}
+
+/// Returns the step-up policy every account is subject to.
+#[query]
+async fn get_step_up_policy() -> StepUpPolicy {
+    StepUpPolicyRepo::get()
+}
+
+/// Returns the step-up rules the caller added to their account.
+#[query]
+async fn get_account_step_up_rules() -> HttpResponse<Vec<StepUpRule>> {
+    to_result_response(security_service::get_account_step_up_rules())
+}
+
+/// Replaces the step-up rules of the caller's account.
+/// They apply on top of the step-up policy, so they can only restrict the access points allowed further.
+#[update]
+#[two_f_a(UpdateStepUpRules)]
+async fn set_account_step_up_rules(rules: Vec<StepUpRule>) -> HttpResponse<Vec<StepUpRule>> {
+    to_result_response(security_service::set_account_step_up_rules(rules))
+}
+
+/// Returns the anchor ranges, reserved blocks and counters of every wallet.
+/// Accessible only to operators.
+#[query]
+#[operator]
+async fn get_anchor_allocators() -> Vec<AnchorAllocator> {
+    AnchorRepo::get_all()
+}
+
 /// Adds the principal ID and email address to temporary storage for email validation during account creation.
 /// The TTL hashmap is utilized to keep the storage efficient.
 /// Accessible only to lambda users.
@@ -296,7 +405,7 @@ async fn add_email_and_principal_for_create_account_validation(
 async fn get_account_by_principal(princ: String) -> HttpResponse<AccountResponse> {
     let mut account_service = get_account_service();
 
-    account_service.get_account_by_principal(princ)
+    to_result_response(account_service.get_account_by_principal(princ))
 }
 
 /// Returns the root principal ID based on any of the access point principal IDs.
@@ -317,14 +426,22 @@ async fn get_anchor_by_principal(princ: String) -> Option<u64> {
     account_service.get_anchor_by_principal(princ)
 }
 
+/// Returns the root ID, anchor and wallet for each of the access point principal IDs, in the given order.
+/// This is necessary for canisters resolving many principals at once instead of calling `get_root_by_principal` for each.
+/// Traps if more than 100 principals are requested.
+#[query]
+async fn get_roots_by_principals(principals: Vec<String>) -> Vec<PrincipalResolution> {
+    resolve_principals(principals)
+}
+
 /// Enables or disables the two-factor authentication (2FA) sign-in option (passkey only).
 /// Once enabled, the user cannot sign in using any other access point, including Web2 devices.
 /// Two-factor authentication (2FA) is required if enabled (via passkey).
 #[update]
-#[two_f_a]
-async fn update_2fa(state: bool) -> AccountResponse {
+#[two_f_a(Update2fa)]
+async fn update_2fa(state: bool) -> HttpResponse<AccountResponse> {
     let mut account_service = get_account_service();
-    account_service.update_2fa(state)
+    to_result_response(account_service.update_2fa(state))
 }
 
 /// Returns the account associated with the caller.
@@ -332,17 +449,75 @@ async fn update_2fa(state: bool) -> AccountResponse {
 #[query]
 async fn get_account() -> HttpResponse<AccountResponse> {
     let mut account_service = get_account_service();
-    account_service.get_account_response()
+    to_result_response(account_service.get_account_response())
+}
+
+/// Returns everything stored about the caller's account as one versioned document:
+/// the account, its access points and personas with their dates, pending temporary keys and security events.
+/// This is necessary for answering data subject access requests.
+#[query]
+async fn export_personal_data() -> HttpResponse<PersonalDataExport> {
+    personal_data_service::export_personal_data()
 }
 
 /// Removes the user account associated with the caller.
 /// If the account has a passkey with 2FA enabled, the caller must use a passkey access point.
 /// Otherwise, if a seed phrase (recovery) access point exists, the caller must use it.
 /// If neither condition applies, any access point is allowed.
+/// With a configured grace period the account is hidden and only purged once the period ends.
 #[update]
+#[two_f_a(RemoveAccount)]
 async fn remove_account() -> HttpResponse<bool> {
     let mut account_service = get_account_service();
-    account_service.remove_account()
+    to_result_response(account_service.remove_account())
+}
+
+/// Cancels the pending removal of the caller's account during the grace period.
+/// The same access point rules as for `remove_account` apply.
+#[update]
+#[authenticated]
+async fn cancel_remove_account() -> HttpResponse<AccountResponse> {
+    removal_service::cancel_removal()
+}
+
+/// Starts a time-locked recovery of the caller's account with a new access point.
+/// The caller must be the recovery phrase access point of an NFID account.
+/// Once the configured delay passes, the access point is added and 2FA is reset, unless a passkey cancels the recovery.
+#[update]
+#[authenticated]
+async fn initiate_recovery(access_point: AccessPointRequest) -> HttpResponse<AccountResponse> {
+    recovery_service::initiate_recovery(access_point)
+}
+
+/// Cancels the pending recovery of the caller's account.
+/// Only a passkey access point of the account is allowed to cancel it.
+#[update]
+#[authenticated]
+async fn cancel_recovery() -> HttpResponse<AccountResponse> {
+    recovery_service::cancel_recovery()
+}
+
+/// Returns the security events of the caller's account, newest first.
+/// Pass the returned `next` cursor as `before` to read older events.
+#[query]
+async fn get_account_events(
+    before: Option<u64>,
+    limit: Option<u64>,
+) -> HttpResponse<AccountEventsResponse> {
+    audit_service::get_account_events(before, limit)
+}
+
+/// Returns the security events of the account for any of its access point principals or its root principal.
+/// This is necessary for investigating support requests.
+/// Accessible only to operators.
+#[query]
+#[operator]
+async fn get_account_events_by_principal(
+    princ: String,
+    before: Option<u64>,
+    limit: Option<u64>,
+) -> HttpResponse<AccountEventsResponse> {
+    audit_service::get_account_events_by_principal(princ, before, limit)
 }
 
 /// Returns a list of personas.
@@ -387,6 +562,24 @@ async fn get_all_accounts_json(from: u32, mut to: u32) -> String {
     serde_json::to_string(&b).expect("Failed to serialize the response to JSON")
 }
 
+/// Returns the next chunk of accounts, starting after the cursor of the previous chunk.
+/// The chunk checksum chains all previous chunks, so the last one verifies the whole export.
+/// Accessible only to operators.
+#[query]
+#[operator]
+async fn export_accounts(request: AccountsExportRequest) -> AccountsChunk {
+    backup_service::export_accounts(request)
+}
+
+/// Restores an exported chunk of accounts, rebuilding the access point index and the certified tree for them.
+/// Chunks must be imported in export order, a chunk that does not continue the checksum chain is rejected.
+/// Accessible only to operators.
+#[update]
+#[operator]
+async fn import_accounts(request: AccountsImportRequest) -> HttpResponse<AccountsImportResponse> {
+    to_result_response(backup_service::import_accounts(request))
+}
+
 /// Returns the number of created anchors.
 /// This is necessary for backup purposes.
 /// Accessible only to operators.
@@ -437,14 +630,61 @@ async fn save_temp_stack_to_rebuild_device_index() -> String {
     device_index_service::save_temp_stack()
 }
 
+/// Starts a background job, or resumes it from its stored progress if it was paused.
+/// A running job is scheduled again, which picks up a job whose last batch trapped.
+/// Jobs run in instruction-bounded batches on timers, so large rebuilds no longer need repeated manual calls.
+/// Accessible only to operators.
+#[update]
+#[operator]
+async fn start_job(kind: JobKind) -> Job {
+    job_service::start(kind)
+}
+
+/// Pauses a running background job after its current batch.
+/// Accessible only to operators.
+#[update]
+#[operator]
+async fn pause_job(kind: JobKind) -> Option<Job> {
+    job_service::pause(kind)
+}
+
+/// Returns the state and progress of all background jobs that have ever been started.
+/// Accessible only to operators.
+#[query]
+#[operator]
+async fn get_jobs() -> Vec<Job> {
+    job_service::get_jobs()
+}
+
+/// Returns how far the backup canister lags behind the account change log.
+/// Accessible only to operators.
+#[query]
+#[operator]
+async fn get_replication_status() -> ReplicationStatus {
+    replication_service::get_status()
+}
+
 /// Retrieves the user recovery phrase from Internet Identity.
 /// This is necessary in the event of an inconsistency between their recovery phrase storage and ours.
 #[update]
+#[authenticated]
 async fn sync_recovery_phrase_from_internet_identity(anchor: u64) -> HttpResponse<AccountResponse> {
     let account_service = get_account_service();
-    account_service
-        .sync_recovery_phrase_from_internet_identity(anchor)
-        .await
+    to_result_response(
+        account_service
+            .sync_recovery_phrase_from_internet_identity(anchor)
+            .await,
+    )
+}
+
+/// Moves the caller's Internet Identity account to a new NFID anchor, so it no longer depends on the Internet Identity `lookup`.
+/// The caller must be a device of the Internet Identity anchor, its recovery phrase is carried over.
+/// The root principal stays the same, so derived delegations do not change, and the old anchor is kept for audit.
+#[update]
+#[authenticated]
+async fn migrate_to_nfid() -> HttpResponse<AccountResponse> {
+    let mut account_service = get_account_service();
+    to_result_response(account_service.migrate_to_nfid().await)
 }
 
 /// Returns a certified response.
@@ -470,21 +710,105 @@ async fn get_root_certified() -> CertifiedResponse {
     }
 }
 
+/// Returns a certified response for each of the access point principal IDs with a single witness.
+/// The witness certifies the root IDs and anchors of registered principals under the `principals` label and the absence of unregistered ones, wallets are not certified.
+/// Verifiable without a call to this canister with the `identity_certification` library.
+/// Traps if more than 100 principals are requested.
+#[query]
+async fn get_roots_certified(principals: Vec<String>) -> CertifiedResolutionsResponse {
+    let response = resolve_principals(principals.clone());
+    let witness = get_batch_witness(&principals).unwrap_or_default();
+    let certificate = ic_cdk::api::data_certificate().expect("No data certificate available");
+    CertifiedResolutionsResponse {
+        response,
+        certificate,
+        witness,
+    }
+}
+
+fn resolve_principals(principals: Vec<String>) -> Vec<PrincipalResolution> {
+    if principals.len() > MAX_PRINCIPALS_PER_RESOLUTION {
+        trap("Too many principals requested");
+    }
+    principals.iter().for_each(secure_principal_2fa);
+    let mut account_service = get_account_service();
+    account_service.resolve_principals(principals)
+}
+
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+/// The metrics are aggregated counts and safe to be public.
+#[query]
+fn http_request(request: HttpRequest) -> canister_metrics::HttpResponse {
+    canister_metrics::http_request(
+        &request,
+        ic_service::get_time(),
+        metrics_service::canister_stats(),
+        metrics_service::encode_metrics,
+    )
+}
+
 #[update]
 pub async fn get_captcha() -> Challenge {
     generate_captcha().await
 }
 
-/// Applies changes before the canister upgrade.
-#[pre_upgrade]
-fn pre_upgrade() {
-    repository::repo::pre_upgrade()
-}
-
 /// Applies changes after the canister upgrade.
+/// The certified tree is rebuilt by a background job.
 #[post_upgrade]
 fn post_upgrade() {
-    repository::repo::post_upgrade()
+    repository::repo::post_upgrade();
+    job_service::post_upgrade();
+    recovery_service::post_upgrade();
+    removal_service::post_upgrade();
+    replication_service::schedule();
 }
 
+// Every endpoint with a guard attribute, in the order they are declared.
+generate_inspect_message!(
+    configure,
+    patch_config,
+    get_config_history,
+    rollback_config,
+    grant_role,
+    revoke_role,
+    get_role_assignments,
+    use_access_point,
+    get_access_point_challenge,
+    create_access_point,
+    update_access_point,
+    update_account,
+    remove_access_point,
+    remove_other_access_points,
+    create_account,
+    get_account_by_anchor,
+    pause_account_creation,
+    set_openid_provider,
+    remove_openid_provider,
+    set_anchor_allocator,
+    set_step_up_policy,
+    set_account_step_up_rules,
+    get_anchor_allocators,
+    add_email_and_principal_for_create_account_validation,
+    get_account_by_principal,
+    update_2fa,
+    remove_account,
+    cancel_remove_account,
+    initiate_recovery,
+    cancel_recovery,
+    get_account_events_by_principal,
+    get_all_accounts_json,
+    export_accounts,
+    import_accounts,
+    count_anchors,
+    rebuild_index,
+    get_remaining_size_after_rebuild_device_index_slice_from_temp_stack,
+    save_temp_stack_to_rebuild_device_index,
+    start_job,
+    pause_job,
+    get_jobs,
+    get_replication_status,
+    sync_recovery_phrase_from_internet_identity,
+    migrate_to_nfid,
+);
+
 fn main() {}
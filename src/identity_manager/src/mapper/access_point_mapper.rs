@@ -31,6 +31,28 @@ pub fn access_point_request_to_access_point(request: AccessPointRequest) -> Acce
     }
 }
 
+/// An ID token alone proves an OpenID access point, so it never gets a privileged device type.
+pub fn openid_access_point_request_to_access_point(request: AccessPointRequest) -> AccessPoint {
+    AccessPoint {
+        device_type: DeviceType::Unknown,
+        ..access_point_request_to_access_point(request)
+    }
+}
+
+pub fn email_principal_to_access_point(principal_id: String) -> AccessPoint {
+    let basic = BasicEntity::new();
+    AccessPoint {
+        principal_id,
+        credential_id: None,
+        icon: Some("email".to_string()),
+        device: Some("Email".to_string()),
+        browser: None,
+        last_used: None,
+        device_type: DeviceType::Email,
+        base_fields: basic,
+    }
+}
+
 pub fn recovery_device_data_to_access_point(device: DeviceData) -> AccessPoint {
     let basic = BasicEntity::new();
     
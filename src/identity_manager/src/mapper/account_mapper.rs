@@ -1,5 +1,5 @@
 use crate::mapper::persona_mapper::persona_to_persona_response;
-use crate::http::requests::{AccountResponse, WalletVariant};
+use crate::http::requests::{AccountResponse, PendingRecoveryResponse, WalletVariant};
 use crate::{AccountRequest};
 use crate::mapper::access_point_mapper::access_point_to_access_point_response;
 use crate::repository::account_repo::Account;
@@ -23,6 +23,12 @@ pub fn account_to_account_response(account: Account) -> AccountResponse {
         wallet: account.wallet,
         is2fa_enabled: account.is2fa_enabled,
         email: account.email,
+        pending_recovery: account.pending_recovery.map(|pending| PendingRecoveryResponse {
+            access_point: access_point_to_access_point_response(pending.access_point),
+            initiated_at: pending.initiated_at,
+            executable_at: pending.executable_at,
+        }),
+        email_verified_at: account.email_verified_at,
     }
 }
 
@@ -44,5 +50,11 @@ pub fn account_request_to_account(account_request: AccountRequest) -> Account {
         },
         is2fa_enabled: false,
         email: account_request.email,
+        pending_recovery: None,
+        email_verified_at: None,
+        openid_subject: None,
+        pending_removal: None,
+        ii_anchor: None,
+        step_up_rules: None,
     }
 }
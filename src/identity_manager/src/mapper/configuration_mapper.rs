@@ -0,0 +1,96 @@
+use std::time::Duration;
+
+use crate::http::requests::{ConfigurationRequest, ConfigurationResponse, ConfigurationVersionResponse};
+use crate::repository::configuration_history_repo::ConfigurationVersion;
+use crate::repository::repo::Configuration;
+
+/// Applies every field set in the request on top of `base`, fields left empty keep the value of `base`.
+pub fn configuration_request_to_configuration(
+    request: ConfigurationRequest,
+    base: Configuration,
+) -> Configuration {
+    Configuration {
+        lambda_url: request.lambda_url.unwrap_or(base.lambda_url),
+        lambda: request.lambda.unwrap_or(base.lambda),
+        token_ttl: request
+            .token_ttl
+            .map(Duration::from_secs)
+            .unwrap_or(base.token_ttl),
+        token_refresh_ttl: request
+            .token_refresh_ttl
+            .map(Duration::from_secs)
+            .unwrap_or(base.token_refresh_ttl),
+        whitelisted_phone_numbers: request
+            .whitelisted_phone_numbers
+            .unwrap_or(base.whitelisted_phone_numbers),
+        heartbeat: request.heartbeat.or(base.heartbeat),
+        backup_canister_id: request.backup_canister_id.or(base.backup_canister_id),
+        ii_canister_id: request.ii_canister_id.unwrap_or(base.ii_canister_id),
+        whitelisted_canisters: request.whitelisted_canisters.or(base.whitelisted_canisters),
+        env: request.env.or(base.env),
+        git_branch: request.git_branch.or(base.git_branch),
+        commit_hash: request.commit_hash.or(base.commit_hash),
+        operator: request.operator.unwrap_or(base.operator),
+        account_creation_paused: request
+            .account_creation_paused
+            .unwrap_or(base.account_creation_paused),
+        max_free_captcha_per_minute: request
+            .max_free_captcha_per_minute
+            .unwrap_or(base.max_free_captcha_per_minute),
+        test_captcha: request.test_captcha.unwrap_or(base.test_captcha),
+        recovery_delay: request
+            .recovery_delay
+            .map(Duration::from_secs)
+            .or(base.recovery_delay),
+        challenge_type: request.challenge_type.or(base.challenge_type),
+        proof_of_work_difficulty: request
+            .proof_of_work_difficulty
+            .or(base.proof_of_work_difficulty),
+        max_challenge_attempts: request
+            .max_challenge_attempts
+            .or(base.max_challenge_attempts),
+        account_removal_grace_period: request
+            .account_removal_grace_period
+            .map(Duration::from_secs)
+            .or(base.account_removal_grace_period),
+    }
+}
+
+pub fn configuration_to_configuration_response(config: Configuration) -> ConfigurationResponse {
+    ConfigurationResponse {
+        lambda_url: Some(config.lambda_url),
+        lambda: Some(config.lambda),
+        token_ttl: Some(config.token_ttl.as_secs()),
+        token_refresh_ttl: Some(config.token_refresh_ttl.as_secs()),
+        whitelisted_phone_numbers: Some(config.whitelisted_phone_numbers),
+        heartbeat: config.heartbeat,
+        backup_canister_id: config.backup_canister_id,
+        ii_canister_id: Some(config.ii_canister_id),
+        whitelisted_canisters: config.whitelisted_canisters,
+        env: config.env,
+        git_branch: config.git_branch,
+        commit_hash: config.commit_hash,
+        operator: Some(config.operator),
+        account_creation_paused: Some(config.account_creation_paused),
+        max_free_captcha_per_minute: Some(config.max_free_captcha_per_minute),
+        test_captcha: Some(config.test_captcha),
+        recovery_delay: config.recovery_delay.map(|delay| delay.as_secs()),
+        challenge_type: config.challenge_type,
+        proof_of_work_difficulty: config.proof_of_work_difficulty,
+        max_challenge_attempts: config.max_challenge_attempts,
+        account_removal_grace_period: config
+            .account_removal_grace_period
+            .map(|period| period.as_secs()),
+    }
+}
+
+pub fn configuration_version_to_response(version: ConfigurationVersion) -> ConfigurationVersionResponse {
+    ConfigurationVersionResponse {
+        version: version.version,
+        caller: version.caller,
+        timestamp: version.timestamp,
+        changes: version.changes,
+        rollback_of: version.rollback_of,
+        configuration: configuration_to_configuration_response(version.configuration),
+    }
+}
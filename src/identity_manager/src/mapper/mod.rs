@@ -1,3 +1,5 @@
 pub mod account_mapper;
+pub mod configuration_mapper;
 pub mod persona_mapper;
 pub mod access_point_mapper;
+pub mod personal_data_mapper;
@@ -0,0 +1,56 @@
+use crate::http::requests::{
+    PendingRecoveryResponse, PersonalAccessPointData, PersonalAccountData, PersonalPersonaData,
+};
+use crate::mapper::access_point_mapper::access_point_to_access_point_response;
+use crate::repository::access_point_repo::AccessPoint;
+use crate::repository::account_repo::Account;
+use crate::repository::persona_repo::Persona;
+
+#[allow(deprecated)]
+pub fn account_to_personal_account_data(account: &Account) -> PersonalAccountData {
+    PersonalAccountData {
+        principal_id: account.principal_id.clone(),
+        anchor: account.anchor,
+        wallet: account.wallet,
+        name: account.name.clone(),
+        email: account.email.clone(),
+        email_verified_at: account.email_verified_at,
+        phone_number: account.phone_number.clone(),
+        phone_number_sha2: account.phone_number_sha2.clone(),
+        is2fa_enabled: account.is2fa_enabled,
+        openid_subject: account.openid_subject.clone(),
+        pending_recovery: account.pending_recovery.clone().map(|pending| PendingRecoveryResponse {
+            access_point: access_point_to_access_point_response(pending.access_point),
+            initiated_at: pending.initiated_at,
+            executable_at: pending.executable_at,
+        }),
+        ii_anchor: account.ii_anchor,
+        created_date: account.base_fields.get_created_date(),
+        modified_date: account.base_fields.get_modified_date(),
+    }
+}
+
+pub fn access_point_to_personal_access_point_data(access_point: AccessPoint) -> PersonalAccessPointData {
+    PersonalAccessPointData {
+        principal_id: access_point.principal_id,
+        credential_id: access_point.credential_id,
+        icon: access_point.icon,
+        device: access_point.device,
+        browser: access_point.browser,
+        device_type: access_point.device_type,
+        last_used: access_point.last_used,
+        created_date: access_point.base_fields.get_created_date(),
+        modified_date: access_point.base_fields.get_modified_date(),
+    }
+}
+
+pub fn persona_to_personal_persona_data(persona: Persona) -> PersonalPersonaData {
+    PersonalPersonaData {
+        domain: persona.domain,
+        persona_id: persona.persona_id,
+        persona_name: persona.persona_name,
+        domain_certified: persona.domain_certified,
+        created_date: persona.base_fields.get_created_date(),
+        modified_date: persona.base_fields.get_modified_date(),
+    }
+}
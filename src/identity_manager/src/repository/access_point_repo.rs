@@ -1,6 +1,7 @@
+use crate::http::error::IdentityManagerError;
 use crate::http::requests::{DeviceType, WalletVariant};
 use crate::repository::account_repo::{Account, AccountRepoTrait};
-use crate::repository::repo::BasicEntity;
+use crate::repository::repo::{BasicEntity, TEMP_KEYS};
 use crate::service::certified_service::update_certify_keys;
 use crate::AccountRepo;
 use candid::{CandidType, Deserialize};
@@ -34,16 +35,20 @@ impl Hash for AccessPoint {
 
 pub trait AccessPointRepoTrait {
     fn get_access_points(&self) -> Option<HashSet<AccessPoint>>;
-    fn get_wallet(&self) -> WalletVariant;
+    fn get_wallet(&self) -> Result<WalletVariant, IdentityManagerError>;
     fn get_access_points_by_principal(&self, princ: String) -> Option<HashSet<AccessPoint>>;
     fn use_access_point(
         &self,
         ap_principal: String,
         time: u64,
         browser: Option<String>,
-    ) -> Option<AccessPoint>;
-    fn store_access_points(&self, access_points: HashSet<AccessPoint>) -> Option<Account>;
+    ) -> Result<AccessPoint, IdentityManagerError>;
+    fn store_access_points(
+        &self,
+        access_points: HashSet<AccessPoint>,
+    ) -> Result<Account, IdentityManagerError>;
     fn remove_ap_index(&self, access_point: String);
+    fn remove_ap_indexes(&self, access_points: Vec<String>);
     fn store_access_points_by_principal(
         &self,
         access_points: HashSet<AccessPoint>,
@@ -69,8 +74,11 @@ impl AccessPointRepoTrait for AccessPointRepo {
             .map(|x| x.access_points.clone()) //todo &
     }
 
-    fn get_wallet(&self) -> WalletVariant {
-        self.account_repo.get_account().expect("Failed to retrieve the account from the repository.").wallet
+    fn get_wallet(&self) -> Result<WalletVariant, IdentityManagerError> {
+        self.account_repo
+            .get_account()
+            .map(|x| x.wallet)
+            .ok_or(IdentityManagerError::AccountNotFound)
     }
 
     fn get_access_points_by_principal(&self, princ: String) -> Option<HashSet<AccessPoint>> {
@@ -84,37 +92,52 @@ impl AccessPointRepoTrait for AccessPointRepo {
         ap_principal: String,
         time: u64,
         browser: Option<String>,
-    ) -> Option<AccessPoint> {
-        let mut points = self.get_access_points().expect("Failed to retrieve access points.");
-        let updated = points
-            .clone()
-            .into_iter()
-            .find(|l| l.principal_id == ap_principal);
-        match updated {
-            None => None,
-            Some(mut ap) => {
-                ap.last_used = Some(time);
-                ap.browser = browser.or(ap.browser);
-                points.replace(ap.clone());
-                self.store_access_points(points);
-                Some(ap)
-            }
-        }
+    ) -> Result<AccessPoint, IdentityManagerError> {
+        let mut points = self
+            .get_access_points()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        let mut ap = points
+            .iter()
+            .find(|l| l.principal_id == ap_principal)
+            .cloned()
+            .ok_or(IdentityManagerError::AccessPointNotFound)?;
+        ap.last_used = Some(time);
+        ap.browser = browser.or(ap.browser);
+        points.replace(ap.clone());
+        self.store_access_points(points)?;
+        Ok(ap)
     }
 
-    fn store_access_points(&self, access_points: HashSet<AccessPoint>) -> Option<Account> {
-        let mut acc = self.account_repo.get_account()
-            .expect("Failed to retrieve the account from the account repository.")
-            .clone();
-        acc.access_points = access_points.clone();
-        
-        self.account_repo.store_account(acc)
+    fn store_access_points(
+        &self,
+        access_points: HashSet<AccessPoint>,
+    ) -> Result<Account, IdentityManagerError> {
+        let mut acc = self
+            .account_repo
+            .get_account()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        acc.access_points = access_points;
+        self.account_repo
+            .store_account(acc)
+            .ok_or(IdentityManagerError::AccountNotFound)
     }
 
     fn remove_ap_index(&self, access_point: String) {
         self.account_repo.remove_account_index(access_point);
     }
 
+    fn remove_ap_indexes(&self, access_points: Vec<String>) {
+        TEMP_KEYS.with(|keys| {
+            let mut keys = keys.borrow_mut();
+            access_points.iter().for_each(|ap| {
+                keys.remove(ap);
+            });
+        });
+        access_points
+            .into_iter()
+            .for_each(|ap| self.account_repo.remove_account_index(ap));
+    }
+
     fn store_access_points_by_principal(
         &self,
         access_points: HashSet<AccessPoint>,
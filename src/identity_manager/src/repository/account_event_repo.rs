@@ -0,0 +1,147 @@
+use crate::http::requests::DeviceType;
+use crate::repository::memory::{
+    candid_storable, get_memory, Memory, ACCOUNT_EVENTS_MEMORY_ID,
+    ACCOUNT_EVENT_SEQUENCES_MEMORY_ID,
+};
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{StableBTreeMap, Storable};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::convert::TryInto;
+
+/// Older events of an account are dropped once it has more than this many.
+/// Sign-ins are dropped first, so they never push out security events.
+pub const MAX_EVENTS_PER_ACCOUNT: u64 = 100;
+
+thread_local! {
+    static ACCOUNT_EVENTS: RefCell<StableBTreeMap<AccountEventKey, AccountEvent, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(ACCOUNT_EVENTS_MEMORY_ID)));
+    /// Root principal to the `(first, next)` sequence numbers of its stored events.
+    static ACCOUNT_EVENT_SEQUENCES: RefCell<StableBTreeMap<String, (u64, u64), Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(ACCOUNT_EVENT_SEQUENCES_MEMORY_ID)));
+}
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum AccountEventType {
+    UseAccessPoint,
+    CreateAccessPoint,
+    UpdateAccessPoint,
+    RemoveAccessPoint,
+    Update2fa,
+    RemoveAccount,
+    CancelRemoveAccount,
+    InitiateRecovery,
+    CancelRecovery,
+    CompleteRecovery,
+    UpdateEmail,
+    MigrateToNfid,
+    UpdateStepUpRules,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct AccountEvent {
+    pub seq: u64,
+    pub event_type: AccountEventType,
+    /// The access point principal that made the call, empty for events triggered by the canister itself.
+    pub principal_id: Option<String>,
+    pub device_type: Option<DeviceType>,
+    pub browser: Option<String>,
+    /// The access point the event was applied to, if it differs from the caller.
+    pub target_principal_id: Option<String>,
+    pub timestamp: u64,
+}
+
+candid_storable!(AccountEvent);
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct AccountEventKey {
+    root: String,
+    seq: u64,
+}
+
+impl Storable for AccountEventKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = self.root.as_bytes().to_vec();
+        bytes.extend_from_slice(&self.seq.to_be_bytes());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        let (root, seq) = bytes.split_at(bytes.len() - 8);
+        AccountEventKey {
+            root: String::from_utf8(root.to_vec()).expect("Failed to decode the event key root."),
+            seq: u64::from_be_bytes(seq.try_into().expect("Failed to decode the event key sequence.")),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 128,
+        is_fixed_size: false,
+    };
+}
+
+pub trait AccountEventRepoTrait {
+    fn append(&self, root: String, event: AccountEvent) -> AccountEvent;
+    fn get_events(&self, root: String, before: Option<u64>, limit: u64) -> (Vec<AccountEvent>, Option<u64>);
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct AccountEventRepo {}
+
+impl AccountEventRepoTrait for AccountEventRepo {
+    fn append(&self, root: String, event: AccountEvent) -> AccountEvent {
+        ACCOUNT_EVENT_SEQUENCES.with(|sequences| {
+            ACCOUNT_EVENTS.with(|events| {
+                let mut sequences = sequences.borrow_mut();
+                let mut events = events.borrow_mut();
+                let (first, next) = sequences.get(&root).unwrap_or((0, 0));
+                let event = AccountEvent { seq: next, ..event };
+                events.insert(AccountEventKey { root: root.clone(), seq: next }, event.clone());
+                let mut stored: Vec<(u64, AccountEventType)> = events
+                    .range(AccountEventKey { root: root.clone(), seq: first }..)
+                    .take_while(|(key, _)| key.root == root)
+                    .map(|(key, event)| (key.seq, event.event_type))
+                    .collect();
+                while stored.len() as u64 > MAX_EVENTS_PER_ACCOUNT {
+                    let evicted = stored
+                        .iter()
+                        .position(|(_, event_type)| *event_type == AccountEventType::UseAccessPoint)
+                        .unwrap_or(0);
+                    let (seq, _) = stored.remove(evicted);
+                    events.remove(&AccountEventKey { root: root.clone(), seq });
+                }
+                let first = stored.first().map_or(next + 1, |(seq, _)| *seq);
+                sequences.insert(root, (first, next + 1));
+                event
+            })
+        })
+    }
+
+    /// Returns up to `limit` events older than `before`, newest first, and the cursor of the next page.
+    /// Only stored events are visited, so the gaps evicted sign-ins leave in the sequence cost nothing.
+    fn get_events(&self, root: String, before: Option<u64>, limit: u64) -> (Vec<AccountEvent>, Option<u64>) {
+        let (first, next) = ACCOUNT_EVENT_SEQUENCES
+            .with(|sequences| sequences.borrow().get(&root))
+            .unwrap_or((0, 0));
+        let end = before.map_or(next, |before| before.min(next)).max(first);
+        let page: Vec<AccountEvent> = ACCOUNT_EVENTS.with(|events| {
+            let events = events.borrow();
+            events
+                .range(
+                    AccountEventKey { root: root.clone(), seq: first }
+                        ..AccountEventKey { root: root.clone(), seq: end },
+                )
+                .rev()
+                .take(limit as usize)
+                .map(|(_, event)| event)
+                .collect()
+        });
+        let cursor = match page.last() {
+            Some(oldest) if page.len() as u64 == limit && oldest.seq > first => Some(oldest.seq),
+            _ => None,
+        };
+        (page, cursor)
+    }
+}
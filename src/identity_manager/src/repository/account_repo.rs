@@ -1,19 +1,33 @@
+use crate::http::error::IdentityManagerError;
 use crate::http::requests::WalletVariant;
 use crate::ic_service;
 use crate::repository::access_point_repo::AccessPoint;
+use crate::repository::change_log_repo::{AccountChange, ChangeLogRepo};
+use crate::repository::memory::{
+    candid_storable, get_memory, Memory, ACCOUNTS_MEMORY_ID, PRINCIPAL_INDEX_MEMORY_ID,
+};
+use crate::repository::openid_repo::OpenIdRepo;
 use crate::repository::persona_repo::Persona;
 use crate::repository::repo::{is_anchor_exists, BasicEntity, TEMP_KEYS};
-use itertools::Itertools;
-use crate::service::certified_service::{remove_certify_keys, update_certify_keys};
+use crate::repository::step_up_policy_repo::StepUpRule;
+use crate::service::certified_service::{
+    certify_account, remove_certify_keys, update_certify_keys,
+};
+use crate::service::recovery_service::forget_pending_recovery;
 use candid::{CandidType, Deserialize};
 use ic_cdk::api::time;
+use ic_stable_structures::StableBTreeMap;
 use serde::Serialize;
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashSet};
 
 thread_local! {
-  pub static ACCOUNTS: RefCell<BTreeMap<String, Account>> = const { RefCell::new(BTreeMap::new()) };
-  pub static PRINCIPAL_INDEX: RefCell<BTreeMap<String, String>> = const { RefCell::new(BTreeMap::new()) };
+  pub static ACCOUNTS: RefCell<StableBTreeMap<String, Account, Memory>> =
+    RefCell::new(StableBTreeMap::init(get_memory(ACCOUNTS_MEMORY_ID)));
+  pub static PRINCIPAL_INDEX: RefCell<StableBTreeMap<String, String, Memory>> =
+    RefCell::new(StableBTreeMap::init(get_memory(PRINCIPAL_INDEX_MEMORY_ID)));
+  /// Secondary index of `(wallet, anchor)` to the root principal, rebuilt from `ACCOUNTS` after each upgrade.
+  pub static ANCHOR_INDEX: RefCell<BTreeMap<(WalletVariant, u64), String>> = const { RefCell::new(BTreeMap::new()) };
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
@@ -32,20 +46,52 @@ pub struct Account {
     pub wallet: WalletVariant,
     pub is2fa_enabled: bool,
     pub email: Option<String>,
+    pub pending_recovery: Option<PendingRecovery>,
+    pub email_verified_at: Option<u64>,
+    /// `issuer#sub` of the ID token an `OpenID` account was created with.
+    pub openid_subject: Option<String>,
+    /// Set while the account waits out the removal grace period, the account is hidden from lookups meanwhile.
+    pub pending_removal: Option<PendingRemoval>,
+    /// The Internet Identity anchor of an account migrated to NFID.
+    pub ii_anchor: Option<u64>,
+    /// Step-up rules the owner added on top of the operator policy.
+    pub step_up_rules: Option<Vec<StepUpRule>>,
+}
+
+candid_storable!(Account);
+
+/// An access point waiting to be added by a time-locked recovery.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct PendingRecovery {
+    pub access_point: AccessPoint,
+    pub initiated_by: String,
+    pub initiated_at: u64,
+    pub executable_at: u64,
+}
+
+/// A removal that is completed once the grace period ends, unless the owner cancels it.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct PendingRemoval {
+    pub requested_by: String,
+    pub requested_at: u64,
+    pub purge_at: u64,
 }
 
 pub trait AccountRepoTrait {
     fn get_account(&self) -> Option<Account>;
     fn get_account_by_principal(&self, princ: String) -> Option<Account>;
     fn get_account_by_anchor(&self, anchor: u64, wallet: WalletVariant) -> Option<Account>;
-    fn create_account(&self, account: Account) -> Option<Account>;
+    fn get_account_by_root(&self, root: String) -> Option<Account>;
+    fn create_account(&self, account: Account) -> Result<Account, IdentityManagerError>;
     fn store_account(&self, account: Account) -> Option<Account>;
     fn remove_account(&self) -> Option<Account>;
+    fn remove_account_by_root(&self, root: String) -> Option<Account>;
+    fn get_account_pending_removal(&self) -> Option<Account>;
     fn update_account_index_with_pub_key(&self, additional_key: String, princ: String);
     fn update_account_index(&self, additional_principal_id: String);
     fn remove_account_index(&self, additional_principal_id: String);
     fn get_all_accounts(&self) -> Vec<Account>;
-    fn find_next_nfid_anchor(&self) -> u64;
+    fn is_principal_indexed(&self, princ: String) -> bool;
     fn find_in_temp_keys(&self, princ: String) -> Option<Account>;
 }
 
@@ -55,68 +101,69 @@ pub struct AccountRepo {}
 impl AccountRepoTrait for AccountRepo {
     fn get_account(&self) -> Option<Account> {
         let princ = ic_service::get_caller().to_text();
-        PRINCIPAL_INDEX.with(|index| {
-            ACCOUNTS.with(|accounts| match index.borrow().get(&princ) {
-                None => {
-                    self.find_in_temp_keys(princ.clone())
-                }
-                Some(key) => match accounts.borrow().get(key) {
-                    None => None,
-                    Some(acc) => Option::from(acc.to_owned()),
-                },
-            })
-        })
+        self.get_account_by_principal(princ)
     }
 
     fn get_account_by_principal(&self, princ: String) -> Option<Account> {
         PRINCIPAL_INDEX.with(|index| {
             ACCOUNTS.with(|accounts| match index.borrow().get(&princ) {
                 None => self.find_in_temp_keys(princ.clone()),
-                Some(key) => match accounts.borrow().get(key) {
-                    None => None,
-                    Some(acc) => Option::from(acc.to_owned()),
-                },
+                Some(key) => accounts.borrow().get(&key),
             })
         })
+        .filter(|account| account.pending_removal.is_none())
     }
 
     fn get_account_by_anchor(&self, anchor: u64, wallet: WalletVariant) -> Option<Account> {
-        ACCOUNTS.with(|accounts| {
-            accounts
-                .borrow()
-                .iter()
-                .find(|l| l.1.anchor == anchor && l.1.wallet == wallet).map(|pair| pair.1.to_owned())
-        })
+        let root = ANCHOR_INDEX.with(|index| index.borrow().get(&(wallet, anchor)).cloned())?;
+        ACCOUNTS
+            .with(|accounts| accounts.borrow().get(&root))
+            .filter(|account| account.pending_removal.is_none())
+    }
+
+    fn get_account_by_root(&self, root: String) -> Option<Account> {
+        ACCOUNTS.with(|accounts| accounts.borrow().get(&root))
     }
 
-    fn create_account(&self, account: Account) -> Option<Account> {
+    /// Whether the principal belongs to any account, including one pending removal.
+    fn is_principal_indexed(&self, princ: String) -> bool {
+        PRINCIPAL_INDEX.with(|index| index.borrow().contains_key(&princ))
+    }
+
+    fn create_account(&self, account: Account) -> Result<Account, IdentityManagerError> {
         ACCOUNTS.with(|accounts| {
             PRINCIPAL_INDEX.with(|index| {
                 if index.borrow().contains_key(&account.principal_id) {
-                    return None;
+                    return Err(IdentityManagerError::AccountExists);
                 }
                 for ap in account.access_points.iter() {
                     if index.borrow().contains_key(&ap.principal_id) {
-                        return None;
+                        return Err(IdentityManagerError::AccessPointExists);
                     }
                 }
                 if is_anchor_exists(account.anchor, account.wallet) {
-                    None
+                    Err(IdentityManagerError::AccountExists)
                 } else {
                     index
                         .borrow_mut()
                         .insert(account.principal_id.clone(), account.principal_id.clone());
-                    update_certify_keys(account.principal_id.clone(), account.principal_id.clone());
+                    log_set_index(&account.principal_id, &account.principal_id);
                     for ap in account.access_points.iter() {
                         index
                             .borrow_mut()
                             .insert(ap.principal_id.clone(), account.principal_id.clone());
-                        update_certify_keys(ap.principal_id.clone(), account.principal_id.clone());
+                        log_set_index(&ap.principal_id, &account.principal_id);
                     }
-                    accounts
+                    let previous = accounts
                         .borrow_mut()
                         .insert(account.principal_id.clone(), account.clone());
-                    Some(account)
+                    update_anchor_index(previous.as_ref(), &account);
+                    certify_account(&account);
+                    ChangeLogRepo::append(AccountChange::CreateAccount {
+                        root: account.principal_id.clone(),
+                        account: ChangeLogRepo::encode_account(&account),
+                    });
+                    Ok(account)
                 }
             })
         })
@@ -124,28 +171,48 @@ impl AccountRepoTrait for AccountRepo {
 
     fn store_account(&self, account: Account) -> Option<Account> {
         ACCOUNTS.with(|accounts| {
-            accounts
+            let previous = accounts
                 .borrow_mut()
                 .insert(account.principal_id.clone(), account.clone());
+            update_anchor_index(previous.as_ref(), &account);
+            if previous.is_some_and(|previous| previous.anchor != account.anchor) {
+                certify_account(&account);
+            }
+            ChangeLogRepo::append(AccountChange::StoreAccount {
+                root: account.principal_id.clone(),
+                account: ChangeLogRepo::encode_account(&account),
+            });
             Some(account)
         })
     }
 
     fn remove_account(&self) -> Option<Account> {
         let acc = self.get_account()?;
+        self.remove_account_by_root(acc.principal_id)
+    }
+
+    fn remove_account_by_root(&self, root: String) -> Option<Account> {
+        let acc = self.get_account_by_root(root)?;
         PRINCIPAL_INDEX.with(|index| {
             acc.access_points.iter().for_each(|ap| {
                 index.borrow_mut().remove(&ap.principal_id);
                 remove_certify_keys(ap.principal_id.clone());
+                log_remove_index(&ap.principal_id);
             });
             index.borrow_mut().remove(&acc.principal_id);
             remove_certify_keys(acc.principal_id.clone());
+            log_remove_index(&acc.principal_id);
         });
         TEMP_KEYS.with(|keys| {
             keys.borrow_mut().remove(&acc.principal_id);
         });
+        if let Some(subject) = acc.openid_subject.as_ref() {
+            OpenIdRepo::unbind_subject(subject);
+        }
+        forget_pending_recovery(&acc.principal_id);
+        // The tombstone keeps its anchor, so the anchor stays reserved and resolvable as before.
         ACCOUNTS.with(|accounts| {
-            accounts.borrow_mut().insert(acc.principal_id.clone(), Account {
+            let tombstone = Account {
                 anchor: acc.anchor,
                 principal_id: acc.principal_id.clone(),
                 name: None,
@@ -157,17 +224,40 @@ impl AccountRepoTrait for AccountRepo {
                 wallet: acc.wallet,
                 is2fa_enabled: false,
                 email: None,
+                pending_recovery: None,
+                email_verified_at: None,
+                openid_subject: None,
+                pending_removal: None,
+                ii_anchor: acc.ii_anchor,
+                step_up_rules: None,
+            };
+            let previous = accounts
+                .borrow_mut()
+                .insert(acc.principal_id.clone(), tombstone.clone());
+            update_anchor_index(previous.as_ref(), &tombstone);
+            ChangeLogRepo::append(AccountChange::RemoveAccount {
+                root: tombstone.principal_id.clone(),
+                account: ChangeLogRepo::encode_account(&tombstone),
             });
         });
         Some(acc)
     }
 
+    /// The caller's account, only if it is waiting out the removal grace period.
+    fn get_account_pending_removal(&self) -> Option<Account> {
+        let princ = ic_service::get_caller().to_text();
+        let root = PRINCIPAL_INDEX.with(|index| index.borrow().get(&princ))?;
+        self.get_account_by_root(root)
+            .filter(|account| account.pending_removal.is_some())
+    }
+
     fn update_account_index_with_pub_key(&self, additional_principal_id: String, princ: String) {
         PRINCIPAL_INDEX.with(|index| {
             update_certify_keys(additional_principal_id.clone(), princ.clone());
             index
                 .borrow_mut()
                 .insert(additional_principal_id.clone(), princ.clone());
+            log_set_index(&additional_principal_id, &princ);
         })
     }
 
@@ -181,6 +271,7 @@ impl AccountRepoTrait for AccountRepo {
                 additional_principal_id.clone(),
                 additional_principal_id.clone(),
             );
+            log_set_index(&additional_principal_id, &additional_principal_id);
         })
     }
 
@@ -188,29 +279,12 @@ impl AccountRepoTrait for AccountRepo {
         PRINCIPAL_INDEX.with(|index| {
             remove_certify_keys(additional_principal_id.clone());
             index.borrow_mut().remove(&additional_principal_id);
+            log_remove_index(&additional_principal_id);
         })
     }
 
     fn get_all_accounts(&self) -> Vec<Account> {
-        ACCOUNTS.with(|accounts| accounts.borrow().values().map(|l| l.to_owned()).collect())
-    }
-
-    fn find_next_nfid_anchor(&self) -> u64 {
-        ACCOUNTS.with(|accounts| {
-            accounts
-                .borrow()
-                .values()
-                .filter(|a| a.wallet.eq(&WalletVariant::NFID))
-                .sorted_by(|a, b| Ord::cmp(&a.anchor, &b.anchor))
-                .last()
-                .map_or(100_000_000, |x| {
-                    if x.anchor < 200_000_000 {
-                        200_000_000
-                    } else {
-                        x.anchor + 1
-                    }
-                })
-        })
+        ACCOUNTS.with(|accounts| accounts.borrow().iter().map(|l| l.1).collect())
     }
 
     fn find_in_temp_keys(&self, princ: String) -> Option<Account> {
@@ -225,3 +299,41 @@ impl AccountRepoTrait for AccountRepo {
         })
     }
 }
+
+fn log_set_index(principal: &str, root: &str) {
+    ChangeLogRepo::append(AccountChange::SetIndex {
+        principal: principal.to_string(),
+        root: root.to_string(),
+    });
+}
+
+fn log_remove_index(principal: &str) {
+    ChangeLogRepo::append(AccountChange::RemoveIndex {
+        principal: principal.to_string(),
+    });
+}
+
+/// Keeps `ANCHOR_INDEX` in line with a write to `ACCOUNTS`, dropping the previous key if the anchor or wallet changed.
+fn update_anchor_index(previous: Option<&Account>, account: &Account) {
+    ANCHOR_INDEX.with(|index| {
+        let mut index = index.borrow_mut();
+        if let Some(previous) = previous {
+            if previous.anchor != account.anchor || previous.wallet != account.wallet {
+                index.remove(&(previous.wallet, previous.anchor));
+            }
+        }
+        index.insert((account.wallet, account.anchor), account.principal_id.clone());
+    })
+}
+
+pub fn rebuild_anchor_index() {
+    ACCOUNTS.with(|accounts| {
+        ANCHOR_INDEX.with(|index| {
+            let mut index = index.borrow_mut();
+            index.clear();
+            for (principal_id, acc) in accounts.borrow().iter() {
+                index.insert((acc.wallet, acc.anchor), principal_id);
+            }
+        })
+    })
+}
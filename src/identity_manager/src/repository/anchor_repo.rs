@@ -0,0 +1,223 @@
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::WalletVariant;
+use crate::repository::account_repo::ANCHOR_INDEX;
+use crate::repository::memory::{candid_storable, get_memory, Memory, ANCHOR_ALLOCATORS_MEMORY_ID};
+use crate::repository::repo::is_anchor_exists;
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::StableBTreeMap;
+use std::cell::RefCell;
+
+thread_local! {
+    /// Wallets whose allocator was configured or used, the others fall back to `AnchorAllocator::default_for`.
+    static ANCHOR_ALLOCATORS: RefCell<StableBTreeMap<WalletVariant, AnchorAllocator, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(ANCHOR_ALLOCATORS_MEMORY_ID)));
+}
+
+candid_storable!(WalletVariant);
+
+/// Anchors from `start` up to but excluding `end`.
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub struct AnchorRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl AnchorRange {
+    fn contains(&self, anchor: u64) -> bool {
+        self.start <= anchor && anchor < self.end
+    }
+}
+
+/// Hands out the anchors of a wallet in increasing order from its ranges, skipping reserved blocks.
+/// The counter only moves forward, so an anchor is never handed out twice, even if the account creation fails afterwards.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AnchorAllocator {
+    pub wallet: WalletVariant,
+    pub ranges: Vec<AnchorRange>,
+    /// Blocks inside the ranges kept for partners or test environments.
+    pub reserved: Vec<AnchorRange>,
+    /// The lowest anchor that may be handed out next, unset until the first allocation.
+    pub next: Option<u64>,
+}
+
+candid_storable!(AnchorAllocator);
+
+impl AnchorAllocator {
+    /// The ranges the anchors were handed out from before allocators were configurable.
+    pub fn default_for(wallet: WalletVariant) -> AnchorAllocator {
+        let ranges = match wallet {
+            WalletVariant::NFID => vec![
+                AnchorRange {
+                    start: 100_000_000,
+                    end: 100_000_001,
+                },
+                AnchorRange {
+                    start: 200_000_000,
+                    end: 300_000_000,
+                },
+            ],
+            WalletVariant::OpenId => vec![AnchorRange {
+                start: 300_000_000,
+                end: u64::MAX,
+            }],
+            // Internet Identity anchors are chosen by Internet Identity.
+            WalletVariant::InternetIdentity => vec![],
+        };
+        AnchorAllocator {
+            wallet,
+            ranges,
+            reserved: vec![],
+            next: None,
+        }
+    }
+
+    /// Returns the first anchor from `from` on that is inside a range, outside the reserved blocks and not taken.
+    fn find_free(&self, from: u64, is_taken: impl Fn(u64) -> bool) -> Option<u64> {
+        let mut candidate = from;
+        loop {
+            let range = self
+                .ranges
+                .iter()
+                .filter(|range| range.end > candidate)
+                .min_by_key(|range| range.start)?;
+            candidate = candidate.max(range.start);
+            if let Some(reserved) = self
+                .reserved
+                .iter()
+                .find(|reserved| reserved.contains(candidate))
+            {
+                candidate = reserved.end;
+            } else if is_taken(candidate) {
+                candidate += 1;
+            } else {
+                return Some(candidate);
+            }
+        }
+    }
+}
+
+pub struct AnchorRepo {}
+
+impl AnchorRepo {
+    pub fn get(wallet: WalletVariant) -> AnchorAllocator {
+        ANCHOR_ALLOCATORS
+            .with(|allocators| allocators.borrow().get(&wallet))
+            .unwrap_or_else(|| AnchorAllocator::default_for(wallet))
+    }
+
+    pub fn get_all() -> Vec<AnchorAllocator> {
+        [
+            WalletVariant::NFID,
+            WalletVariant::InternetIdentity,
+            WalletVariant::OpenId,
+        ]
+        .into_iter()
+        .map(Self::get)
+        .collect()
+    }
+
+    /// Replaces the ranges and reserved blocks, the counter is kept, so ranges below it are not used.
+    pub fn configure(
+        wallet: WalletVariant,
+        ranges: Vec<AnchorRange>,
+        reserved: Vec<AnchorRange>,
+    ) -> Result<AnchorAllocator, IdentityManagerError> {
+        if ranges
+            .iter()
+            .chain(reserved.iter())
+            .any(|range| range.start >= range.end)
+        {
+            return Err(IdentityManagerError::InvalidAnchorRange);
+        }
+        let allocator = AnchorAllocator {
+            ranges,
+            reserved,
+            ..Self::get(wallet)
+        };
+        Self::save(allocator.clone());
+        Ok(allocator)
+    }
+
+    /// Takes the next free anchor of the wallet and persists the counter before returning it.
+    /// The first allocation starts after the highest existing anchor of the wallet.
+    pub fn allocate(wallet: WalletVariant) -> Result<u64, IdentityManagerError> {
+        let mut allocator = Self::get(wallet);
+        let from = allocator.next.unwrap_or_else(|| {
+            ANCHOR_INDEX.with(|index| {
+                index
+                    .borrow()
+                    .range((wallet, 0)..=(wallet, u64::MAX))
+                    .next_back()
+                    .map_or(0, |((_, anchor), _)| anchor + 1)
+            })
+        });
+        let anchor = allocator
+            .find_free(from, |anchor| is_anchor_exists(anchor, wallet))
+            .ok_or(IdentityManagerError::AnchorRangeExhausted)?;
+        allocator.next = Some(anchor + 1);
+        Self::save(allocator);
+        Ok(anchor)
+    }
+
+    fn save(allocator: AnchorAllocator) {
+        ANCHOR_ALLOCATORS.with(|allocators| {
+            allocators.borrow_mut().insert(allocator.wallet, allocator);
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocator(ranges: Vec<AnchorRange>, reserved: Vec<AnchorRange>) -> AnchorAllocator {
+        AnchorAllocator {
+            wallet: WalletVariant::NFID,
+            ranges,
+            reserved,
+            next: None,
+        }
+    }
+
+    #[test]
+    fn default_nfid_ranges_match_legacy_anchors() {
+        let allocator = AnchorAllocator::default_for(WalletVariant::NFID);
+        assert_eq!(Some(100_000_000), allocator.find_free(0, |_| false));
+        assert_eq!(
+            Some(200_000_000),
+            allocator.find_free(100_000_001, |_| false)
+        );
+        assert_eq!(
+            Some(200_000_005),
+            allocator.find_free(200_000_005, |_| false)
+        );
+    }
+
+    #[test]
+    fn skips_reserved_blocks_and_taken_anchors() {
+        let allocator = allocator(
+            vec![AnchorRange { start: 10, end: 20 }],
+            vec![AnchorRange { start: 12, end: 15 }],
+        );
+        assert_eq!(Some(15), allocator.find_free(12, |_| false));
+        assert_eq!(
+            Some(16),
+            allocator.find_free(11, |anchor| anchor == 11 || anchor == 15)
+        );
+    }
+
+    #[test]
+    fn moves_to_the_next_range_and_fails_when_exhausted() {
+        let allocator = allocator(
+            vec![
+                AnchorRange { start: 50, end: 60 },
+                AnchorRange { start: 10, end: 12 },
+            ],
+            vec![],
+        );
+        assert_eq!(Some(10), allocator.find_free(0, |_| false));
+        assert_eq!(Some(50), allocator.find_free(12, |_| false));
+        assert_eq!(None, allocator.find_free(60, |_| false));
+        assert_eq!(None, allocator.find_free(58, |anchor| anchor >= 58));
+    }
+}
@@ -2,6 +2,7 @@ use candid::{CandidType, Deserialize};
 use std::cmp::Ordering;
 use std::collections::HashSet;
 
+use crate::repository::memory::candid_storable;
 use crate::repository::repo::APPLICATIONS;
 
 #[deprecated()]
@@ -17,6 +18,8 @@ pub struct Application {
     pub is_iframe_allowed: Option<bool>,
 }
 
+candid_storable!(Application);
+
 impl PartialEq for Application {
     fn eq(&self, other: &Self) -> bool {
         self.domain.eq(&other.domain)
@@ -46,7 +49,7 @@ impl ApplicationRepoTrait for ApplicationRepo {
     fn read_applications(&self) -> Vec<Application> {
         APPLICATIONS.with(|apps| {
             let applications = apps.borrow();
-            applications.iter().cloned().collect()
+            applications.iter().map(|(_, app)| app).collect()
         })
     }
 }
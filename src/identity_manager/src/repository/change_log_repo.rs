@@ -0,0 +1,136 @@
+use crate::ic_service;
+use crate::repository::account_repo::Account;
+use crate::repository::memory::{
+    candid_storable, get_memory, Memory, CHANGE_LOG_MEMORY_ID, REPLICATION_STATE_MEMORY_ID,
+};
+use crate::repository::repo::ConfigurationRepo;
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::{StableBTreeMap, StableCell};
+use serde::Serialize;
+use serde_bytes::ByteBuf;
+use std::cell::RefCell;
+
+thread_local! {
+    /// Changes not yet acknowledged by the backup canister, by sequence number.
+    static CHANGE_LOG: RefCell<StableBTreeMap<u64, ChangeLogEntry, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(CHANGE_LOG_MEMORY_ID)));
+    static REPLICATION_STATE: RefCell<StableCell<ReplicationState, Memory>> = RefCell::new(
+        StableCell::init(get_memory(REPLICATION_STATE_MEMORY_ID), ReplicationState::default())
+            .expect("Failed to initialize the replication state stable cell.")
+    );
+}
+
+/// A mutation of `ACCOUNTS` or `PRINCIPAL_INDEX`.
+/// Accounts are CBOR encoded like the `Cbor` export, so the backup canister does not depend on their shape.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub enum AccountChange {
+    CreateAccount { root: String, account: ByteBuf },
+    StoreAccount { root: String, account: ByteBuf },
+    RemoveAccount { root: String, account: ByteBuf },
+    SetIndex { principal: String, root: String },
+    RemoveIndex { principal: String },
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct ChangeLogEntry {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub change: AccountChange,
+}
+
+candid_storable!(ChangeLogEntry);
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize, Serialize)]
+pub struct ReplicationState {
+    /// The sequence number of the next change.
+    pub next_seq: u64,
+    /// Every change before it has been applied by the backup canister.
+    pub acked_seq: u64,
+    pub last_attempt_at: Option<u64>,
+    pub last_ack_at: Option<u64>,
+    pub last_error: Option<String>,
+    /// Failed pushes since the last acknowledgement.
+    pub failed_attempts: u32,
+}
+
+candid_storable!(ReplicationState);
+
+pub struct ChangeLogRepo {}
+
+impl ChangeLogRepo {
+    /// Changes are only kept while a backup canister is configured, otherwise nothing would ever truncate the log.
+    pub fn append(change: AccountChange) {
+        if ConfigurationRepo::get().backup_canister_id.is_none() {
+            return;
+        }
+        let mut state = Self::get_state();
+        let entry = ChangeLogEntry {
+            seq: state.next_seq,
+            timestamp: ic_service::get_time(),
+            change,
+        };
+        CHANGE_LOG.with(|log| log.borrow_mut().insert(entry.seq, entry));
+        state.next_seq += 1;
+        Self::save_state(state);
+    }
+
+    pub fn encode_account(account: &Account) -> ByteBuf {
+        ByteBuf::from(serde_cbor::to_vec(account).expect("Failed to encode the account to CBOR."))
+    }
+
+    /// Returns up to `limit` changes the backup canister has not acknowledged yet, oldest first.
+    pub fn get_pending(limit: usize) -> Vec<ChangeLogEntry> {
+        CHANGE_LOG.with(|log| log.borrow().iter().map(|(_, entry)| entry).take(limit).collect())
+    }
+
+    pub fn get_oldest_pending() -> Option<ChangeLogEntry> {
+        CHANGE_LOG.with(|log| log.borrow().first_key_value().map(|(_, entry)| entry))
+    }
+
+    /// Drops every change before `acked_seq`, the backup canister has applied them.
+    pub fn acknowledge(acked_seq: u64, time: u64) {
+        CHANGE_LOG.with(|log| {
+            let mut log = log.borrow_mut();
+            let acked: Vec<u64> = log.range(..acked_seq).map(|(seq, _)| seq).collect();
+            for seq in acked {
+                log.remove(&seq);
+            }
+        });
+        let state = Self::get_state();
+        Self::save_state(ReplicationState {
+            acked_seq: acked_seq.max(state.acked_seq),
+            last_ack_at: Some(time),
+            last_error: None,
+            failed_attempts: 0,
+            ..state
+        });
+    }
+
+    pub fn record_attempt(time: u64) {
+        Self::save_state(ReplicationState {
+            last_attempt_at: Some(time),
+            ..Self::get_state()
+        });
+    }
+
+    pub fn record_failure(error: String) {
+        let state = Self::get_state();
+        Self::save_state(ReplicationState {
+            last_error: Some(error),
+            failed_attempts: state.failed_attempts + 1,
+            ..state
+        });
+    }
+
+    pub fn get_state() -> ReplicationState {
+        REPLICATION_STATE.with(|state| state.borrow().get().clone())
+    }
+
+    fn save_state(state: ReplicationState) {
+        REPLICATION_STATE.with(|cell| {
+            cell.borrow_mut()
+                .set(state)
+                .expect("Failed to save the replication state to stable memory.");
+        });
+    }
+}
@@ -0,0 +1,154 @@
+use crate::repository::memory::{
+    candid_storable, get_memory, Memory, CONFIGURATION_HISTORY_MEMORY_ID,
+};
+use crate::repository::repo::Configuration;
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::StableBTreeMap;
+use std::cell::RefCell;
+
+thread_local! {
+    static CONFIGURATION_HISTORY: RefCell<StableBTreeMap<u64, ConfigurationVersion, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(CONFIGURATION_HISTORY_MEMORY_ID)));
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ConfigurationChange {
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+/// A saved configuration together with who saved it and what changed compared to the previous one.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ConfigurationVersion {
+    pub version: u64,
+    pub caller: Principal,
+    pub timestamp: u64,
+    pub changes: Vec<ConfigurationChange>,
+    /// The version this one restored, if it was created by a rollback.
+    pub rollback_of: Option<u64>,
+    pub configuration: Configuration,
+}
+
+candid_storable!(ConfigurationVersion);
+
+/// Lists every field of `Configuration` without `..`, so a new field fails to compile until it is added here.
+macro_rules! diff_configuration {
+    ($old:expr, $new:expr, $($field:ident),* $(,)?) => {{
+        let Configuration { $($field: _),* } = $new;
+        let mut changes = Vec::new();
+        $(
+            let old_value = format!("{:?}", $old.$field);
+            let new_value = format!("{:?}", $new.$field);
+            if old_value != new_value {
+                changes.push(ConfigurationChange {
+                    field: stringify!($field).to_string(),
+                    old_value,
+                    new_value,
+                });
+            }
+        )*
+        changes
+    }};
+}
+
+pub fn diff(old: &Configuration, new: &Configuration) -> Vec<ConfigurationChange> {
+    diff_configuration!(
+        old,
+        new,
+        lambda_url,
+        lambda,
+        token_ttl,
+        token_refresh_ttl,
+        whitelisted_phone_numbers,
+        heartbeat,
+        backup_canister_id,
+        ii_canister_id,
+        whitelisted_canisters,
+        env,
+        git_branch,
+        commit_hash,
+        operator,
+        account_creation_paused,
+        max_free_captcha_per_minute,
+        test_captcha,
+        recovery_delay,
+        challenge_type,
+        proof_of_work_difficulty,
+        max_challenge_attempts,
+        account_removal_grace_period,
+    )
+}
+
+pub struct ConfigurationHistoryRepo {}
+
+impl ConfigurationHistoryRepo {
+    /// Records `new` as the next version. The first version is numbered 1.
+    pub fn append(
+        caller: Principal,
+        timestamp: u64,
+        old: &Configuration,
+        new: &Configuration,
+        rollback_of: Option<u64>,
+    ) -> ConfigurationVersion {
+        CONFIGURATION_HISTORY.with(|history| {
+            let mut history = history.borrow_mut();
+            let version = history.last_key_value().map_or(1, |(last, _)| last + 1);
+            let entry = ConfigurationVersion {
+                version,
+                caller,
+                timestamp,
+                changes: diff(old, new),
+                rollback_of,
+                configuration: new.clone(),
+            };
+            history.insert(version, entry.clone());
+            entry
+        })
+    }
+
+    pub fn get(version: u64) -> Option<ConfigurationVersion> {
+        CONFIGURATION_HISTORY.with(|history| history.borrow().get(&version))
+    }
+
+    /// Returns up to `limit` versions older than `before`, newest first.
+    pub fn get_history(before: Option<u64>, limit: usize) -> Vec<ConfigurationVersion> {
+        CONFIGURATION_HISTORY.with(|history| {
+            let history = history.borrow();
+            let end = before.unwrap_or(u64::MAX);
+            history
+                .range(..end)
+                .collect::<Vec<_>>()
+                .into_iter()
+                .rev()
+                .take(limit)
+                .map(|(_, entry)| entry)
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::repo::ConfigurationRepo;
+    use std::time::Duration;
+
+    #[test]
+    fn diff_reports_only_changed_fields() {
+        let old = ConfigurationRepo::get_default_config();
+        let new = Configuration {
+            token_refresh_ttl: Duration::from_secs(120),
+            env: Some("prod".to_string()),
+            ..old.clone()
+        };
+
+        let changes = diff(&old, &new);
+
+        let fields: Vec<&str> = changes.iter().map(|change| change.field.as_str()).collect();
+        assert_eq!(fields, vec!["token_refresh_ttl", "env"]);
+        assert_eq!(changes[1].old_value, "None");
+        assert_eq!(changes[1].new_value, "Some(\"prod\")");
+        assert!(diff(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,93 @@
+use candid::Principal;
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{DefaultMemoryImpl, Storable};
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+pub type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+pub const ACCOUNTS_MEMORY_ID: MemoryId = MemoryId::new(0);
+pub const PRINCIPAL_INDEX_MEMORY_ID: MemoryId = MemoryId::new(1);
+pub const CONFIGURATION_MEMORY_ID: MemoryId = MemoryId::new(2);
+pub const ADMINS_MEMORY_ID: MemoryId = MemoryId::new(3);
+pub const CONTROLLERS_MEMORY_ID: MemoryId = MemoryId::new(4);
+pub const APPLICATIONS_MEMORY_ID: MemoryId = MemoryId::new(5);
+pub const JOBS_MEMORY_ID: MemoryId = MemoryId::new(6);
+pub const PENDING_RECOVERIES_MEMORY_ID: MemoryId = MemoryId::new(7);
+pub const ACCOUNT_EVENTS_MEMORY_ID: MemoryId = MemoryId::new(8);
+pub const ACCOUNT_EVENT_SEQUENCES_MEMORY_ID: MemoryId = MemoryId::new(9);
+pub const CONFIGURATION_HISTORY_MEMORY_ID: MemoryId = MemoryId::new(10);
+pub const ROLES_MEMORY_ID: MemoryId = MemoryId::new(11);
+pub const OPENID_PROVIDERS_MEMORY_ID: MemoryId = MemoryId::new(12);
+pub const OPENID_SUBJECTS_MEMORY_ID: MemoryId = MemoryId::new(13);
+pub const CHANGE_LOG_MEMORY_ID: MemoryId = MemoryId::new(14);
+pub const REPLICATION_STATE_MEMORY_ID: MemoryId = MemoryId::new(15);
+pub const PENDING_REMOVALS_MEMORY_ID: MemoryId = MemoryId::new(16);
+pub const ANCHOR_ALLOCATORS_MEMORY_ID: MemoryId = MemoryId::new(17);
+pub const STEP_UP_POLICY_MEMORY_ID: MemoryId = MemoryId::new(18);
+
+/// The memory manager writes this magic at offset 0 of the stable memory.
+/// Anything else there means the canister still holds the legacy `stable_save` blob.
+const MEMORY_MANAGER_MAGIC: &[u8; 3] = b"MGR";
+
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+}
+
+pub fn get_memory(id: MemoryId) -> Memory {
+    MEMORY_MANAGER.with(|manager| manager.borrow().get(id))
+}
+
+/// Checks whether the stable memory still contains the candid blob written by the legacy `pre_upgrade`.
+/// Must be called before any stable structure is touched, otherwise the memory manager overwrites the blob.
+pub fn is_legacy_layout() -> bool {
+    if ic_cdk::api::stable::stable_size() == 0 {
+        return false;
+    }
+    let mut magic = [0u8; 3];
+    ic_cdk::api::stable::stable_read(0, &mut magic);
+    magic.ne(MEMORY_MANAGER_MAGIC)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StorablePrincipal(pub Principal);
+
+impl Storable for StorablePrincipal {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Borrowed(self.0.as_slice())
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        StorablePrincipal(Principal::from_slice(bytes.as_ref()))
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 29,
+        is_fixed_size: false,
+    };
+}
+
+/// Implements `Storable` for a candid type by encoding it as an unbounded candid blob.
+/// New fields of such types must be optional to keep previously stored values decodable.
+macro_rules! candid_storable {
+    ($type:ty) => {
+        impl ic_stable_structures::Storable for $type {
+            fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+                std::borrow::Cow::Owned(
+                    candid::encode_one(self).expect("Failed to encode the value to candid."),
+                )
+            }
+
+            fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+                candid::decode_one(bytes.as_ref()).expect("Failed to decode the value from candid.")
+            }
+
+            const BOUND: ic_stable_structures::storable::Bound =
+                ic_stable_structures::storable::Bound::Unbounded;
+        }
+    };
+}
+
+pub(crate) use candid_storable;
@@ -1,5 +1,13 @@
 pub mod access_point_repo;
+pub mod account_event_repo;
 pub mod account_repo;
+pub mod anchor_repo;
 pub mod application_repo;
+pub mod change_log_repo;
+pub mod configuration_history_repo;
+pub mod memory;
+pub mod openid_repo;
 pub mod persona_repo;
 pub mod repo;
+pub mod role_repo;
+pub mod step_up_policy_repo;
@@ -0,0 +1,75 @@
+use crate::repository::memory::{
+    candid_storable, get_memory, Memory, OPENID_PROVIDERS_MEMORY_ID, OPENID_SUBJECTS_MEMORY_ID,
+};
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::StableBTreeMap;
+use std::cell::RefCell;
+
+thread_local! {
+    /// Issuer to its provider.
+    static OPENID_PROVIDERS: RefCell<StableBTreeMap<String, OpenIdProvider, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(OPENID_PROVIDERS_MEMORY_ID)));
+    /// `issuer#sub` to the root principal of the account bound to it.
+    static OPENID_SUBJECTS: RefCell<StableBTreeMap<String, String, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(OPENID_SUBJECTS_MEMORY_ID)));
+}
+
+/// An OpenID Connect issuer whose ID tokens are accepted for the given client ids.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct OpenIdProvider {
+    pub issuer: String,
+    pub client_ids: Vec<String>,
+    pub keys: Vec<Jwk>,
+}
+
+candid_storable!(OpenIdProvider);
+
+/// An RSA key from the issuer's JWKS, the modulus and exponent are base64url encoded as published.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct Jwk {
+    pub kid: String,
+    pub n: String,
+    pub e: String,
+}
+
+pub struct OpenIdRepo {}
+
+impl OpenIdRepo {
+    pub fn save_provider(provider: OpenIdProvider) {
+        OPENID_PROVIDERS.with(|providers| {
+            providers
+                .borrow_mut()
+                .insert(provider.issuer.clone(), provider);
+        })
+    }
+
+    pub fn remove_provider(issuer: &String) -> bool {
+        OPENID_PROVIDERS.with(|providers| providers.borrow_mut().remove(issuer).is_some())
+    }
+
+    pub fn get_providers() -> Vec<OpenIdProvider> {
+        OPENID_PROVIDERS.with(|providers| {
+            providers
+                .borrow()
+                .iter()
+                .map(|(_, provider)| provider)
+                .collect()
+        })
+    }
+
+    pub fn get_root_by_subject(subject: &String) -> Option<String> {
+        OPENID_SUBJECTS.with(|subjects| subjects.borrow().get(subject))
+    }
+
+    pub fn bind_subject(subject: String, root: String) {
+        OPENID_SUBJECTS.with(|subjects| {
+            subjects.borrow_mut().insert(subject, root);
+        })
+    }
+
+    pub fn unbind_subject(subject: &String) {
+        OPENID_SUBJECTS.with(|subjects| {
+            subjects.borrow_mut().remove(subject);
+        })
+    }
+}
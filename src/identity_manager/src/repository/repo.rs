@@ -1,13 +1,22 @@
-use crate::http::requests::{DeviceType, WalletVariant};
+use crate::http::requests::{ChallengeType, DeviceType, WalletVariant};
 use crate::ic_service;
 use crate::logger::logger::Logs;
 use crate::repository::access_point_repo::AccessPoint;
-use crate::repository::account_repo::{Account, ACCOUNTS, PRINCIPAL_INDEX};
+use crate::repository::account_repo::{
+    rebuild_anchor_index, Account, ACCOUNTS, ANCHOR_INDEX, PRINCIPAL_INDEX,
+};
 use crate::repository::application_repo::Application;
+use crate::repository::configuration_history_repo::{ConfigurationHistoryRepo, ConfigurationVersion};
+use crate::repository::memory::{
+    candid_storable, get_memory, is_legacy_layout, Memory, StorablePrincipal, ADMINS_MEMORY_ID,
+    APPLICATIONS_MEMORY_ID, CONFIGURATION_MEMORY_ID, CONTROLLERS_MEMORY_ID,
+};
 use crate::repository::persona_repo::Persona;
 use crate::structure::ttl_hashmap::TtlHashMap;
+use crate::util::captcha::CaptchaChallenge;
 use candid::{CandidType, Deserialize, Principal};
 use ic_cdk::storage;
+use ic_stable_structures::{StableBTreeMap, StableCell};
 use serde::Serialize;
 use std::cell::RefCell;
 use std::collections::{BTreeSet, HashSet};
@@ -18,7 +27,7 @@ const fn secs_to_nanos(secs: u64) -> u64 {
     secs * 1_000_000_000
 }
 pub const MINUTE_NS: u64 = secs_to_nanos(60);
-const TEMP_KEY_EXPIRATION_NS: u64 = 10 * MINUTE_NS;
+pub const TEMP_KEY_EXPIRATION_NS: u64 = 10 * MINUTE_NS;
 const CAPTCHA_KEY_EXPIRATION_NS: u64 = 5 * MINUTE_NS;
 
 #[derive(Debug, Deserialize, CandidType, Clone)]
@@ -39,18 +48,31 @@ pub struct Configuration {
     pub account_creation_paused: bool,
     pub max_free_captcha_per_minute: u16,
     pub test_captcha: bool,
+    pub recovery_delay: Option<Duration>,
+    pub challenge_type: Option<ChallengeType>,
+    pub proof_of_work_difficulty: Option<u8>,
+    pub max_challenge_attempts: Option<u8>,
+    pub account_removal_grace_period: Option<Duration>,
 }
 
+candid_storable!(Configuration);
+
 //todo rethink visibility
 pub type Applications = BTreeSet<Application>;
 
 thread_local! {
-  pub static APPLICATIONS: RefCell<BTreeSet<Application>> = const { RefCell::new(BTreeSet::new()) };
+  pub static APPLICATIONS: RefCell<StableBTreeMap<String, Application, Memory>> =
+    RefCell::new(StableBTreeMap::init(get_memory(APPLICATIONS_MEMORY_ID)));
   pub static TEMP_KEYS: RefCell<TtlHashMap<String, u64>> = RefCell::new(TtlHashMap::new(TEMP_KEY_EXPIRATION_NS));
-  pub static CAPTCHA_CAHLLENGES: RefCell<TtlHashMap<String, Option<String>>> = RefCell::new(TtlHashMap::new(CAPTCHA_KEY_EXPIRATION_NS));
-    pub static ADMINS: RefCell<HashSet<Principal>> = RefCell::new(HashSet::new());
-    pub static CONTROLLERS: RefCell<HashSet<Principal>> = RefCell::new(HashSet::new());
-    pub static CONFIGURATION: RefCell<Configuration> = RefCell::new(ConfigurationRepo::get_default_config());
+  pub static CAPTCHA_CAHLLENGES: RefCell<TtlHashMap<String, CaptchaChallenge>> = RefCell::new(TtlHashMap::new(CAPTCHA_KEY_EXPIRATION_NS));
+    pub static ADMINS: RefCell<StableBTreeMap<StorablePrincipal, u64, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(ADMINS_MEMORY_ID)));
+    pub static CONTROLLERS: RefCell<StableBTreeMap<StorablePrincipal, u64, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(CONTROLLERS_MEMORY_ID)));
+    pub static CONFIGURATION: RefCell<StableCell<Configuration, Memory>> = RefCell::new(
+        StableCell::init(get_memory(CONFIGURATION_MEMORY_ID), ConfigurationRepo::get_default_config())
+            .expect("Failed to initialize the configuration stable cell.")
+    );
 
 }
 
@@ -94,49 +116,70 @@ impl BasicEntity {
 impl AdminRepo {
     pub fn get() -> Principal {
         ADMINS.with(|admins| {
-            *admins
+            admins
                 .borrow()
                 .iter()
                 .next()
+                .map(|(admin, _)| admin.0)
                 .expect("Failed to retrieve an admin. The admin list is empty.")
         })
     }
 
     pub fn save(principal: Principal) {
         ADMINS.with(|admins| {
-            admins.borrow_mut().insert(principal);
+            admins
+                .borrow_mut()
+                .insert(StorablePrincipal(principal), ic_service::get_time());
         });
     }
 }
 
 impl ControllersRepo {
     pub fn get() -> Vec<Principal> {
-        CONTROLLERS.with(|controllers| controllers.borrow().iter().copied().collect())
+        CONTROLLERS.with(|controllers| controllers.borrow().iter().map(|(p, _)| p.0).collect())
     }
 
     pub fn save(principals: Vec<Principal>) {
         CONTROLLERS.with(|controllers| {
             for p in principals {
-                controllers.borrow_mut().insert(p);
+                controllers
+                    .borrow_mut()
+                    .insert(StorablePrincipal(p), ic_service::get_time());
             }
         });
     }
 
     pub fn contains(principal: &Principal) -> bool {
-        CONTROLLERS.with(|controllers| controllers.borrow().contains(principal))
+        CONTROLLERS.with(|controllers| controllers.borrow().contains_key(&StorablePrincipal(*principal)))
     }
 }
 
 impl ConfigurationRepo {
     //todo fix Principle not implement default!
     pub fn get() -> Configuration {
-        CONFIGURATION.with(|config| config.borrow().clone())
+        CONFIGURATION.with(|config| config.borrow().get().clone())
     }
 
-    pub fn save(configuration: Configuration) {
+    /// Saves the configuration as a new version attributed to the caller.
+    pub fn save(configuration: Configuration) -> ConfigurationVersion {
+        Self::save_version(configuration, None)
+    }
+
+    pub fn save_version(configuration: Configuration, rollback_of: Option<u64>) -> ConfigurationVersion {
+        let previous = Self::get();
         CONFIGURATION.with(|config| {
-            config.replace(configuration);
+            config
+                .borrow_mut()
+                .set(configuration.clone())
+                .expect("Failed to save the configuration to stable memory.");
         });
+        ConfigurationHistoryRepo::append(
+            ic_service::get_caller(),
+            ic_service::get_time(),
+            &previous,
+            &configuration,
+            rollback_of,
+        )
     }
 
     pub fn get_default_config() -> Configuration {
@@ -161,17 +204,17 @@ impl ConfigurationRepo {
             account_creation_paused: false,
             max_free_captcha_per_minute: 10,
             test_captcha: false,
+            recovery_delay: None,
+            challenge_type: None,
+            proof_of_work_difficulty: None,
+            max_challenge_attempts: None,
+            account_removal_grace_period: None,
         }
     }
 }
 
 pub fn is_anchor_exists(anchor: u64, wallet: WalletVariant) -> bool {
-    ACCOUNTS.with(|accounts| {
-        accounts
-            .borrow()
-            .iter()
-            .any(|x| x.1.anchor == anchor && x.1.wallet.eq(&wallet))
-    })
+    ANCHOR_INDEX.with(|index| index.borrow().contains_key(&(wallet, anchor)))
 }
 
 #[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
@@ -201,47 +244,18 @@ pub struct AccessPointMemoryModel {
     pub base_fields: BasicEntity,
 }
 
-pub fn pre_upgrade() {
-    let mut accounts = Vec::new();
-    ACCOUNTS.with(|accounts_struct| {
-        for p in accounts_struct.borrow().iter() {
-            accounts.push(AccountMemoryModel {
-                anchor: p.1.anchor,
-                principal_id: p.1.principal_id.to_string(),
-                name: p.1.name.clone(),
-                personas: p.1.personas.clone(),
-                phone_number: p.1.phone_number.clone(),
-                phone_number_sha2: p.1.phone_number_sha2.clone(),
-                access_points: p
-                    .1
-                    .access_points
-                    .clone()
-                    .into_iter()
-                    .map(access_point_to_memory_model)
-                    .collect(),
-                base_fields: p.1.base_fields,
-                wallet: Some(p.1.wallet),
-                is2fa_enabled: Some(p.1.is2fa_enabled),
-                email: p.1.email.clone(),
-            })
-        }
-    });
-    let admin = AdminRepo::get();
-    let logs: Vec<Logs> = Vec::default(); //todo remove somehow
-    let applications = APPLICATIONS.with(|apps| apps.borrow().clone());
-    let configuration = CONFIGURATION.with(|config| config.borrow().clone());
-    match storage::stable_save((
-        accounts,
-        admin,
-        logs,
-        Some(applications),
-        Some(configuration),
-    )) {
-        _ => (),
-    }; //todo migrate to object
+/// Accounts, the principal index, configuration, admins and controllers live in stable structures,
+/// so nothing has to be restored unless the canister still holds the legacy `stable_save` blob.
+/// The heap-only anchor index is rebuilt from the accounts.
+pub fn post_upgrade() {
+    if is_legacy_layout() {
+        migrate_legacy_memory();
+    }
+    rebuild_anchor_index();
 }
 
-pub fn post_upgrade() {
+/// One-time migration from the legacy `(Vec<AccountMemoryModel>, Principal, Logs, Option<Applications>, Option<Configuration>)` blob.
+fn migrate_legacy_memory() {
     let (old_accs, admin, _logs, applications, configuration_maybe): (
         Vec<AccountMemoryModel>,
         Principal,
@@ -250,18 +264,13 @@ pub fn post_upgrade() {
         Option<Configuration>,
     ) = storage::stable_restore()
         .expect("Stable restore exited unexpectedly: unable to restore data from stable memory.");
-    CONFIGURATION.with(|config| {
-        let configuration = configuration_maybe.unwrap_or(ConfigurationRepo::get_default_config());
-        config.replace(configuration);
-    });
-    ADMINS.with(|admins| {
-        admins.borrow_mut().insert(admin);
-    });
+    ConfigurationRepo::save(configuration_maybe.unwrap_or(ConfigurationRepo::get_default_config()));
+    AdminRepo::save(admin);
     for u in old_accs {
         let princ = u.principal_id.clone();
 
         PRINCIPAL_INDEX.with(|index| {
-            for x in u.access_points.clone().into_iter() {
+            for x in u.access_points.iter() {
                 index
                     .borrow_mut()
                     .insert(x.principal_id.clone(), princ.clone());
@@ -279,7 +288,6 @@ pub fn post_upgrade() {
                     personas: u.personas,
                     access_points: u
                         .access_points
-                        .clone()
                         .into_iter()
                         .map(access_point_mm_to_ap)
                         .collect(),
@@ -293,6 +301,12 @@ pub fn post_upgrade() {
                         Some(x) => x,
                     },
                     email: u.email,
+                    pending_recovery: None,
+                    email_verified_at: None,
+                    openid_subject: None,
+                    pending_removal: None,
+                    ii_anchor: None,
+                    step_up_rules: None,
                 },
             );
         });
@@ -300,26 +314,13 @@ pub fn post_upgrade() {
     match applications {
         None => {}
         Some(applications) => APPLICATIONS.with(|apps| {
-            applications.iter().for_each(|a| {
-                apps.borrow_mut().insert(a.to_owned());
+            applications.into_iter().for_each(|a| {
+                apps.borrow_mut().insert(a.domain.clone(), a);
             })
         }),
     }
 }
 
-fn access_point_to_memory_model(ap: AccessPoint) -> AccessPointMemoryModel {
-    AccessPointMemoryModel {
-        principal_id: ap.principal_id,
-        credential_id: ap.credential_id,
-        icon: ap.icon,
-        device: ap.device,
-        browser: ap.browser,
-        last_used: ap.last_used,
-        device_type: Some(ap.device_type),
-        base_fields: ap.base_fields,
-    }
-}
-
 fn access_point_mm_to_ap(ap: AccessPointMemoryModel) -> AccessPoint {
     AccessPoint {
         principal_id: ap.principal_id,
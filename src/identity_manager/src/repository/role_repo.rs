@@ -0,0 +1,139 @@
+use crate::repository::memory::{get_memory, Memory, StorablePrincipal, ROLES_MEMORY_ID};
+use crate::repository::repo::{ConfigurationRepo, ControllersRepo, ADMINS};
+use candid::{CandidType, Deserialize, Principal};
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{StableBTreeMap, Storable};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+thread_local! {
+    /// Granted `(role, principal)` pairs to the time they were granted.
+    static ROLES: RefCell<StableBTreeMap<RoleKey, u64, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(ROLES_MEMORY_ID)));
+}
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Role {
+    Admin,
+    Operator,
+    Lambda,
+}
+
+impl Role {
+    /// Stored as a single byte, so existing variants must keep their byte.
+    fn to_byte(self) -> u8 {
+        match self {
+            Role::Admin => 0,
+            Role::Operator => 1,
+            Role::Lambda => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Role {
+        match byte {
+            0 => Role::Admin,
+            1 => Role::Operator,
+            2 => Role::Lambda,
+            _ => panic!("Failed to decode the role {}.", byte),
+        }
+    }
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct RoleAssignment {
+    pub role: Role,
+    pub principal: Principal,
+    pub granted_at: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct RoleKey {
+    role: Role,
+    principal: StorablePrincipal,
+}
+
+impl Storable for RoleKey {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut bytes = vec![self.role.to_byte()];
+        bytes.extend_from_slice(self.principal.0.as_slice());
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        RoleKey {
+            role: Role::from_byte(bytes[0]),
+            principal: StorablePrincipal(Principal::from_slice(&bytes[1..])),
+        }
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 30,
+        is_fixed_size: false,
+    };
+}
+
+pub struct RoleRepo {}
+
+impl RoleRepo {
+    /// Returns false if the principal already had the role.
+    pub fn grant(role: Role, principal: Principal, time: u64) -> bool {
+        ROLES.with(|roles| {
+            let key = RoleKey { role, principal: StorablePrincipal(principal) };
+            let mut roles = roles.borrow_mut();
+            if roles.contains_key(&key) {
+                return false;
+            }
+            roles.insert(key, time);
+            true
+        })
+    }
+
+    /// Returns false if the role was not granted through the registry.
+    pub fn revoke(role: Role, principal: Principal) -> bool {
+        ROLES.with(|roles| {
+            roles
+                .borrow_mut()
+                .remove(&RoleKey { role, principal: StorablePrincipal(principal) })
+                .is_some()
+        })
+    }
+
+    pub fn get_assignments(role: Option<Role>) -> Vec<RoleAssignment> {
+        ROLES.with(|roles| {
+            roles
+                .borrow()
+                .iter()
+                .filter(|(key, _)| role.map_or(true, |role| role == key.role))
+                .map(|(key, granted_at)| RoleAssignment {
+                    role: key.role,
+                    principal: key.principal.0,
+                    granted_at,
+                })
+                .collect()
+        })
+    }
+
+    /// Besides the registry, admins saved on init, controllers and the principals
+    /// set as `operator` and `lambda` in the configuration keep their roles.
+    pub fn has_role(principal: &Principal, role: Role) -> bool {
+        let granted = ROLES.with(|roles| {
+            roles
+                .borrow()
+                .contains_key(&RoleKey { role, principal: StorablePrincipal(*principal) })
+        });
+        granted
+            || match role {
+                Role::Admin => {
+                    ADMINS.with(|admins| admins.borrow().contains_key(&StorablePrincipal(*principal)))
+                        || ControllersRepo::contains(principal)
+                }
+                Role::Operator => ConfigurationRepo::get().operator.eq(principal),
+                Role::Lambda => ConfigurationRepo::get().lambda.eq(principal),
+            }
+    }
+
+    pub fn has_any_role(principal: &Principal, roles: &[Role]) -> bool {
+        roles.iter().any(|role| Self::has_role(principal, *role))
+    }
+}
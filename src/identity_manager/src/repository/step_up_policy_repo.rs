@@ -0,0 +1,169 @@
+use crate::http::requests::DeviceType;
+use crate::repository::memory::{candid_storable, get_memory, Memory, STEP_UP_POLICY_MEMORY_ID};
+use candid::{CandidType, Deserialize};
+use ic_stable_structures::StableCell;
+use serde::Serialize;
+use std::cell::RefCell;
+
+thread_local! {
+    static STEP_UP_POLICY: RefCell<StableCell<StepUpPolicy, Memory>> = RefCell::new(
+        StableCell::init(get_memory(STEP_UP_POLICY_MEMORY_ID), StepUpPolicy::default())
+            .expect("Failed to initialize the step-up policy stable cell.")
+    );
+}
+
+/// Operations that may need a stronger access point than the one used to sign in.
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum StepUpOperation {
+    SignIn,
+    CreateAccessPoint,
+    UpdateAccessPoint,
+    RemoveAccessPoint,
+    Update2fa,
+    UpdateAccount,
+    UpdateEmail,
+    RemoveAccount,
+    UpdateStepUpRules,
+}
+
+/// Limits the access point types allowed to perform an operation.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub struct StepUpRule {
+    pub operation: StepUpOperation,
+    /// Restricts the rule to operations on access points of this type, e.g. removing a passkey.
+    pub target: Option<DeviceType>,
+    pub allowed: Vec<DeviceType>,
+    /// Applies the rule only to accounts with 2FA enabled.
+    pub only_with_2fa: bool,
+}
+
+impl StepUpRule {
+    pub fn applies(
+        &self,
+        operation: StepUpOperation,
+        target: Option<DeviceType>,
+        is2fa_enabled: bool,
+    ) -> bool {
+        self.operation == operation
+            && (self.target.is_none() || self.target == target)
+            && (is2fa_enabled || !self.only_with_2fa)
+    }
+}
+
+/// The operator rules, every account is subject to them on top of its own rules.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct StepUpPolicy {
+    pub rules: Vec<StepUpRule>,
+}
+
+candid_storable!(StepUpPolicy);
+
+impl Default for StepUpPolicy {
+    /// Blocks email and unknown access points on accounts with 2FA enabled, as before policies were configurable.
+    fn default() -> Self {
+        let operations = [
+            StepUpOperation::SignIn,
+            StepUpOperation::CreateAccessPoint,
+            StepUpOperation::UpdateAccessPoint,
+            StepUpOperation::RemoveAccessPoint,
+            StepUpOperation::Update2fa,
+            StepUpOperation::UpdateAccount,
+            StepUpOperation::UpdateEmail,
+            StepUpOperation::RemoveAccount,
+            StepUpOperation::UpdateStepUpRules,
+        ];
+        StepUpPolicy {
+            rules: operations
+                .into_iter()
+                .map(|operation| StepUpRule {
+                    operation,
+                    target: None,
+                    allowed: vec![
+                        DeviceType::Passkey,
+                        DeviceType::Recovery,
+                        DeviceType::Password,
+                        DeviceType::InternetIdentity,
+                    ],
+                    only_with_2fa: true,
+                })
+                .collect(),
+        }
+    }
+}
+
+pub struct StepUpPolicyRepo {}
+
+impl StepUpPolicyRepo {
+    pub fn get() -> StepUpPolicy {
+        STEP_UP_POLICY.with(|policy| policy.borrow().get().clone())
+    }
+
+    pub fn save(policy: StepUpPolicy) {
+        STEP_UP_POLICY.with(|cell| {
+            cell.borrow_mut()
+                .set(policy)
+                .expect("Failed to save the step-up policy to stable memory.");
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(target: Option<DeviceType>, only_with_2fa: bool) -> StepUpRule {
+        StepUpRule {
+            operation: StepUpOperation::RemoveAccessPoint,
+            target,
+            allowed: vec![DeviceType::Passkey, DeviceType::Recovery],
+            only_with_2fa,
+        }
+    }
+
+    #[test]
+    fn rule_applies_to_its_operation_and_target() {
+        let passkey_removal = rule(Some(DeviceType::Passkey), false);
+        assert!(passkey_removal.applies(
+            StepUpOperation::RemoveAccessPoint,
+            Some(DeviceType::Passkey),
+            false
+        ));
+        assert!(!passkey_removal.applies(
+            StepUpOperation::RemoveAccessPoint,
+            Some(DeviceType::Email),
+            false
+        ));
+        assert!(!passkey_removal.applies(StepUpOperation::RemoveAccessPoint, None, false));
+        assert!(!passkey_removal.applies(
+            StepUpOperation::CreateAccessPoint,
+            Some(DeviceType::Passkey),
+            false
+        ));
+
+        let any_removal = rule(None, false);
+        assert!(any_removal.applies(
+            StepUpOperation::RemoveAccessPoint,
+            Some(DeviceType::Email),
+            false
+        ));
+        assert!(any_removal.applies(StepUpOperation::RemoveAccessPoint, None, false));
+    }
+
+    #[test]
+    fn rule_only_with_2fa_skips_accounts_without_2fa() {
+        let rule = rule(None, true);
+        assert!(rule.applies(StepUpOperation::RemoveAccessPoint, None, true));
+        assert!(!rule.applies(StepUpOperation::RemoveAccessPoint, None, false));
+    }
+
+    #[test]
+    fn default_policy_blocks_email_and_unknown_access_points() {
+        let policy = StepUpPolicy::default();
+        for rule in policy.rules.iter() {
+            assert!(rule.only_with_2fa);
+            assert!(!rule.allowed.contains(&DeviceType::Email));
+            assert!(!rule.allowed.contains(&DeviceType::Unknown));
+            assert!(rule.allowed.contains(&DeviceType::Passkey));
+        }
+    }
+}
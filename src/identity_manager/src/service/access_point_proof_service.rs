@@ -0,0 +1,67 @@
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::{AccessPointChallenge, AccessPointProof};
+use crate::repository::repo::MINUTE_NS;
+use crate::service::ic_service;
+use crate::structure::ttl_hashmap::TtlHashMap;
+use crate::util::access_point_proof::{
+    challenge_message, check_delegation, delegation_message, verify_key_signature,
+    SignedDelegation,
+};
+use crate::util::captcha::random_salt;
+use candid::Principal;
+use serde_bytes::ByteBuf;
+use std::cell::RefCell;
+
+const CHALLENGE_EXPIRATION_NS: u64 = 5 * MINUTE_NS;
+
+thread_local! {
+    /// Caller principal to the nonce issued to it, each caller has at most one open challenge.
+    static CHALLENGES: RefCell<TtlHashMap<String, Vec<u8>>> = RefCell::new(TtlHashMap::new(CHALLENGE_EXPIRATION_NS));
+}
+
+/// Issues a nonce for the caller to have signed by the key of the access point it is about to add.
+pub async fn issue_challenge() -> AccessPointChallenge {
+    let nonce = random_salt().await.to_vec();
+    let caller = ic_service::get_caller();
+    let now = ic_service::get_time();
+    CHALLENGES.with(|challenges| {
+        let mut challenges = challenges.borrow_mut();
+        challenges.clean_expired_entries(now);
+        challenges.insert(caller.to_text(), nonce.clone(), now);
+    });
+    AccessPointChallenge {
+        nonce: ByteBuf::from(nonce),
+        expires_at: now + CHALLENGE_EXPIRATION_NS,
+    }
+}
+
+/// Checks that whoever controls `principal` agreed to it being added by the caller,
+/// either by signing the challenge issued to the caller or a delegation to the caller's session key.
+/// The challenge is consumed by the attempt.
+pub fn verify_proof(principal: &Principal, proof: AccessPointProof) -> Result<(), IdentityManagerError> {
+    let caller = ic_service::get_caller();
+    let message = match proof.delegation.as_ref() {
+        None => {
+            let now = ic_service::get_time();
+            let nonce = CHALLENGES.with(|challenges| {
+                let mut challenges = challenges.borrow_mut();
+                challenges.clean_expired_entries(now);
+                challenges.remove(&caller.to_text())
+            });
+            let nonce = nonce.ok_or(IdentityManagerError::InvalidAccessPointProof)?;
+            challenge_message(&nonce, &caller)
+        }
+        Some(delegation) => {
+            let delegation = SignedDelegation {
+                pubkey: delegation.pubkey.as_slice(),
+                expiration: delegation.expiration,
+                targets: delegation.targets.as_deref(),
+            };
+            check_delegation(&delegation, &caller, ic_service::get_time())
+                .map_err(|_| IdentityManagerError::InvalidAccessPointProof)?;
+            delegation_message(&delegation)
+        }
+    };
+    verify_key_signature(principal, &proof.public_key, &message, &proof.signature)
+        .map_err(|_| IdentityManagerError::InvalidAccessPointProof)
+}
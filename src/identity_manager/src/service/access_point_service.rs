@@ -1,35 +1,44 @@
+use crate::http::error::IdentityManagerError;
 use crate::http::requests::{DeviceType, WalletVariant};
 use crate::ic_service::DeviceData;
 use crate::mapper::access_point_mapper::{
     access_point_request_to_access_point, access_point_to_access_point_response,
-    device_data_to_access_point, recovery_device_data_to_access_point,
+    device_data_to_access_point, openid_access_point_request_to_access_point,
+    recovery_device_data_to_access_point,
 };
 use crate::repository::access_point_repo::{AccessPoint, AccessPointRepoTrait};
+use crate::repository::account_event_repo::AccountEventType;
 use crate::requests::{AccessPointRequest, AccessPointResponse};
-use crate::response_mapper::{to_error_response, to_success_response, HttpResponse};
 use crate::service::account_service::AccountServiceTrait;
-use crate::{get_account_service, ic_service, AccessPointRemoveRequest, Account};
+use crate::repository::account_repo::AccountRepoTrait;
+use crate::repository::openid_repo::OpenIdRepo;
+use crate::service::{access_point_proof_service, audit_service, openid_service};
+use crate::{get_account_repo, get_account_service, ic_service, AccessPointRemoveRequest, Account};
 use async_trait::async_trait;
 use candid::Principal;
-use ic_cdk::{caller, trap};
+use ic_cdk::caller;
 use std::collections::HashSet;
 
 #[async_trait(? Send)]
 pub trait AccessPointServiceTrait {
-    fn read_access_points(&self) -> HttpResponse<Vec<AccessPointResponse>>;
-    fn use_access_point(&self, browser: Option<String>) -> HttpResponse<AccessPointResponse>;
+    fn read_access_points(&self) -> Result<Vec<AccessPointResponse>, IdentityManagerError>;
+    fn use_access_point(
+        &self,
+        browser: Option<String>,
+    ) -> Result<AccessPointResponse, IdentityManagerError>;
     async fn create_access_point(
         &self,
         access_point: AccessPointRequest,
-    ) -> HttpResponse<Vec<AccessPointResponse>>;
+    ) -> Result<Vec<AccessPointResponse>, IdentityManagerError>;
     fn update_access_point(
         &self,
         access_point_request: AccessPointRequest,
-    ) -> HttpResponse<Vec<AccessPointResponse>>;
+    ) -> Result<Vec<AccessPointResponse>, IdentityManagerError>;
     fn remove_access_point(
         &self,
         access_point: AccessPointRemoveRequest,
-    ) -> HttpResponse<Vec<AccessPointResponse>>;
+    ) -> Result<Vec<AccessPointResponse>, IdentityManagerError>;
+    fn remove_other_access_points(&self) -> Result<Vec<AccessPointResponse>, IdentityManagerError>;
     fn migrate_recovery_device(&self, device_data: DeviceData, account: &Account) -> Account;
     fn recover_root_access_point(&self, device: DeviceData, account: Account) -> Account;
 }
@@ -41,70 +50,70 @@ pub struct AccessPointService<T> {
 
 #[async_trait(? Send)]
 impl<T: AccessPointRepoTrait> AccessPointServiceTrait for AccessPointService<T> {
-    fn read_access_points(&self) -> HttpResponse<Vec<AccessPointResponse>> {
-        match self.access_point_repo.get_access_points() {
-            Some(content) => {
-                let response: Vec<AccessPointResponse> = content
-                    .into_iter()
-                    .map(access_point_to_access_point_response)
-                    .collect();
-                to_success_response(response)
-            }
-            None => to_error_response("Unable to find Account."),
-        }
+    fn read_access_points(&self) -> Result<Vec<AccessPointResponse>, IdentityManagerError> {
+        let content = self
+            .access_point_repo
+            .get_access_points()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        Ok(to_access_point_responses(content))
     }
 
-    fn use_access_point(&self, browser: Option<String>) -> HttpResponse<AccessPointResponse> {
+    fn use_access_point(
+        &self,
+        browser: Option<String>,
+    ) -> Result<AccessPointResponse, IdentityManagerError> {
         let principal = ic_service::get_caller().to_text();
-        match self
-            .access_point_repo
-            .use_access_point(principal, ic_service::get_time(), browser)
-        {
-            Some(access_point) => {
-                to_success_response(access_point_to_access_point_response(access_point))
-            }
-            None => to_error_response("Unable to find object."),
-        }
+        let access_point =
+            self.access_point_repo
+                .use_access_point(principal, ic_service::get_time(), browser)?;
+        audit_service::record(AccountEventType::UseAccessPoint, None);
+        Ok(access_point_to_access_point_response(access_point))
     }
 
     async fn create_access_point(
         &self,
         access_point_request: AccessPointRequest,
-    ) -> HttpResponse<Vec<AccessPointResponse>> {
-        match get_account_service().get_account() {
-            Some(acc) => {
-                let mut access_points = acc.access_points;
-                let princ = Principal::from_text(access_point_request.pub_key.clone())
-                    .expect("Failed to parse the public key from the access point request.");
-                match acc.wallet {
-                    WalletVariant::NFID => {
-                        if !acc.wallet.eq(&WalletVariant::NFID) {
-                            trap("Unable to add access point")
-                        }
-                    }
-                    WalletVariant::InternetIdentity => {
-                        ic_service::trap_if_not_authenticated(acc.anchor, princ).await;
-                    }
-                }
-                let access_point =
-                    access_point_request_to_access_point(access_point_request.clone());
-                if access_points.clone().iter().any(|x| x.eq(&access_point)) {
-                    return to_error_response("Access Point exists.");
+    ) -> Result<Vec<AccessPointResponse>, IdentityManagerError> {
+        if let Some(id_token) = access_point_request.id_token.clone() {
+            return self.create_openid_access_point(&id_token, access_point_request);
+        }
+        let acc = get_account_service()
+            .get_account()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        let mut access_points = acc.access_points;
+        let princ = Principal::from_text(access_point_request.pub_key.clone())
+            .expect("Failed to parse the public key from the access point request.");
+        match acc.wallet {
+            WalletVariant::NFID | WalletVariant::OpenId => {
+                // The caller already proved possession of its own key by signing the call.
+                if !princ.eq(&ic_service::get_caller()) {
+                    let proof = access_point_request
+                        .proof
+                        .clone()
+                        .ok_or(IdentityManagerError::AccessPointProofRequired)?;
+                    access_point_proof_service::verify_proof(&princ, proof)?;
                 }
-                access_points.insert(access_point.clone());
-                self.access_point_repo.store_access_points(
-                    access_points.clone()
-                );
-                self.access_point_repo
-                    .update_account_index(access_point.principal_id, acc.principal_id);
-                let response: Vec<AccessPointResponse> = access_points
-                    .into_iter()
-                    .map(access_point_to_access_point_response)
-                    .collect();
-                to_success_response(response)
             }
-            None => to_error_response("Unable to find Account."),
+            WalletVariant::InternetIdentity => {
+                ic_service::trap_if_not_authenticated(acc.anchor, princ).await;
+            }
+        }
+        let access_point = access_point_request_to_access_point(access_point_request.clone());
+        if access_points.iter().any(|x| x.eq(&access_point))
+            || get_account_repo().is_principal_indexed(access_point.principal_id.clone())
+        {
+            return Err(IdentityManagerError::AccessPointExists);
         }
+        access_points.insert(access_point.clone());
+        self.access_point_repo
+            .store_access_points(access_points.clone())?;
+        self.access_point_repo
+            .update_account_index(access_point.principal_id.clone(), acc.principal_id);
+        audit_service::record(
+            AccountEventType::CreateAccessPoint,
+            Some(access_point.principal_id),
+        );
+        Ok(to_access_point_responses(access_points))
     }
 
     fn migrate_recovery_device(&self, device_data: DeviceData, account: &Account) -> Account {
@@ -124,65 +133,82 @@ impl<T: AccessPointRepoTrait> AccessPointServiceTrait for AccessPointService<T>
     fn update_access_point(
         &self,
         access_point_request: AccessPointRequest,
-    ) -> HttpResponse<Vec<AccessPointResponse>> {
-        match self.access_point_repo.get_access_points() {
-            Some(mut content) => {
-                let access_point =
-                    access_point_request_to_access_point(access_point_request.clone());
-                if !content.clone().iter().any(|x| x.eq(&access_point)) {
-                    return to_error_response("Access Point not exists.");
-                }
-                content.replace(access_point.clone());
-                self.access_point_repo.store_access_points(content.clone());
-                let response: Vec<AccessPointResponse> = content
-                    .into_iter()
-                    .map(access_point_to_access_point_response)
-                    .collect();
-                to_success_response(response)
-            }
-            None => to_error_response("Unable to find Account."),
+    ) -> Result<Vec<AccessPointResponse>, IdentityManagerError> {
+        let mut content = self
+            .access_point_repo
+            .get_access_points()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        let access_point = access_point_request_to_access_point(access_point_request.clone());
+        if !content.iter().any(|x| x.eq(&access_point)) {
+            return Err(IdentityManagerError::AccessPointNotFound);
         }
+        content.replace(access_point.clone());
+        self.access_point_repo.store_access_points(content.clone())?;
+        audit_service::record(
+            AccountEventType::UpdateAccessPoint,
+            Some(access_point.principal_id),
+        );
+        Ok(to_access_point_responses(content))
     }
 
     fn remove_access_point(
         &self,
         access_point_request: AccessPointRemoveRequest,
-    ) -> HttpResponse<Vec<AccessPointResponse>> {
-        match self.access_point_repo.get_access_points() {
-            Some(content) => {
-                let principal = access_point_request.pub_key.clone();
-
-                if self.access_point_repo.get_wallet().eq(&WalletVariant::NFID) {
-                    let caller = caller().to_text();
-                    if content
-                        .clone()
-                        .iter()
-                        .filter(|m| m.principal_id.eq(&access_point_request.pub_key))
-                        .filter(|x| x.device_type.eq(&DeviceType::Recovery))
-                        .any(|x| !x.principal_id.eq(&caller))
-                    {
-                        trap("Recovery phrase is protected")
-                    }
-                }
+    ) -> Result<Vec<AccessPointResponse>, IdentityManagerError> {
+        let content = self
+            .access_point_repo
+            .get_access_points()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        let principal = access_point_request.pub_key.clone();
 
-                let aps: HashSet<AccessPoint> = content
-                    .iter()
-                    .filter(|x| x.principal_id != principal)
-                    .cloned()
-                    .collect();
-                if aps.len() == content.len() {
-                    return to_error_response("Access Point not exists.");
-                }
-                self.access_point_repo.store_access_points(aps.clone());
-                self.access_point_repo.remove_ap_index(principal);
-                let response: Vec<AccessPointResponse> = aps
-                    .into_iter()
-                    .map(access_point_to_access_point_response)
-                    .collect();
-                to_success_response(response)
+        if self.access_point_repo.get_wallet()?.eq(&WalletVariant::NFID) {
+            let caller = caller().to_text();
+            if content
+                .iter()
+                .filter(|m| m.principal_id.eq(&access_point_request.pub_key))
+                .filter(|x| x.device_type.eq(&DeviceType::Recovery))
+                .any(|x| !x.principal_id.eq(&caller))
+            {
+                return Err(IdentityManagerError::RecoveryPhraseProtected);
             }
-            None => to_error_response("Unable to find Account."),
         }
+
+        let aps: HashSet<AccessPoint> = content
+            .iter()
+            .filter(|x| x.principal_id != principal)
+            .cloned()
+            .collect();
+        if aps.len() == content.len() {
+            return Err(IdentityManagerError::AccessPointNotFound);
+        }
+        audit_service::record(AccountEventType::RemoveAccessPoint, Some(principal.clone()));
+        self.access_point_repo.store_access_points(aps.clone())?;
+        self.access_point_repo.remove_ap_index(principal);
+        Ok(to_access_point_responses(aps))
+    }
+
+    fn remove_other_access_points(&self) -> Result<Vec<AccessPointResponse>, IdentityManagerError> {
+        let content = self
+            .access_point_repo
+            .get_access_points()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        let caller = caller().to_text();
+        if !content.iter().any(|x| x.principal_id.eq(&caller)) {
+            return Err(IdentityManagerError::NotAnAccessPoint);
+        }
+
+        // Recovery phrases are protected the same way as in `remove_access_point`, so they are never revoked.
+        let (aps, removed): (HashSet<AccessPoint>, HashSet<AccessPoint>) =
+            content.into_iter().partition(|x| {
+                x.principal_id.eq(&caller) || x.device_type.eq(&DeviceType::Recovery)
+            });
+        let removed: Vec<String> = removed.into_iter().map(|x| x.principal_id).collect();
+        removed.iter().for_each(|principal| {
+            audit_service::record(AccountEventType::RemoveAccessPoint, Some(principal.clone()))
+        });
+        self.access_point_repo.store_access_points(aps.clone())?;
+        self.access_point_repo.remove_ap_indexes(removed);
+        Ok(to_access_point_responses(aps))
     }
 
     fn recover_root_access_point(&self, device: DeviceData, mut account: Account) -> Account {
@@ -199,3 +225,55 @@ impl<T: AccessPointRepoTrait> AccessPointServiceTrait for AccessPointService<T>
         account_updated.expect("Failed to update access points for the account.")
     }
 }
+
+impl<T: AccessPointRepoTrait> AccessPointService<T> {
+    /// Adds the caller to the OpenID account bound to the subject of its ID token.
+    /// The caller does not need to be an access point yet, the token nonce binds it to the caller instead.
+    fn create_openid_access_point(
+        &self,
+        id_token: &str,
+        access_point_request: AccessPointRequest,
+    ) -> Result<Vec<AccessPointResponse>, IdentityManagerError> {
+        let subject = openid_service::verify_caller_id_token(id_token)?.subject_key();
+        let caller = ic_service::get_caller().to_text();
+        if !access_point_request.pub_key.eq(&caller) {
+            return Err(IdentityManagerError::Forbidden);
+        }
+        let root = OpenIdRepo::get_root_by_subject(&subject)
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        let account_repo = get_account_repo();
+        // An account pending removal is hidden from lookups, it cannot gain access points either.
+        let acc = account_repo
+            .get_account_by_root(root.clone())
+            .filter(|acc| acc.pending_removal.is_none())
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        if account_repo.is_principal_indexed(caller.clone()) {
+            return Err(IdentityManagerError::AccessPointExists);
+        }
+        let mut access_points = acc.access_points.clone();
+        let access_point = openid_access_point_request_to_access_point(access_point_request);
+        if access_points.iter().any(|x| x.eq(&access_point)) {
+            return Err(IdentityManagerError::AccessPointExists);
+        }
+        access_points.insert(access_point.clone());
+        self.access_point_repo
+            .store_access_points_by_principal(access_points.clone(), root.clone())
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        self.access_point_repo
+            .update_account_index(access_point.principal_id.clone(), root);
+        audit_service::record_for_account(
+            &acc,
+            Some(caller),
+            AccountEventType::CreateAccessPoint,
+            Some(access_point.principal_id),
+        );
+        Ok(to_access_point_responses(access_points))
+    }
+}
+
+fn to_access_point_responses(access_points: HashSet<AccessPoint>) -> Vec<AccessPointResponse> {
+    access_points
+        .into_iter()
+        .map(access_point_to_access_point_response)
+        .collect()
+}
@@ -1,45 +1,63 @@
 use async_trait::async_trait;
 use ic_cdk::api::time;
-use ic_cdk::{caller, trap};
+use ic_cdk::caller;
 
-use crate::http::requests::{AccountResponse, ChallengeAttempt, DeviceType, WalletVariant};
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::{
+    AccountResponse, ChallengeAttempt, DeviceType, PrincipalResolution, WalletVariant,
+};
 use crate::ic_service::KeyType;
-use crate::mapper::access_point_mapper::access_point_request_to_access_point;
+use crate::mapper::access_point_mapper::{
+    access_point_request_to_access_point, email_principal_to_access_point,
+    openid_access_point_request_to_access_point,
+};
 use crate::mapper::account_mapper::{account_request_to_account, account_to_account_response};
+use crate::repository::account_event_repo::AccountEventType;
 use crate::repository::account_repo::AccountRepoTrait;
-use crate::requests::AccountRequest;
-use crate::response_mapper::{to_error_response, to_success_response, ErrorResponse};
+use crate::repository::anchor_repo::AnchorRepo;
+use crate::repository::openid_repo::OpenIdRepo;
+use crate::requests::{AccountRequest, AccountUpdateRequest};
 use crate::service::ic_service;
 use crate::service::ic_service::DeviceData;
-use crate::repository::access_point_repo::AccessPoint;
-use crate::{get_caller, AccessPointServiceTrait, Account, HttpResponse};
-use crate::repository::repo::{CAPTCHA_CAHLLENGES, TEMP_KEYS};
-use super::email_validation_service;
+use crate::{get_caller, AccessPointServiceTrait, Account};
+use crate::repository::repo::{ConfigurationRepo, TEMP_KEYS};
+use crate::repository::step_up_policy_repo::StepUpOperation;
+use crate::util::captcha;
+use super::{audit_service, email_validation_service, openid_service, removal_service, security_service};
 
 #[async_trait(? Send)]
 pub trait AccountServiceTrait {
-    fn get_account_response(&mut self) -> HttpResponse<AccountResponse>;
+    fn get_account_response(&mut self) -> Result<AccountResponse, IdentityManagerError>;
     fn get_account(&mut self) -> Option<Account>;
-    fn update_2fa(&mut self, state: bool) -> AccountResponse;
+    fn update_2fa(&mut self, state: bool) -> Result<AccountResponse, IdentityManagerError>;
+    fn update_account(
+        &mut self,
+        account_update_request: AccountUpdateRequest,
+    ) -> Result<AccountResponse, IdentityManagerError>;
     async fn create_account(
         &mut self,
         account_request: AccountRequest,
-    ) -> HttpResponse<AccountResponse>;
-    fn remove_account(&mut self) -> HttpResponse<bool>;
+    ) -> Result<AccountResponse, IdentityManagerError>;
+    fn remove_account(&mut self) -> Result<bool, IdentityManagerError>;
     fn get_account_by_anchor(
         &mut self,
         anchor: u64,
         wallet: WalletVariant,
-    ) -> HttpResponse<AccountResponse>;
-    fn get_account_by_principal(&mut self, princ: String) -> HttpResponse<AccountResponse>;
+    ) -> Result<AccountResponse, IdentityManagerError>;
+    fn get_account_by_principal(
+        &mut self,
+        princ: String,
+    ) -> Result<AccountResponse, IdentityManagerError>;
     fn get_root_id_by_principal(&mut self, princ: String) -> Option<String>;
     fn get_anchor_by_principal(&mut self, princ: String) -> Option<u64>;
+    fn resolve_principals(&mut self, principals: Vec<String>) -> Vec<PrincipalResolution>;
     fn get_all_accounts(&mut self) -> Vec<Account>;
     async fn sync_recovery_phrase_from_internet_identity(
         &self,
         anchor: u64,
-    ) -> HttpResponse<AccountResponse>;
-    fn validate_captcha(&self, challenge_attempt: ChallengeAttempt);
+    ) -> Result<AccountResponse, IdentityManagerError>;
+    async fn migrate_to_nfid(&mut self) -> Result<AccountResponse, IdentityManagerError>;
+    fn validate_captcha(&self, challenge_attempt: ChallengeAttempt) -> Result<(), IdentityManagerError>;
 }
 
 #[derive(Default)]
@@ -50,151 +68,232 @@ pub struct AccountService<T, A> {
 
 #[async_trait(? Send)]
 impl<T: AccountRepoTrait, A: AccessPointServiceTrait> AccountServiceTrait for AccountService<T, A> {
-    fn get_account_response(&mut self) -> HttpResponse<AccountResponse> {
-        match self.account_repo.get_account() {
-            Some(content) => to_success_response(account_to_account_response(content.clone())),
-            None => to_error_response("Unable to find Account"),
-        }
+    fn get_account_response(&mut self) -> Result<AccountResponse, IdentityManagerError> {
+        self.account_repo
+            .get_account()
+            .map(account_to_account_response)
+            .ok_or(IdentityManagerError::AccountNotFound)
     }
 
     fn get_account(&mut self) -> Option<Account> {
         self.account_repo.get_account()
     }
 
-    fn update_2fa(&mut self, state: bool) -> AccountResponse {
-        match self.account_repo.get_account() {
-            None => trap("No such Account"),
-            Some(mut acc) => {
-                if !acc
-                    .access_points
-                    .clone()
-                    .into_iter()
-                    .any(|l| l.device_type.eq(&DeviceType::Passkey))
-                {
-                    trap("Forbidden")
-                }
-                acc.is2fa_enabled = state;
-                self.account_repo.store_account(acc.clone());
-                account_to_account_response(acc)
+    fn update_2fa(&mut self, state: bool) -> Result<AccountResponse, IdentityManagerError> {
+        let mut acc = self
+            .account_repo
+            .get_account()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        if !acc
+            .access_points
+            .iter()
+            .any(|l| l.device_type.eq(&DeviceType::Passkey))
+        {
+            return Err(IdentityManagerError::Forbidden);
+        }
+        acc.is2fa_enabled = state;
+        self.account_repo.store_account(acc.clone());
+        audit_service::record_for_account(
+            &acc,
+            Some(caller().to_text()),
+            AccountEventType::Update2fa,
+            None,
+        );
+        Ok(account_to_account_response(acc))
+    }
+
+    fn update_account(
+        &mut self,
+        account_update_request: AccountUpdateRequest,
+    ) -> Result<AccountResponse, IdentityManagerError> {
+        let mut acc = self
+            .account_repo
+            .get_account()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        if account_update_request.name.is_some() {
+            acc.name = account_update_request.name;
+        }
+        let mut replaced_email = None;
+        if let Some(email) = account_update_request.email {
+            if !acc.wallet.eq(&WalletVariant::NFID) {
+                return Err(IdentityManagerError::NfidOnly);
+            }
+            security_service::check_operation(StepUpOperation::UpdateEmail, Some(DeviceType::Email))?;
+            // The lambda issues the validation for the principal of the new email access point.
+            // It is used up only once every check has passed, so a rejected update leaves it valid.
+            let email_principal = email_validation_service::validated_principal(email.clone())
+                .ok_or(IdentityManagerError::EmailNotValidated)?;
+            if self.account_repo.is_principal_indexed(email_principal.clone()) {
+                return Err(IdentityManagerError::AccessPointExists);
             }
+            email_validation_service::consume(email.clone());
+            let previous: Vec<String> = acc
+                .access_points
+                .iter()
+                .filter(|ap| ap.device_type.eq(&DeviceType::Email))
+                .map(|ap| ap.principal_id.clone())
+                .collect();
+            acc.access_points
+                .retain(|ap| !ap.device_type.eq(&DeviceType::Email));
+            acc.access_points
+                .insert(email_principal_to_access_point(email_principal.clone()));
+            acc.email = Some(email);
+            acc.email_verified_at = Some(time());
+            replaced_email = Some((previous, email_principal));
+        }
+        acc.base_fields = acc.base_fields.with_modified_now();
+        self.account_repo.store_account(acc.clone());
+        if let Some((previous, email_principal)) = replaced_email {
+            previous
+                .into_iter()
+                .for_each(|principal| self.account_repo.remove_account_index(principal));
+            self.account_repo
+                .update_account_index_with_pub_key(email_principal.clone(), acc.principal_id.clone());
+            audit_service::record_for_account(
+                &acc,
+                Some(caller().to_text()),
+                AccountEventType::UpdateEmail,
+                Some(email_principal),
+            );
         }
+        Ok(account_to_account_response(acc))
     }
 
     async fn create_account(
         &mut self,
         account_request: AccountRequest,
-    ) -> HttpResponse<AccountResponse> {
+    ) -> Result<AccountResponse, IdentityManagerError> {
         let princ = ic_service::get_caller().to_text();
         if ic_service::is_anonymous(princ.clone()) {
-            return to_error_response("User is anonymous");
+            return Err(IdentityManagerError::AnonymousCaller);
         }
         let mut devices: Vec<DeviceData> = Vec::default();
         let mut acc = account_request_to_account(account_request.clone());
-        if account_request.email.is_some()
-            && !email_validation_service::contains(
-                account_request.email.clone().expect("Failed to retrieve the email from the account request.").to_string(),
-                princ.clone(),
-            ) {
-                trap("Email and principal are not valid.")
+        if let Some(email) = account_request.email.clone() {
+            if !email_validation_service::contains(email, princ.clone()) {
+                return Err(IdentityManagerError::EmailNotValidated);
             }
+            acc.email_verified_at = Some(time());
+        }
         if acc.wallet.eq(&WalletVariant::NFID) {
-            match account_request.access_point.clone() {
-                None => trap("Device Data required"),
-                Some(dd) => {
-                    acc.access_points
-                        .insert(access_point_request_to_access_point(dd.clone()));
-                }
+            let dd = account_request
+                .access_point
+                .clone()
+                .ok_or(IdentityManagerError::DeviceDataRequired)?;
+            if account_request.email.is_none() && dd.device_type.eq(&DeviceType::Email) {
+                return Err(IdentityManagerError::EmailRequired);
             }
-            let device_type = account_request.access_point.clone().unwrap().device_type;
-            if account_request.email.is_none() && device_type.eq(&DeviceType::Email) {
-                trap("Email is empty");
+            if account_request.name.is_none() && dd.device_type.eq(&DeviceType::Passkey) {
+                return Err(IdentityManagerError::NameRequired);
             }
-            if account_request.name.is_none() && device_type.eq(&DeviceType::Passkey) {
-                trap("Name is empty");
+            acc.access_points
+                .insert(access_point_request_to_access_point(dd));
+        } else if acc.wallet.eq(&WalletVariant::OpenId) {
+            let id_token = account_request
+                .id_token
+                .clone()
+                .ok_or(IdentityManagerError::IdTokenRequired)?;
+            let subject = openid_service::verify_caller_id_token(&id_token)?.subject_key();
+            if OpenIdRepo::get_root_by_subject(&subject).is_some() {
+                return Err(IdentityManagerError::AccountExists);
             }
-            let anchor = self.account_repo.find_next_nfid_anchor();
-            acc.anchor = anchor;
+            if let Some(dd) = account_request.access_point.clone() {
+                acc.access_points
+                    .insert(openid_access_point_request_to_access_point(dd));
+            }
+            acc.openid_subject = Some(subject);
         } else {
             devices = ic_service::trap_if_not_authenticated(acc.anchor, get_caller()).await;
         }
         let access_point = account_request.access_point.clone();
         let is_ii_device = access_point.is_some() && access_point.unwrap().device_type.eq(&DeviceType::InternetIdentity);
         if account_request.name.is_some() && !is_ii_device {
-            let challenge_attempt = account_request.challenge_attempt.clone().unwrap_or_else(|| {
-                trap("Challenge solution required");
-            });
-            self.validate_captcha(challenge_attempt);
+            let challenge_attempt = account_request
+                .challenge_attempt
+                .clone()
+                .ok_or(IdentityManagerError::ChallengeRequired)?;
+            self.validate_captcha(challenge_attempt)?;
             acc.name = account_request.name.clone();
-            acc.is2fa_enabled = true;
+            // OpenID access points are not privileged, so the step-up policy of a 2FA account would lock them out.
+            acc.is2fa_enabled = !acc.wallet.eq(&WalletVariant::OpenId);
         }
-        match self.account_repo.create_account(acc) {
-            None => to_error_response("Impossible to link this II anchor, please try another one."),
-            Some(mut new_acc) => {
-                if new_acc.name.is_some() {
-                    TEMP_KEYS.with(|keys| {
-                        keys.borrow_mut().clean_expired_entries(time());
-                        keys.borrow_mut().insert(princ.clone(), new_acc.anchor, time());
-                    });
-                }
-                let recovery_device = devices
-                    .into_iter()
-                    .find(|dd| dd.key_type.eq(&KeyType::SeedPhrase));
-                match recovery_device {
-                    None => {}
-                    Some(rd) => {
-                        new_acc = self.access_point_service.migrate_recovery_device(rd, &new_acc);
-                    }
-                }
-                to_success_response(account_to_account_response(new_acc))
+        // Allocated once every check has passed, a rejected request must not use up an anchor.
+        if self.account_repo.is_principal_indexed(acc.principal_id.clone()) {
+            return Err(IdentityManagerError::AccountExists);
+        }
+        if acc
+            .access_points
+            .iter()
+            .any(|ap| self.account_repo.is_principal_indexed(ap.principal_id.clone()))
+        {
+            return Err(IdentityManagerError::AccessPointExists);
+        }
+        if !acc.wallet.eq(&WalletVariant::InternetIdentity) {
+            acc.anchor = AnchorRepo::allocate(acc.wallet)?;
+        }
+        let mut new_acc = self.account_repo.create_account(acc)?;
+        if let Some(subject) = new_acc.openid_subject.clone() {
+            OpenIdRepo::bind_subject(subject, new_acc.principal_id.clone());
+        }
+        if new_acc.name.is_some() {
+            TEMP_KEYS.with(|keys| {
+                keys.borrow_mut().clean_expired_entries(time());
+                keys.borrow_mut().insert(princ.clone(), new_acc.anchor, time());
+            });
+        }
+        let recovery_device = devices
+            .into_iter()
+            .find(|dd| dd.key_type.eq(&KeyType::SeedPhrase));
+        match recovery_device {
+            None => {}
+            Some(rd) => {
+                new_acc = self.access_point_service.migrate_recovery_device(rd, &new_acc);
             }
         }
+        Ok(account_to_account_response(new_acc))
     }
 
-    fn remove_account(&mut self) -> HttpResponse<bool> {
+    fn remove_account(&mut self) -> Result<bool, IdentityManagerError> {
         let principal = caller().to_text();
-        let account = match self.account_repo.get_account() {
-            None => return HttpResponse::error(404, "Unable to remove Account"),
-            Some(account) => account,
-        };
-
-        if account.is2fa_enabled {
-            match find_access_point(&account, &DeviceType::Passkey) {
-                None => return HttpResponse::error(403, "Unauthorised: no passkey found"),
-                Some(device) if !device.principal_id.eq(&principal) => {
-                    return HttpResponse::error(403, "Unauthorised: passkey required to delete account");
-                }
-                _ => {}
-            }
-        } else {
-            if let Some(device) = find_access_point(&account, &DeviceType::Recovery) {
-                if !device.principal_id.eq(&principal) {
-                    return HttpResponse::error(403, "Unauthorised: seed phrase required to delete account");
-                }
-            }
-        }
+        let account = self
+            .account_repo
+            .get_account()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        removal_service::authorize(&account, &principal)?;
 
-        match self.account_repo.remove_account() {
-            None => to_error_response("Unable to remove Account"),
-            Some(_) => to_success_response(true),
+        audit_service::record_for_account(&account, Some(principal.clone()), AccountEventType::RemoveAccount, None);
+        let grace_period = ConfigurationRepo::get()
+            .account_removal_grace_period
+            .unwrap_or_default();
+        if !grace_period.is_zero() {
+            removal_service::request_removal(account, principal, grace_period);
+            return Ok(true);
         }
+        self.account_repo
+            .remove_account()
+            .map(|_| true)
+            .ok_or(IdentityManagerError::AccountNotFound)
     }
 
     fn get_account_by_anchor(
         &mut self,
         anchor: u64,
         wallet: WalletVariant,
-    ) -> HttpResponse<AccountResponse> {
-        match self.account_repo.get_account_by_anchor(anchor, wallet) {
-            None => to_error_response("Anchor not registered."),
-            Some(acc) => to_success_response(account_to_account_response(acc)),
-        }
+    ) -> Result<AccountResponse, IdentityManagerError> {
+        self.account_repo
+            .get_account_by_anchor(anchor, wallet)
+            .map(account_to_account_response)
+            .ok_or(IdentityManagerError::AnchorNotRegistered)
     }
 
-    fn get_account_by_principal(&mut self, princ: String) -> HttpResponse<AccountResponse> {
-        match self.account_repo.get_account_by_principal(princ) {
-            None => to_error_response("Principal not registered."),
-            Some(acc) => to_success_response(account_to_account_response(acc)),
-        }
+    fn get_account_by_principal(
+        &mut self,
+        princ: String,
+    ) -> Result<AccountResponse, IdentityManagerError> {
+        self.account_repo
+            .get_account_by_principal(princ)
+            .map(account_to_account_response)
+            .ok_or(IdentityManagerError::PrincipalNotRegistered)
     }
 
     fn get_root_id_by_principal(&mut self, princ: String) -> Option<String> {
@@ -211,6 +310,21 @@ impl<T: AccountRepoTrait, A: AccessPointServiceTrait> AccountServiceTrait for Ac
         }
     }
 
+    fn resolve_principals(&mut self, principals: Vec<String>) -> Vec<PrincipalResolution> {
+        principals
+            .into_iter()
+            .map(|principal| {
+                let account = self.account_repo.get_account_by_principal(principal.clone());
+                PrincipalResolution {
+                    principal,
+                    root: account.as_ref().map(|acc| acc.principal_id.clone()),
+                    anchor: account.as_ref().map(|acc| acc.anchor),
+                    wallet: account.as_ref().map(|acc| acc.wallet),
+                }
+            })
+            .collect()
+    }
+
     fn get_all_accounts(&mut self) -> Vec<Account> {
         self.account_repo.get_all_accounts()
     }
@@ -218,61 +332,68 @@ impl<T: AccountRepoTrait, A: AccessPointServiceTrait> AccountServiceTrait for Ac
     async fn sync_recovery_phrase_from_internet_identity(
         &self,
         anchor: u64,
-    ) -> HttpResponse<AccountResponse> {
+    ) -> Result<AccountResponse, IdentityManagerError> {
         let devices = ic_service::trap_if_not_authenticated(anchor, ic_service::get_caller()).await;
 
-        let account = match self
+        let account = self
             .account_repo
             .get_account_by_anchor(anchor, WalletVariant::InternetIdentity)
-        {
-            None => {
-                return to_error_response(
-                    "There is no Internet Identity account by the anchor in Identity Manager.",
-                )
-            }
-            Some(account) => account,
-        };
+            .ok_or(IdentityManagerError::AnchorNotRegistered)?;
 
-        let account_response = devices
+        devices
             .iter()
             .find(|device_data| device_data.key_type.eq(&ic_service::KeyType::SeedPhrase))
             .map(|device_data| {
                 self.access_point_service
                     .migrate_recovery_device(device_data.clone(), &account)
             })
-            .map(|account| to_success_response(account_to_account_response(account)))
-            .unwrap_or_else(|| {
-                to_error_response("The user has no recovery phrase in Internet Identity.")
-            });
-
-        account_response
+            .map(account_to_account_response)
+            .ok_or(IdentityManagerError::NoRecoveryPhrase)
     }
 
+    async fn migrate_to_nfid(&mut self) -> Result<AccountResponse, IdentityManagerError> {
+        let caller = ic_service::get_caller();
+        let account = self
+            .account_repo
+            .get_account()
+            .ok_or(IdentityManagerError::AccountNotFound)?;
+        if !account.wallet.eq(&WalletVariant::InternetIdentity) {
+            return Err(IdentityManagerError::InternetIdentityOnly);
+        }
+        let ii_anchor = account.anchor;
+        let devices = ic_service::trap_if_not_authenticated(ii_anchor, caller).await;
 
-     fn validate_captcha(&self, challenge_attempt: ChallengeAttempt) {
-        CAPTCHA_CAHLLENGES.with(|challenges| {
-            let mut challenges = challenges.borrow_mut();
-            challenges.clean_expired_entries(time());
-
-            let challenge = challenges.remove(&challenge_attempt.challenge_key)
-                .unwrap_or_else(||
-                    { trap("Incorrect captcha key"); }
-                );
-            match challenge {
-                None => {}
-                Some(challenge) => {
-                    let challenge_attempt_chars = challenge_attempt.chars.unwrap_or_else(||
-                        { trap("Solution is required"); }
-                    );
-                    if !challenge.eq(&challenge_attempt_chars) {
-                        trap("Incorrect captcha solution");
-                    }
-                }
-            }
-        });
+        // The account is read again, it may have been changed or migrated while Internet Identity was called.
+        let mut account = self
+            .account_repo
+            .get_account_by_anchor(ii_anchor, WalletVariant::InternetIdentity)
+            .filter(|acc| acc.principal_id.eq(&account.principal_id))
+            .ok_or(IdentityManagerError::AnchorNotRegistered)?;
+        // Allocated before any state is written, so a failed allocation leaves the account untouched.
+        let anchor = AnchorRepo::allocate(WalletVariant::NFID)?;
+        if let Some(recovery_device) = devices
+            .into_iter()
+            .find(|dd| dd.key_type.eq(&KeyType::SeedPhrase))
+        {
+            account = self
+                .access_point_service
+                .migrate_recovery_device(recovery_device, &account);
+        }
+        account.anchor = anchor;
+        account.wallet = WalletVariant::NFID;
+        account.ii_anchor = Some(ii_anchor);
+        account.base_fields = account.base_fields.with_modified_now();
+        self.account_repo.store_account(account.clone());
+        audit_service::record_for_account(
+            &account,
+            Some(caller.to_text()),
+            AccountEventType::MigrateToNfid,
+            None,
+        );
+        Ok(account_to_account_response(account))
     }
-}
 
-fn find_access_point<'a>(account: &'a Account, device_type: &DeviceType) -> Option<&'a AccessPoint> {
-    account.access_points.iter().find(|ap| ap.device_type.eq(device_type))
+    fn validate_captcha(&self, challenge_attempt: ChallengeAttempt) -> Result<(), IdentityManagerError> {
+        captcha::verify_challenge_attempt(challenge_attempt)
+    }
 }
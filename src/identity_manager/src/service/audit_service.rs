@@ -0,0 +1,76 @@
+use crate::container::container_wrapper::get_account_repo;
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::AccountEventsResponse;
+use crate::ic_service;
+use crate::repository::account_event_repo::{
+    AccountEvent, AccountEventRepo, AccountEventRepoTrait, AccountEventType,
+};
+use crate::repository::account_repo::{Account, AccountRepoTrait};
+use crate::response_mapper::{to_success_response, HttpResponse};
+
+const DEFAULT_PAGE_SIZE: u64 = 20;
+const MAX_PAGE_SIZE: u64 = 100;
+
+/// Records an event on the caller's account. Does nothing if the caller has no account.
+pub fn record(event_type: AccountEventType, target_principal_id: Option<String>) {
+    let caller = ic_service::get_caller().to_text();
+    if let Some(account) = get_account_repo().get_account() {
+        record_for_account(&account, Some(caller), event_type, target_principal_id);
+    }
+}
+
+/// Records an event on the account, taking the device type and browser from the caller's access point.
+pub fn record_for_account(
+    account: &Account,
+    caller: Option<String>,
+    event_type: AccountEventType,
+    target_principal_id: Option<String>,
+) {
+    let access_point = caller.as_ref().and_then(|caller| {
+        account
+            .access_points
+            .iter()
+            .find(|ap| ap.principal_id.eq(caller))
+    });
+    AccountEventRepo::default().append(
+        account.principal_id.clone(),
+        AccountEvent {
+            seq: 0,
+            event_type,
+            principal_id: caller.clone(),
+            device_type: access_point.map(|ap| ap.device_type),
+            browser: access_point.and_then(|ap| ap.browser.clone()),
+            target_principal_id,
+            timestamp: ic_service::get_time(),
+        },
+    );
+}
+
+pub fn get_account_events(before: Option<u64>, limit: Option<u64>) -> HttpResponse<AccountEventsResponse> {
+    match get_account_repo().get_account() {
+        None => HttpResponse::from(IdentityManagerError::AccountNotFound),
+        Some(account) => to_success_response(get_events(account.principal_id, before, limit)),
+    }
+}
+
+pub fn get_account_events_by_principal(
+    princ: String,
+    before: Option<u64>,
+    limit: Option<u64>,
+) -> HttpResponse<AccountEventsResponse> {
+    let account_repo = get_account_repo();
+    // Removed accounts are no longer indexed, so their events are still reachable by the root principal.
+    let account = account_repo
+        .get_account_by_principal(princ.clone())
+        .or_else(|| account_repo.get_account_by_root(princ));
+    match account {
+        None => HttpResponse::from(IdentityManagerError::PrincipalNotRegistered),
+        Some(account) => to_success_response(get_events(account.principal_id, before, limit)),
+    }
+}
+
+fn get_events(root: String, before: Option<u64>, limit: Option<u64>) -> AccountEventsResponse {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let (events, next) = AccountEventRepo::default().get_events(root, before, limit);
+    AccountEventsResponse { events, next }
+}
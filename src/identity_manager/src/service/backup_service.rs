@@ -0,0 +1,179 @@
+use std::cell::RefCell;
+use std::ops::Bound::{Excluded, Unbounded};
+
+use candid::{decode_one, encode_one};
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::{
+    AccountsChunk, AccountsExportRequest, AccountsImportRequest, AccountsImportResponse,
+    BackupFormat,
+};
+use crate::repository::account_repo::{Account, AccountRepo, AccountRepoTrait, ACCOUNTS};
+use crate::repository::openid_repo::OpenIdRepo;
+use crate::service::{recovery_service, removal_service};
+
+const DEFAULT_CHUNK_SIZE: u32 = 500;
+/// Keeps a chunk well below the 3MB reply limit of a query.
+const MAX_CHUNK_SIZE: u32 = 2_000;
+
+thread_local! {
+    /// Checksum of the last imported chunk, the next chunk has to continue it.
+    /// Lives on the heap, so an interrupted import restarts from the first chunk after an upgrade.
+    /// A request flagged as the first chunk starts a new chain.
+    static IMPORT_CHECKSUM: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Returns the accounts after the cursor of the request.
+/// Accounts are keyed by their root principal, so the cursor stays valid while accounts are added.
+pub fn export_accounts(request: AccountsExportRequest) -> AccountsChunk {
+    let limit = request
+        .limit
+        .unwrap_or(DEFAULT_CHUNK_SIZE)
+        .clamp(1, MAX_CHUNK_SIZE) as usize;
+    let start = request.cursor.map_or(Unbounded, Excluded);
+
+    let (accounts, has_more): (Vec<Account>, bool) = ACCOUNTS.with(|accounts| {
+        let accounts = accounts.borrow();
+        let mut range = accounts.range((start, Unbounded)).map(|(_, account)| account);
+        let chunk = range.by_ref().take(limit).collect();
+        (chunk, range.next().is_some())
+    });
+
+    let data = encode(&accounts, request.format);
+    let previous = request.checksum.map(|checksum| checksum.into_vec()).unwrap_or_default();
+    AccountsChunk {
+        checksum: ByteBuf::from(chain_checksum(&previous, &data)),
+        count: accounts.len() as u32,
+        next_cursor: if has_more {
+            accounts.last().map(|account| account.principal_id.clone())
+        } else {
+            None
+        },
+        data: ByteBuf::from(data),
+    }
+}
+
+/// Restores the accounts of an exported chunk, chunks have to be imported in the order they were exported.
+/// Each account is written with its root and access points added to `PRINCIPAL_INDEX` and the certified tree,
+/// its OpenID subject bound and its pending recovery or removal scheduled again.
+pub fn import_accounts(
+    request: AccountsImportRequest,
+) -> Result<AccountsImportResponse, IdentityManagerError> {
+    let previous = if request.first_chunk.unwrap_or_default() {
+        Vec::new()
+    } else {
+        IMPORT_CHECKSUM.with(|checksum| checksum.borrow().clone())
+    };
+    let checksum = chain_checksum(&previous, &request.data);
+    if checksum != request.checksum.into_vec() {
+        return Err(IdentityManagerError::BackupChecksumMismatch);
+    }
+    let accounts = decode(&request.data, request.format)?;
+
+    let account_repo = AccountRepo {};
+    for account in accounts.iter() {
+        // Stored first, so the anchor of the account is certified along with its principals.
+        account_repo.store_account(account.clone());
+        account_repo.update_account_index(account.principal_id.clone());
+        for access_point in account.access_points.iter() {
+            account_repo.update_account_index_with_pub_key(
+                access_point.principal_id.clone(),
+                account.principal_id.clone(),
+            );
+        }
+        if let Some(subject) = account.openid_subject.clone() {
+            OpenIdRepo::bind_subject(subject, account.principal_id.clone());
+        }
+        recovery_service::restore_pending_recovery(account);
+        removal_service::restore_pending_removal(account);
+    }
+
+    IMPORT_CHECKSUM.with(|stored| *stored.borrow_mut() = checksum.clone());
+    Ok(AccountsImportResponse {
+        imported: accounts.len() as u32,
+        checksum: ByteBuf::from(checksum),
+    })
+}
+
+/// SHA-256 of the checksum of the previous chunk followed by the data of this one.
+fn chain_checksum(previous: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut hasher = Sha256::new();
+    hasher.update(previous);
+    hasher.update(data);
+    hasher.finalize().to_vec()
+}
+
+fn encode(accounts: &[Account], format: BackupFormat) -> Vec<u8> {
+    match format {
+        BackupFormat::Candid => encode_one(accounts).expect("Failed to encode the accounts to Candid."),
+        BackupFormat::Cbor => {
+            serde_cbor::to_vec(accounts).expect("Failed to encode the accounts to CBOR.")
+        }
+    }
+}
+
+fn decode(data: &[u8], format: BackupFormat) -> Result<Vec<Account>, IdentityManagerError> {
+    match format {
+        BackupFormat::Candid => decode_one(data).map_err(|_| IdentityManagerError::InvalidBackup),
+        BackupFormat::Cbor => {
+            serde_cbor::from_slice(data).map_err(|_| IdentityManagerError::InvalidBackup)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::requests::WalletVariant;
+    use crate::repository::repo::BasicEntity;
+    use std::collections::HashSet;
+
+    fn account(principal_id: &str, anchor: u64) -> Account {
+        Account {
+            anchor,
+            principal_id: principal_id.to_string(),
+            name: Some("name".to_string()),
+            phone_number: None,
+            phone_number_sha2: None,
+            personas: vec![],
+            access_points: HashSet::new(),
+            base_fields: BasicEntity::default(),
+            wallet: WalletVariant::NFID,
+            is2fa_enabled: false,
+            email: Some("test@test.test".to_string()),
+            pending_recovery: None,
+            email_verified_at: None,
+            openid_subject: None,
+            pending_removal: None,
+            ii_anchor: None,
+            step_up_rules: None,
+        }
+    }
+
+    #[test]
+    fn round_trips_both_formats() {
+        let accounts = vec![account("a", 100_000_000), account("b", 100_000_001)];
+        for format in [BackupFormat::Candid, BackupFormat::Cbor] {
+            let decoded = decode(&encode(&accounts, format), format).unwrap();
+            assert_eq!(decoded.len(), 2);
+            assert_eq!(decoded[1].principal_id, "b");
+            assert_eq!(decoded[1].anchor, 100_000_001);
+        }
+        assert_eq!(
+            decode(b"garbage", BackupFormat::Cbor).err(),
+            Some(IdentityManagerError::InvalidBackup)
+        );
+    }
+
+    #[test]
+    fn checksum_depends_on_every_previous_chunk() {
+        let first = chain_checksum(&[], b"first");
+        let second = chain_checksum(&first, b"second");
+
+        assert_eq!(second, chain_checksum(&chain_checksum(&[], b"first"), b"second"));
+        assert_ne!(second, chain_checksum(&chain_checksum(&[], b"other"), b"second"));
+        assert_ne!(second, chain_checksum(&[], b"second"));
+    }
+}
@@ -1,11 +1,113 @@
+use crate::http::requests::PrincipalResolution;
+use crate::repository::account_repo::{Account, ACCOUNTS};
 use candid::CandidType;
 use ic_cdk::api::set_certified_data;
-use ic_certified_map::{AsHashTree, RbTree};
+use ic_cdk::trap;
+use ic_certified_map::{
+    fork, fork_hash, labeled, labeled_hash, leaf_hash, AsHashTree, Hash, HashTree, RbTree,
+};
 use serde::Serialize;
+use std::borrow::Cow;
 use std::cell::RefCell;
+use std::iter;
 
 thread_local! {
-  pub static TREE: RefCell<RbTree<String, Vec<u8>>> = const { RefCell::new(RbTree::new()) };
+  pub static TREE: RefCell<RootTree> = const { RefCell::new(RootTree::new()) };
+  /// Access point principals to the root and anchor of their account, certified under `LABEL_PRINCIPALS`.
+  pub static PRINCIPAL_TREE: RefCell<RbTree<String, CertifiedPrincipal>> = const { RefCell::new(RbTree::new()) };
+}
+
+/// The certified data is the hash of `fork(fork(before, labeled(LABEL_PRINCIPALS, PRINCIPAL_TREE)), after)`,
+/// where `before` and `after` are the principals of `TREE` sorting before and after the label.
+/// Witnesses keep resolving a principal directly under the top level, and the top-level labels stay sorted.
+pub const LABEL_PRINCIPALS: &[u8] = b"principals";
+pub const LABEL_ANCHOR: &[u8] = b"anchor";
+pub const LABEL_ROOT: &[u8] = b"root";
+
+/// Access point principals to the SHA-256 of their root, split around `LABEL_PRINCIPALS`.
+pub struct RootTree {
+    before: RbTree<String, Vec<u8>>,
+    after: RbTree<String, Vec<u8>>,
+}
+
+impl RootTree {
+    const fn new() -> Self {
+        RootTree {
+            before: RbTree::new(),
+            after: RbTree::new(),
+        }
+    }
+
+    fn insert(&mut self, key: String, root_hash: Vec<u8>) {
+        if key.as_bytes() < LABEL_PRINCIPALS {
+            self.before.insert(key, root_hash);
+        } else {
+            self.after.insert(key, root_hash);
+        }
+    }
+
+    fn delete(&mut self, key: &[u8]) {
+        if key < LABEL_PRINCIPALS {
+            self.before.delete(key);
+        } else {
+            self.after.delete(key);
+        }
+    }
+
+    fn root_hash(&self, principals: &RbTree<String, CertifiedPrincipal>) -> Hash {
+        fork_hash(
+            &fork_hash(
+                &self.before.root_hash(),
+                &labeled_hash(LABEL_PRINCIPALS, &principals.root_hash()),
+            ),
+            &self.after.root_hash(),
+        )
+    }
+
+    /// Reveals the root of `key` under the top level and `principals` under `LABEL_PRINCIPALS`.
+    fn witness<'a>(&'a self, key: &[u8], principals: HashTree<'a>) -> HashTree<'a> {
+        let (before, after) = if key < LABEL_PRINCIPALS {
+            (
+                self.before.witness(key),
+                HashTree::Pruned(self.after.root_hash()),
+            )
+        } else {
+            (
+                HashTree::Pruned(self.before.root_hash()),
+                self.after.witness(key),
+            )
+        };
+        fork(fork(before, labeled(LABEL_PRINCIPALS, principals)), after)
+    }
+}
+
+/// Certified as `fork(labeled("anchor", anchor as 8 big-endian bytes), labeled("root", root principal text))`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertifiedPrincipal {
+    pub root: String,
+    pub anchor: u64,
+}
+
+impl AsHashTree for CertifiedPrincipal {
+    fn root_hash(&self) -> Hash {
+        fork_hash(
+            &labeled_hash(LABEL_ANCHOR, &leaf_hash(&self.anchor.to_be_bytes())),
+            &labeled_hash(LABEL_ROOT, &leaf_hash(self.root.as_bytes())),
+        )
+    }
+
+    fn as_hash_tree(&self) -> HashTree<'_> {
+        fork(
+            labeled(
+                LABEL_ANCHOR,
+                HashTree::Leaf(Cow::Owned(self.anchor.to_be_bytes().to_vec())),
+            ),
+            labeled(
+                LABEL_ROOT,
+                HashTree::Leaf(Cow::Borrowed(self.root.as_bytes())),
+            ),
+        )
+    }
 }
 
 #[derive(CandidType)]
@@ -15,40 +117,300 @@ pub struct CertifiedResponse {
     pub witness: Vec<u8>,
 }
 
+/// The witness proves the root and anchor of every registered principal and the absence of the others.
+/// Wallets are not part of the certified tree.
+#[derive(CandidType)]
+pub struct CertifiedResolutionsResponse {
+    pub response: Vec<PrincipalResolution>,
+    pub certificate: Vec<u8>,
+    pub witness: Vec<u8>,
+}
+
+/// Certifies the root of the principal and, once the account is stored, its root and anchor.
 pub fn update_certify_keys(key: String, principal: String) -> String {
-    TREE.with(|k| {
-        let mut keys = k.borrow_mut();
-        let b = hex::decode(sha256::digest(principal))
-            .expect("Failed to decode the SHA-256 digest of the principal.");
-        keys.insert(key.clone(), b);
-        set_certified_data(&keys.root_hash());
-        key
+    let anchor = ACCOUNTS.with(|accounts| accounts.borrow().get(&principal).map(|acc| acc.anchor));
+    insert_certify_keys([(key.clone(), principal, anchor)]);
+    key
+}
+
+/// Certifies every principal of the account, needed when the account is created or its anchor changes.
+pub fn certify_account(account: &Account) {
+    insert_certify_keys(
+        account_principals(account)
+            .map(|key| (key, account.principal_id.clone(), Some(account.anchor))),
+    );
+}
+
+/// Removes every principal of the account from the certified tree, so certified lookups stop resolving it.
+pub fn uncertify_account(account: &Account) {
+    TREE.with(|tree| {
+        PRINCIPAL_TREE.with(|principals| {
+            let mut tree = tree.borrow_mut();
+            let mut principals = principals.borrow_mut();
+            for key in account_principals(account) {
+                tree.delete(key.as_bytes());
+                principals.delete(key.as_bytes());
+            }
+            set_certified_data(&certified_data(&tree, &principals));
+        })
+    })
+}
+
+fn account_principals(account: &Account) -> impl Iterator<Item = String> + '_ {
+    iter::once(account.principal_id.clone()).chain(
+        account
+            .access_points
+            .iter()
+            .map(|ap| ap.principal_id.clone()),
+    )
+}
+
+/// Inserts `(principal, root, anchor)` entries and updates the certified data once.
+/// `keys` is consumed lazily, so an iterator can stop early, e.g. once an instruction limit is reached.
+pub fn insert_certify_keys(keys: impl IntoIterator<Item = (String, String, Option<u64>)>) {
+    TREE.with(|tree| {
+        PRINCIPAL_TREE.with(|principals| {
+            let mut tree = tree.borrow_mut();
+            let mut principals = principals.borrow_mut();
+            for (key, root, anchor) in keys {
+                let root_hash = hex::decode(sha256::digest(root.as_str()))
+                    .expect("Failed to decode the SHA-256 digest of the principal.");
+                tree.insert(key.clone(), root_hash);
+                if let Some(anchor) = anchor {
+                    principals.insert(key, CertifiedPrincipal { root, anchor });
+                }
+            }
+            set_certified_data(&certified_data(&tree, &principals));
+        })
     })
 }
 
 pub fn remove_certify_keys(key: String) {
-    TREE.with(|k| {
-        let mut keys = k.borrow_mut();
-        keys.delete(key.as_ref());
-        set_certified_data(&keys.root_hash());
+    TREE.with(|tree| {
+        PRINCIPAL_TREE.with(|principals| {
+            let mut tree = tree.borrow_mut();
+            let mut principals = principals.borrow_mut();
+            tree.delete(key.as_bytes());
+            principals.delete(key.as_bytes());
+            set_certified_data(&certified_data(&tree, &principals));
+        })
     })
 }
 
+fn certified_data(tree: &RootTree, principals: &RbTree<String, CertifiedPrincipal>) -> Hash {
+    tree.root_hash(principals)
+}
+
 pub fn get_witness(key: String) -> anyhow::Result<Vec<u8>> {
     TREE.with(|tree| {
-        let tree = tree.borrow();
-        let mut witness = vec![];
-        let mut witness_serializer = serde_cbor::Serializer::new(&mut witness);
+        PRINCIPAL_TREE.with(|principals| {
+            let tree = tree.borrow();
+            let principals = principals.borrow();
+            serialize_witness(
+                tree.witness(key.as_bytes(), HashTree::Pruned(principals.root_hash())),
+            )
+        })
+    })
+}
 
-        witness_serializer.self_describe()?;
+/// A single witness covering all keys, so registered and unregistered principals can be verified at once.
+/// Both the root under the top level and the root and anchor under `LABEL_PRINCIPALS` are revealed.
+pub fn get_batch_witness(keys: &[String]) -> anyhow::Result<Vec<u8>> {
+    TREE.with(|tree| {
+        PRINCIPAL_TREE.with(|principals| {
+            let tree = tree.borrow();
+            let principals = principals.borrow();
+            let witness = keys
+                .iter()
+                .map(|key| tree.witness(key.as_bytes(), principals.witness(key.as_bytes())))
+                .reduce(merge_hash_trees)
+                .unwrap_or_else(|| HashTree::Pruned(certified_data(&tree, &principals)));
+            serialize_witness(witness)
+        })
+    })
+}
 
-        tree.witness(key.as_bytes())
-            .serialize(&mut witness_serializer)
-            .expect("Failed to serialize the witness.");
+fn serialize_witness(tree: HashTree) -> anyhow::Result<Vec<u8>> {
+    let mut witness = vec![];
+    let mut witness_serializer = serde_cbor::Serializer::new(&mut witness);
 
-        Ok(witness)
-    })
+    witness_serializer.self_describe()?;
+
+    tree.serialize(&mut witness_serializer)
+        .expect("Failed to serialize the witness.");
+
+    Ok(witness)
+}
+
+/// Merges two witnesses of the same tree, keeping the revealed parts of both.
+fn merge_hash_trees<'a>(lhs: HashTree<'a>, rhs: HashTree<'a>) -> HashTree<'a> {
+    match (lhs, rhs) {
+        (HashTree::Pruned(l), HashTree::Pruned(r)) => {
+            if l != r {
+                trap("Failed to merge witnesses: inconsistent hashes.");
+            }
+            HashTree::Pruned(l)
+        }
+        (HashTree::Pruned(_), r) => r,
+        (l, HashTree::Pruned(_)) => l,
+        (HashTree::Fork(l), HashTree::Fork(r)) => {
+            let (l_left, l_right) = *l;
+            let (r_left, r_right) = *r;
+            fork(
+                merge_hash_trees(l_left, r_left),
+                merge_hash_trees(l_right, r_right),
+            )
+        }
+        (HashTree::Labeled(l_label, l), HashTree::Labeled(r_label, r)) => {
+            if l_label != r_label {
+                trap("Failed to merge witnesses: inconsistent labels.");
+            }
+            labeled(l_label, merge_hash_trees(*l, *r))
+        }
+        (HashTree::Empty, HashTree::Empty) => HashTree::Empty,
+        (HashTree::Leaf(l), HashTree::Leaf(r)) => {
+            if l != r {
+                trap("Failed to merge witnesses: inconsistent leaves.");
+            }
+            HashTree::Leaf(l)
+        }
+        _ => trap("Failed to merge witnesses: inconsistent tree structure."),
+    }
 }
 
 #[test]
 fn sub_account_test() {}
+
+#[test]
+fn merged_witness_reveals_all_keys() {
+    let mut tree: RbTree<String, Vec<u8>> = RbTree::new();
+    for key in ["a", "b", "c", "d"] {
+        tree.insert(key.to_string(), key.as_bytes().to_vec());
+    }
+    let merged = merge_hash_trees(tree.witness(b"a"), tree.witness(b"d"));
+    assert_eq!(tree.root_hash(), merged.reconstruct());
+    let mut leaves = vec![];
+    collect_leaves(&merged, &mut leaves);
+    assert_eq!(vec![b"a".to_vec(), b"d".to_vec()], leaves);
+}
+
+#[cfg(test)]
+fn collect_leaves(tree: &HashTree, leaves: &mut Vec<Vec<u8>>) {
+    match tree {
+        HashTree::Fork(children) => {
+            collect_leaves(&children.0, leaves);
+            collect_leaves(&children.1, leaves);
+        }
+        HashTree::Labeled(_, subtree) => collect_leaves(subtree, leaves),
+        HashTree::Leaf(value) => leaves.push(value.to_vec()),
+        HashTree::Empty | HashTree::Pruned(_) => {}
+    }
+}
+
+#[test]
+fn principals_after_the_label_resolve_through_a_spec_lookup() {
+    let registered = "rrkah-fqaaa-aaaaa-aaaaq-cai";
+    let unregistered = "ryjl3-tyaaa-aaaaa-aaaba-cai";
+    let mut tree = RootTree::new();
+    let mut principals = RbTree::new();
+    for key in ["aaaaa-aa", registered] {
+        tree.insert(key.to_string(), key.as_bytes().to_vec());
+        principals.insert(
+            key.to_string(),
+            CertifiedPrincipal {
+                root: key.to_string(),
+                anchor: 100_000_000,
+            },
+        );
+    }
+
+    let witness = tree.witness(
+        registered.as_bytes(),
+        principals.witness(registered.as_bytes()),
+    );
+    assert_eq!(certified_data(&tree, &principals), witness.reconstruct());
+    assert_eq!(
+        SpecLookup::Found(registered.as_bytes()),
+        spec_lookup_path(&witness, &[registered.as_bytes()])
+    );
+    assert_eq!(
+        SpecLookup::Found(registered.as_bytes()),
+        spec_lookup_path(
+            &witness,
+            &[LABEL_PRINCIPALS, registered.as_bytes(), LABEL_ROOT]
+        )
+    );
+
+    let absence = tree.witness(
+        unregistered.as_bytes(),
+        principals.witness(unregistered.as_bytes()),
+    );
+    assert_eq!(certified_data(&tree, &principals), absence.reconstruct());
+    assert_eq!(
+        SpecLookup::Absent,
+        spec_lookup_path(&absence, &[unregistered.as_bytes()])
+    );
+    assert_eq!(
+        SpecLookup::Absent,
+        spec_lookup_path(
+            &absence,
+            &[LABEL_PRINCIPALS, unregistered.as_bytes(), LABEL_ROOT]
+        )
+    );
+}
+
+#[cfg(test)]
+#[derive(Debug, PartialEq, Eq)]
+enum SpecLookup<'a> {
+    Found(&'a [u8]),
+    Absent,
+    Unknown,
+}
+
+/// The lookup of the interface specification, which relies on sorted labels and stops at the first larger one.
+#[cfg(test)]
+fn spec_lookup_path<'a>(tree: &'a HashTree<'a>, path: &[&[u8]]) -> SpecLookup<'a> {
+    let Some((label, rest)) = path.split_first() else {
+        return match tree {
+            HashTree::Leaf(value) => SpecLookup::Found(value.as_ref()),
+            HashTree::Pruned(_) => SpecLookup::Unknown,
+            _ => SpecLookup::Absent,
+        };
+    };
+    let mut nodes = vec![];
+    flatten_forks(tree, &mut nodes);
+    let mut previous_revealed = true;
+    for node in nodes {
+        match node {
+            HashTree::Labeled(node_label, subtree) if *node_label == *label => {
+                return spec_lookup_path(subtree, rest);
+            }
+            HashTree::Labeled(node_label, _) if *node_label > *label => {
+                return if previous_revealed {
+                    SpecLookup::Absent
+                } else {
+                    SpecLookup::Unknown
+                };
+            }
+            HashTree::Labeled(..) => previous_revealed = true,
+            _ => previous_revealed = false,
+        }
+    }
+    if previous_revealed {
+        SpecLookup::Absent
+    } else {
+        SpecLookup::Unknown
+    }
+}
+
+#[cfg(test)]
+fn flatten_forks<'a>(tree: &'a HashTree<'a>, nodes: &mut Vec<&'a HashTree<'a>>) {
+    match tree {
+        HashTree::Empty => {}
+        HashTree::Fork(children) => {
+            flatten_forks(&children.0, nodes);
+            flatten_forks(&children.1, nodes);
+        }
+        node => nodes.push(node),
+    }
+}
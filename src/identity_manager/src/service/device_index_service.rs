@@ -1,13 +1,15 @@
+use std::ops::Bound::{Excluded, Unbounded};
+use std::collections::HashMap;
 use std::{cell::RefCell, collections::VecDeque, convert::TryInto};
 
-use ic_cdk::api::set_certified_data;
-use ic_certified_map::AsHashTree;
+use ic_cdk::api::instruction_counter;
 
-use super::certified_service::TREE;
-use crate::repository::account_repo::PRINCIPAL_INDEX;
+use super::certified_service::insert_certify_keys;
+use super::job_service::BatchOutcome;
+use crate::repository::account_repo::{ACCOUNTS, PRINCIPAL_INDEX};
 
 thread_local! {
-    pub static DEVICE_INDEX_STACK: RefCell<VecDeque<(String, Vec<u8>)>> = const { RefCell::new(VecDeque::new()) };
+    pub static DEVICE_INDEX_STACK: RefCell<VecDeque<(String, String)>> = const { RefCell::new(VecDeque::new()) };
 }
 
 pub fn save_temp_stack() -> String {
@@ -17,17 +19,8 @@ pub fn save_temp_stack() -> String {
         return String::from("The stack is not empty. No action required.");
     }
 
-    let device_index_iterator: Vec<(String, Vec<u8>)> = PRINCIPAL_INDEX.with(|index| {
-        index
-            .borrow()
-            .iter()
-            .map(|(device, root)| {
-                let root_hex = hex::decode(sha256::digest(root.clone()))
-                    .expect("Failed to decode the SHA-256 digest of the root.");
-                (device.clone(), root_hex)
-            })
-            .collect()
-    });
+    let device_index_iterator: Vec<(String, String)> =
+        PRINCIPAL_INDEX.with(|index| index.borrow().iter().collect());
 
     DEVICE_INDEX_STACK.with(|index_ref| {
         index_ref.borrow_mut().extend(device_index_iterator);
@@ -39,7 +32,7 @@ pub fn save_temp_stack() -> String {
 pub fn get_remaining_size_after_rebuild_index_slice_from_temp_stack(
     amount_opt: Option<u64>,
 ) -> u64 {
-    let (slice, remaining_size): (Vec<(String, Vec<u8>)>, usize) =
+    let (slice, remaining_size): (Vec<(String, String)>, usize) =
         DEVICE_INDEX_STACK.with(|index_ref| {
             let mut index = index_ref.borrow_mut();
             let mut amount: usize = amount_opt.map_or_else(|| 10_000, |v| v.try_into()
@@ -54,15 +47,96 @@ pub fn get_remaining_size_after_rebuild_index_slice_from_temp_stack(
         return 0;
     }
 
-    TREE.with(|keys_ref| {
-        let mut keys = keys_ref.borrow_mut();
+    let mut anchors = AnchorCache::default();
+    insert_certify_keys(
+        slice
+            .into_iter()
+            .filter_map(|(device, root)| anchors.certify_key(device, root)),
+    );
+
+    remaining_size.try_into().expect("Failed to convert remaining size to usize.")
+}
 
-        for (device, root_hex) in slice {
-            keys.insert(device, root_hex);
-        }
+/// Inserts `PRINCIPAL_INDEX` entries after `cursor` into the certified tree until the instruction limit is reached.
+/// Entries are inserted as they are read, so the tree inserts count against the limit,
+/// and the certified data is updated once for the whole batch.
+pub fn rebuild_device_index_batch(cursor: Option<String>, instruction_limit: u64) -> BatchOutcome {
+    let mut outcome = BatchOutcome {
+        cursor: cursor.clone(),
+        processed: 0,
+        finished: true,
+    };
+    let start = cursor.map_or(Unbounded, Excluded);
+    let mut anchors = AnchorCache::default();
 
-        set_certified_data(&keys.root_hash());
+    PRINCIPAL_INDEX.with(|index_ref| {
+        let index = index_ref.borrow();
+        let keys = index
+            .range((start, Unbounded))
+            .map_while(|(device, root)| {
+                if instruction_counter() > instruction_limit {
+                    outcome.finished = false;
+                    return None;
+                }
+                outcome.cursor = Some(device.clone());
+                outcome.processed += 1;
+                Some((device, root))
+            })
+            .filter_map(|(device, root)| anchors.certify_key(device, root));
+        insert_certify_keys(keys);
     });
 
-    remaining_size.try_into().expect("Failed to convert remaining size to usize.")
+    outcome
+}
+
+/// Adds the root principal of every account after `cursor` to `PRINCIPAL_INDEX` until the instruction limit is reached.
+/// The certified tree is not touched.
+pub fn rebuild_index_batch(cursor: Option<String>, instruction_limit: u64) -> BatchOutcome {
+    let mut outcome = BatchOutcome {
+        cursor: cursor.clone(),
+        processed: 0,
+        finished: true,
+    };
+    let start = cursor.map_or(Unbounded, Excluded);
+
+    ACCOUNTS.with(|accounts_ref| {
+        PRINCIPAL_INDEX.with(|index_ref| {
+            let accounts = accounts_ref.borrow();
+            let mut index = index_ref.borrow_mut();
+
+            for (principal_id, _) in accounts.range((start, Unbounded)) {
+                if instruction_counter() > instruction_limit {
+                    outcome.finished = false;
+                    break;
+                }
+                index.insert(principal_id.clone(), principal_id.clone());
+                outcome.cursor = Some(principal_id);
+                outcome.processed += 1;
+            }
+        })
+    });
+
+    outcome
+}
+
+/// Anchors of the accounts seen in a batch, so each account is decoded once however many principals it has.
+/// `None` marks an account pending removal, which stays out of the certified tree.
+#[derive(Default)]
+struct AnchorCache(HashMap<String, Option<Option<u64>>>);
+
+impl AnchorCache {
+    /// Adds the anchor of the account to a `(device, root)` entry of `PRINCIPAL_INDEX`.
+    fn certify_key(
+        &mut self,
+        device: String,
+        root: String,
+    ) -> Option<(String, String, Option<u64>)> {
+        let anchor = *self.0.entry(root.clone()).or_insert_with(|| {
+            match ACCOUNTS.with(|accounts| accounts.borrow().get(&root)) {
+                Some(acc) if acc.pending_removal.is_some() => None,
+                acc => Some(acc.map(|acc| acc.anchor)),
+            }
+        });
+        anchor.map(|anchor| (device, root, anchor))
+    }
 }
@@ -1,6 +1,7 @@
 use crate::structure::ttl_hashmap::TtlHashMap;
 use crate::{HttpResponse, to_success_response};
 use std::cell::RefCell;
+use ic_cdk::api::time;
 use ic_cdk::trap;
 
 thread_local! {
@@ -30,6 +31,28 @@ pub fn contains(key: String, value: String) -> bool {
     })
 }
 
+/// Returns the principal the validation of the email address was issued for, without using the validation up.
+/// Validations older than 15 minutes are rejected; the lambda issues them with a timestamp in milliseconds.
+pub fn validated_principal(key: String) -> Option<String> {
+    let now_millis = time() / 1_000_000;
+    TOKENS_REPOSITORY.with(|repository| {
+        let mut repo = repository.borrow_mut();
+        repo.clean_expired_entries(now_millis);
+        repo.get(&key).cloned()
+    })
+}
+
+/// Removes the validation of the email address and returns the principal it was issued for, so it can be used only once.
+/// Validations older than 15 minutes are rejected; the lambda issues them with a timestamp in milliseconds.
+pub fn consume(key: String) -> Option<String> {
+    let now_millis = time() / 1_000_000;
+    TOKENS_REPOSITORY.with(|repository| {
+        let mut repo = repository.borrow_mut();
+        repo.clean_expired_entries(now_millis);
+        repo.remove(&key)
+    })
+}
+
 fn is_valid_email_address_size(email: &str) -> bool {
     email.len() <= 320
 }
\ No newline at end of file
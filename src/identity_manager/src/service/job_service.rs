@@ -0,0 +1,213 @@
+use candid::{CandidType, Deserialize};
+use ic_cdk_timers::TimerId;
+use ic_stable_structures::StableBTreeMap;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::ic_service;
+use crate::repository::memory::{candid_storable, get_memory, Memory, JOBS_MEMORY_ID};
+use crate::service::{device_index_service, metrics_service, replication_service};
+
+/// Instructions a single batch may spend before it yields to the next timer tick.
+/// Well below the 40B limit of an update message so that the bookkeeping always fits.
+const BATCH_INSTRUCTION_LIMIT: u64 = 10_000_000_000;
+
+/// Jobs whose results live on the heap, so they start over after an upgrade instead of resuming.
+const HEAP_JOBS: [JobKind; 2] = [JobKind::RebuildDeviceIndex, JobKind::CountAccounts];
+
+/// How often the accounts are counted again for the metrics.
+const COUNT_ACCOUNTS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+thread_local! {
+    static JOBS: RefCell<StableBTreeMap<JobKind, Job, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(JOBS_MEMORY_ID)));
+    /// The pending timer of every job, so a job never runs two timer chains at once.
+    static TIMERS: RefCell<BTreeMap<JobKind, TimerId>> = const { RefCell::new(BTreeMap::new()) };
+}
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum JobKind {
+    /// Rebuilds the certified tree from `PRINCIPAL_INDEX`.
+    RebuildDeviceIndex,
+    /// Adds the root principal of every account to `PRINCIPAL_INDEX`.
+    RebuildIndex,
+    /// Appends every account to the change log, so a new backup canister receives a full copy.
+    ReplicateAccounts,
+    /// Counts accounts by wallet and access points by device type for the metrics.
+    CountAccounts,
+}
+
+#[derive(Clone, Copy, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq)]
+pub enum JobStatus {
+    Running,
+    Paused,
+    Completed,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct Job {
+    pub kind: JobKind,
+    pub status: JobStatus,
+    /// The last key processed by the job, the next batch resumes right after it.
+    pub cursor: Option<String>,
+    pub processed: u64,
+    pub batches: u64,
+    pub started_at: u64,
+    pub updated_at: u64,
+}
+
+candid_storable!(JobKind);
+candid_storable!(Job);
+
+/// The result of one instruction-bounded batch.
+pub struct BatchOutcome {
+    pub cursor: Option<String>,
+    pub processed: u64,
+    pub finished: bool,
+}
+
+/// Starts the job, or resumes it from its cursor if it was paused.
+/// A running job is scheduled again, since a batch that trapped leaves it running without a pending timer.
+/// `schedule` replaces the timer of a healthy job, so it never runs two timer chains.
+pub fn start(kind: JobKind) -> Job {
+    match get(kind) {
+        Some(job) if job.status == JobStatus::Running => {
+            schedule(kind);
+            job
+        }
+        Some(job) if job.status == JobStatus::Paused => {
+            let job = save(Job {
+                status: JobStatus::Running,
+                updated_at: ic_service::get_time(),
+                ..job
+            });
+            schedule(kind);
+            job
+        }
+        _ => restart(kind),
+    }
+}
+
+/// Starts the job from the beginning, dropping any stored progress.
+pub fn restart(kind: JobKind) -> Job {
+    let now = ic_service::get_time();
+    let job = save(Job {
+        kind,
+        status: JobStatus::Running,
+        cursor: None,
+        processed: 0,
+        batches: 0,
+        started_at: now,
+        updated_at: now,
+    });
+    schedule(kind);
+    job
+}
+
+/// Pauses the job after the batch in progress. `start` resumes it from its cursor.
+pub fn pause(kind: JobKind) -> Option<Job> {
+    let job = get(kind)?;
+    if job.status != JobStatus::Running {
+        return Some(job);
+    }
+    cancel(kind);
+    Some(save(Job {
+        status: JobStatus::Paused,
+        updated_at: ic_service::get_time(),
+        ..job
+    }))
+}
+
+pub fn get_jobs() -> Vec<Job> {
+    JOBS.with(|jobs| jobs.borrow().iter().map(|(_, job)| job).collect())
+}
+
+/// Timers do not survive an upgrade, so jobs that were running are scheduled again.
+pub fn post_upgrade() {
+    get_jobs()
+        .into_iter()
+        .filter(|job| job.status == JobStatus::Running)
+        .filter(|job| !HEAP_JOBS.contains(&job.kind))
+        .for_each(|job| schedule(job.kind));
+    restart(JobKind::RebuildDeviceIndex);
+    schedule_account_counts();
+}
+
+/// Counts the accounts now and again every `COUNT_ACCOUNTS_INTERVAL`, unless an operator paused the count.
+/// A count still running is scheduled again rather than restarted.
+pub fn schedule_account_counts() {
+    restart(JobKind::CountAccounts);
+    ic_cdk_timers::set_timer_interval(COUNT_ACCOUNTS_INTERVAL, || {
+        if get(JobKind::CountAccounts).map_or(true, |job| job.status != JobStatus::Paused) {
+            start(JobKind::CountAccounts);
+        }
+    });
+}
+
+fn get(kind: JobKind) -> Option<Job> {
+    JOBS.with(|jobs| jobs.borrow().get(&kind))
+}
+
+fn save(job: Job) -> Job {
+    JOBS.with(|jobs| jobs.borrow_mut().insert(job.kind, job.clone()));
+    job
+}
+
+/// Replaces the pending timer of the job, if any.
+fn schedule(kind: JobKind) {
+    let timer = ic_cdk_timers::set_timer(Duration::ZERO, move || run(kind));
+    TIMERS.with(|timers| {
+        if let Some(previous) = timers.borrow_mut().insert(kind, timer) {
+            ic_cdk_timers::clear_timer(previous);
+        }
+    });
+}
+
+fn cancel(kind: JobKind) {
+    if let Some(timer) = TIMERS.with(|timers| timers.borrow_mut().remove(&kind)) {
+        ic_cdk_timers::clear_timer(timer);
+    }
+}
+
+fn run(kind: JobKind) {
+    TIMERS.with(|timers| timers.borrow_mut().remove(&kind));
+    let job = match get(kind) {
+        Some(job) if job.status == JobStatus::Running => job,
+        _ => return,
+    };
+    let outcome = run_batch(kind, job.cursor.clone());
+    let job = save(Job {
+        status: if outcome.finished {
+            JobStatus::Completed
+        } else {
+            JobStatus::Running
+        },
+        cursor: if outcome.finished { None } else { outcome.cursor },
+        processed: job.processed + outcome.processed,
+        batches: job.batches + 1,
+        updated_at: ic_service::get_time(),
+        ..job
+    });
+    if job.status == JobStatus::Running {
+        schedule(kind);
+    }
+}
+
+fn run_batch(kind: JobKind, cursor: Option<String>) -> BatchOutcome {
+    match kind {
+        JobKind::RebuildDeviceIndex => {
+            device_index_service::rebuild_device_index_batch(cursor, BATCH_INSTRUCTION_LIMIT)
+        }
+        JobKind::RebuildIndex => {
+            device_index_service::rebuild_index_batch(cursor, BATCH_INSTRUCTION_LIMIT)
+        }
+        JobKind::ReplicateAccounts => {
+            replication_service::snapshot_batch(cursor, BATCH_INSTRUCTION_LIMIT)
+        }
+        JobKind::CountAccounts => {
+            metrics_service::count_accounts_batch(cursor, BATCH_INSTRUCTION_LIMIT)
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io;
+use std::ops::Bound::{Excluded, Unbounded};
+
+use canister_metrics::{CanisterStats, MetricsEncoder};
+use ic_cdk::api::instruction_counter;
+
+use crate::http::requests::{DeviceType, WalletVariant};
+use crate::repository::account_repo::{ACCOUNTS, PRINCIPAL_INDEX};
+use crate::repository::repo::{CAPTCHA_CAHLLENGES, TEMP_KEYS};
+use crate::service::ic_service;
+use crate::service::job_service::BatchOutcome;
+
+const WALLETS: [WalletVariant; 3] = [
+    WalletVariant::NFID,
+    WalletVariant::InternetIdentity,
+    WalletVariant::OpenId,
+];
+
+const DEVICE_TYPES: [DeviceType; 6] = [
+    DeviceType::Email,
+    DeviceType::Passkey,
+    DeviceType::Recovery,
+    DeviceType::Unknown,
+    DeviceType::Password,
+    DeviceType::InternetIdentity,
+];
+
+thread_local! {
+    /// The counts served at `/metrics`, taken by the last completed `CountAccounts` job.
+    static ACCOUNT_COUNTS: RefCell<AccountCounts> = RefCell::new(AccountCounts::default());
+    /// The counts of the `CountAccounts` job in progress.
+    static PARTIAL_COUNTS: RefCell<AccountCounts> = RefCell::new(AccountCounts::default());
+}
+
+#[derive(Default)]
+struct AccountCounts {
+    accounts: HashMap<WalletVariant, u64>,
+    access_points: HashMap<DeviceType, u64>,
+}
+
+pub fn canister_stats() -> CanisterStats {
+    CanisterStats::new(
+        ic_cdk::api::canister_balance128(),
+        ic_cdk::api::stable::stable64_size(),
+    )
+}
+
+/// Account and access point counts come from the last `CountAccounts` job, so a scrape never reads `ACCOUNTS`.
+pub fn encode_metrics(w: &mut MetricsEncoder<Vec<u8>>) -> io::Result<()> {
+    ACCOUNT_COUNTS.with(|counts| -> io::Result<()> {
+        let counts = counts.borrow();
+        let mut builder = w.gauge_vec("accounts", "Number of accounts by wallet.")?;
+        for wallet in WALLETS {
+            let count = counts.accounts.get(&wallet).copied().unwrap_or_default();
+            builder = builder.value(&[("wallet", wallet_label(wallet))], count as f64)?;
+        }
+
+        let mut builder =
+            w.gauge_vec("access_points", "Number of access points by device type.")?;
+        for device_type in DEVICE_TYPES {
+            let count = counts
+                .access_points
+                .get(&device_type)
+                .copied()
+                .unwrap_or_default();
+            builder = builder.value(
+                &[("device_type", device_type_label(device_type))],
+                count as f64,
+            )?;
+        }
+        Ok(())
+    })?;
+
+    let now = ic_service::get_time();
+    let captcha_challenges =
+        CAPTCHA_CAHLLENGES.with(|challenges| challenges.borrow().count_unexpired(now));
+    w.encode_gauge(
+        "captcha_challenges",
+        captcha_challenges as f64,
+        "Number of captcha challenges waiting for a solution.",
+    )?;
+    let temp_keys = TEMP_KEYS.with(|keys| keys.borrow().count());
+    w.encode_gauge(
+        "temp_keys",
+        temp_keys as f64,
+        "Number of temporary keys, including expired ones not cleaned up yet.",
+    )?;
+    Ok(())
+}
+
+/// Counts the accounts after `cursor` until the instruction limit is reached, starting over when `cursor` is empty.
+/// Tombstones, whose root is no longer indexed, and accounts pending removal are left out.
+/// The counts are served once the last batch completes.
+pub fn count_accounts_batch(cursor: Option<String>, instruction_limit: u64) -> BatchOutcome {
+    let mut outcome = BatchOutcome {
+        cursor: cursor.clone(),
+        processed: 0,
+        finished: true,
+    };
+    let start = match cursor {
+        None => {
+            PARTIAL_COUNTS.with(|partial| *partial.borrow_mut() = AccountCounts::default());
+            Unbounded
+        }
+        Some(cursor) => Excluded(cursor),
+    };
+
+    PARTIAL_COUNTS.with(|partial| {
+        let mut partial = partial.borrow_mut();
+        ACCOUNTS.with(|accounts| {
+            for (root, account) in accounts.borrow().range((start, Unbounded)) {
+                if instruction_counter() > instruction_limit {
+                    outcome.finished = false;
+                    break;
+                }
+                let indexed = PRINCIPAL_INDEX.with(|index| index.borrow().contains_key(&root));
+                if indexed && account.pending_removal.is_none() {
+                    *partial.accounts.entry(account.wallet).or_default() += 1;
+                    for access_point in account.access_points.iter() {
+                        *partial
+                            .access_points
+                            .entry(access_point.device_type)
+                            .or_default() += 1;
+                    }
+                }
+                outcome.cursor = Some(root);
+                outcome.processed += 1;
+            }
+        });
+    });
+
+    if outcome.finished {
+        let counts = PARTIAL_COUNTS.with(|partial| partial.take());
+        ACCOUNT_COUNTS.with(|served| *served.borrow_mut() = counts);
+    }
+    outcome
+}
+
+fn wallet_label(wallet: WalletVariant) -> &'static str {
+    match wallet {
+        WalletVariant::NFID => "NFID",
+        WalletVariant::InternetIdentity => "II",
+        WalletVariant::OpenId => "OpenID",
+    }
+}
+
+fn device_type_label(device_type: DeviceType) -> &'static str {
+    match device_type {
+        DeviceType::Email => "Email",
+        DeviceType::Passkey => "Passkey",
+        DeviceType::Recovery => "Recovery",
+        DeviceType::Unknown => "Unknown",
+        DeviceType::Password => "Password",
+        DeviceType::InternetIdentity => "InternetIdentity",
+    }
+}
@@ -1,9 +1,19 @@
+pub mod access_point_proof_service;
 pub mod access_point_service;
 pub mod account_service;
 pub mod application_service;
+pub mod audit_service;
+pub mod backup_service;
 pub mod certified_service;
 pub mod device_index_service;
 pub mod email_validation_service;
 pub mod ic_service;
+pub mod job_service;
+pub mod metrics_service;
+pub mod openid_service;
 pub mod persona_service;
+pub mod personal_data_service;
+pub mod recovery_service;
+pub mod removal_service;
+pub mod replication_service;
 pub mod security_service;
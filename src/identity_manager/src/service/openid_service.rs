@@ -0,0 +1,17 @@
+use crate::http::error::IdentityManagerError;
+use crate::repository::openid_repo::OpenIdRepo;
+use crate::service::ic_service;
+use crate::util::openid::{verify_id_token, IdTokenClaims};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Verifies the ID token against the providers registered by the operator for the current caller.
+pub fn verify_caller_id_token(id_token: &str) -> Result<IdTokenClaims, IdentityManagerError> {
+    verify_id_token(
+        id_token,
+        &OpenIdRepo::get_providers(),
+        &ic_service::get_caller(),
+        ic_service::get_time() / NANOS_PER_SEC,
+    )
+    .map_err(|_| IdentityManagerError::InvalidIdToken)
+}
@@ -0,0 +1,67 @@
+use crate::container::container_wrapper::get_account_repo;
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::{PersonalDataExport, TempKeyData, WalletVariant};
+use crate::ic_service;
+use crate::mapper::personal_data_mapper::{
+    access_point_to_personal_access_point_data, account_to_personal_account_data,
+    persona_to_personal_persona_data,
+};
+use crate::repository::account_event_repo::{
+    AccountEventRepo, AccountEventRepoTrait, MAX_EVENTS_PER_ACCOUNT,
+};
+use crate::repository::account_repo::AccountRepoTrait;
+use crate::repository::repo::{TEMP_KEYS, TEMP_KEY_EXPIRATION_NS};
+use crate::response_mapper::{to_success_response, HttpResponse};
+
+/// Raised when the meaning of an exported field changes, new fields do not require it.
+const PERSONAL_DATA_EXPORT_VERSION: u32 = 1;
+
+/// Collects everything stored about the caller's root account into one document.
+#[allow(deprecated)]
+pub fn export_personal_data() -> HttpResponse<PersonalDataExport> {
+    let account = match get_account_repo().get_account() {
+        None => return HttpResponse::from(IdentityManagerError::AccountNotFound),
+        Some(account) => account,
+    };
+    let now = ic_service::get_time();
+
+    // Temporary keys only ever point to NFID anchors.
+    let temp_keys = if account.wallet == WalletVariant::NFID {
+        TEMP_KEYS.with(|keys| {
+            let mut keys = keys.borrow_mut();
+            keys.clean_expired_entries(now);
+            keys.entries()
+                .filter(|(_, anchor, _)| **anchor == account.anchor)
+                .map(|(principal_id, _, inserted_at)| TempKeyData {
+                    principal_id: principal_id.clone(),
+                    expires_at: inserted_at + TEMP_KEY_EXPIRATION_NS,
+                })
+                .collect()
+        })
+    } else {
+        vec![]
+    };
+    let (events, _) = AccountEventRepo::default().get_events(
+        account.principal_id.clone(),
+        None,
+        MAX_EVENTS_PER_ACCOUNT,
+    );
+
+    to_success_response(PersonalDataExport {
+        version: PERSONAL_DATA_EXPORT_VERSION,
+        exported_at: now,
+        account: account_to_personal_account_data(&account),
+        access_points: account
+            .access_points
+            .into_iter()
+            .map(access_point_to_personal_access_point_data)
+            .collect(),
+        personas: account
+            .personas
+            .into_iter()
+            .map(persona_to_personal_persona_data)
+            .collect(),
+        temp_keys,
+        events,
+    })
+}
@@ -0,0 +1,171 @@
+use candid::Principal;
+use ic_stable_structures::StableBTreeMap;
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::container::container_wrapper::get_account_repo;
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::{AccountResponse, DeviceType, WalletVariant};
+use crate::ic_service;
+use crate::mapper::access_point_mapper::access_point_request_to_access_point;
+use crate::mapper::account_mapper::account_to_account_response;
+use crate::repository::account_event_repo::AccountEventType;
+use crate::repository::account_repo::{Account, AccountRepoTrait, PendingRecovery};
+use crate::repository::memory::{get_memory, Memory, PENDING_RECOVERIES_MEMORY_ID};
+use crate::repository::repo::ConfigurationRepo;
+use crate::requests::AccessPointRequest;
+use crate::response_mapper::{to_success_response, HttpResponse};
+use crate::service::audit_service;
+
+pub const DEFAULT_RECOVERY_DELAY: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+thread_local! {
+    /// Root principals of accounts with a pending recovery to the time it can be completed.
+    /// Needed to schedule the completion timers again after an upgrade without scanning all accounts.
+    static PENDING_RECOVERIES: RefCell<StableBTreeMap<String, u64, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(PENDING_RECOVERIES_MEMORY_ID)));
+}
+
+pub fn initiate_recovery(access_point_request: AccessPointRequest) -> HttpResponse<AccountResponse> {
+    let account_repo = get_account_repo();
+    let caller = ic_service::get_caller().to_text();
+    let mut account = match account_repo.get_account() {
+        None => return HttpResponse::from(IdentityManagerError::AccountNotFound),
+        Some(account) => account,
+    };
+    if !account.wallet.eq(&WalletVariant::NFID) {
+        return HttpResponse::from(IdentityManagerError::NfidOnly);
+    }
+    if !account
+        .access_points
+        .iter()
+        .any(|ap| ap.principal_id.eq(&caller) && ap.device_type.eq(&DeviceType::Recovery))
+    {
+        return HttpResponse::from(IdentityManagerError::RecoveryPhraseRequired);
+    }
+    if account.pending_recovery.is_some() {
+        return HttpResponse::from(IdentityManagerError::RecoveryPending);
+    }
+    if access_point_request.device_type.eq(&DeviceType::Recovery) {
+        return HttpResponse::from(IdentityManagerError::RecoveryNotAllowed);
+    }
+    if Principal::from_text(&access_point_request.pub_key).is_err() {
+        return HttpResponse::from(IdentityManagerError::InvalidPublicKey);
+    }
+    // Includes accounts pending removal, whose principals must not move to another account.
+    if account_repo.is_principal_indexed(access_point_request.pub_key.clone()) {
+        return HttpResponse::from(IdentityManagerError::AccessPointExists);
+    }
+
+    let delay = ConfigurationRepo::get()
+        .recovery_delay
+        .unwrap_or(DEFAULT_RECOVERY_DELAY);
+    let initiated_at = ic_service::get_time();
+    let executable_at = initiated_at + delay.as_nanos() as u64;
+    account.pending_recovery = Some(PendingRecovery {
+        access_point: access_point_request_to_access_point(access_point_request),
+        initiated_by: caller.clone(),
+        initiated_at,
+        executable_at,
+    });
+    account_repo.store_account(account.clone());
+    PENDING_RECOVERIES.with(|pending| {
+        pending
+            .borrow_mut()
+            .insert(account.principal_id.clone(), executable_at)
+    });
+    schedule_completion(account.principal_id.clone(), delay);
+    let target = account
+        .pending_recovery
+        .as_ref()
+        .map(|pending| pending.access_point.principal_id.clone());
+    audit_service::record_for_account(&account, Some(caller), AccountEventType::InitiateRecovery, target);
+    to_success_response(account_to_account_response(account))
+}
+
+pub fn cancel_recovery() -> HttpResponse<AccountResponse> {
+    let account_repo = get_account_repo();
+    let caller = ic_service::get_caller().to_text();
+    let mut account = match account_repo.get_account() {
+        None => return HttpResponse::from(IdentityManagerError::AccountNotFound),
+        Some(account) => account,
+    };
+    if !account
+        .access_points
+        .iter()
+        .any(|ap| ap.principal_id.eq(&caller) && ap.device_type.eq(&DeviceType::Passkey))
+    {
+        return HttpResponse::from(IdentityManagerError::PasskeyRequired);
+    }
+    if account.pending_recovery.is_none() {
+        return HttpResponse::from(IdentityManagerError::NoPendingRecovery);
+    }
+    account.pending_recovery = None;
+    account_repo.store_account(account.clone());
+    forget_pending_recovery(&account.principal_id);
+    audit_service::record_for_account(&account, Some(caller), AccountEventType::CancelRecovery, None);
+    to_success_response(account_to_account_response(account))
+}
+
+/// Timers do not survive an upgrade, so completions of pending recoveries are scheduled again.
+pub fn post_upgrade() {
+    let now = ic_service::get_time();
+    let pending: Vec<(String, u64)> =
+        PENDING_RECOVERIES.with(|pending| pending.borrow().iter().collect());
+    for (root, executable_at) in pending {
+        schedule_completion(root, Duration::from_nanos(executable_at.saturating_sub(now)));
+    }
+}
+
+/// Schedules the pending recovery of a restored account, so it completes like one initiated here.
+pub fn restore_pending_recovery(account: &Account) {
+    if let Some(pending) = account.pending_recovery.as_ref() {
+        PENDING_RECOVERIES.with(|recoveries| {
+            recoveries
+                .borrow_mut()
+                .insert(account.principal_id.clone(), pending.executable_at)
+        });
+        let delay = pending.executable_at.saturating_sub(ic_service::get_time());
+        schedule_completion(account.principal_id.clone(), Duration::from_nanos(delay));
+    }
+}
+
+/// Drops the completion of the account from the schedule restored after an upgrade.
+pub fn forget_pending_recovery(root: &str) {
+    PENDING_RECOVERIES.with(|pending| pending.borrow_mut().remove(&root.to_string()));
+}
+
+fn schedule_completion(root: String, delay: Duration) {
+    ic_cdk_timers::set_timer(delay, move || complete_recovery(root));
+}
+
+/// Adds the pending access point to the account and resets 2FA.
+/// Does nothing if the recovery was cancelled or replaced by a later one in the meantime.
+fn complete_recovery(root: String) {
+    let account_repo = get_account_repo();
+    let mut account = match account_repo.get_account_by_root(root.clone()) {
+        None => return forget_pending_recovery(&root),
+        Some(account) => account,
+    };
+    let pending = match account.pending_recovery.clone() {
+        None => return forget_pending_recovery(&root),
+        // A later recovery replaced this one, its own timer completes it.
+        Some(pending) if pending.executable_at > ic_service::get_time() => return,
+        Some(pending) => pending,
+    };
+    forget_pending_recovery(&root);
+    account.pending_recovery = None;
+
+    let principal_id = pending.access_point.principal_id.clone();
+    if account_repo.is_principal_indexed(principal_id.clone()) {
+        // The access point was registered elsewhere during the delay, the recovery is dropped.
+        account_repo.store_account(account);
+        return;
+    }
+    account.access_points.insert(pending.access_point);
+    account.is2fa_enabled = false;
+    account.base_fields = account.base_fields.with_modified_now();
+    account_repo.store_account(account.clone());
+    account_repo.update_account_index_with_pub_key(principal_id.clone(), root);
+    audit_service::record_for_account(&account, None, AccountEventType::CompleteRecovery, Some(principal_id));
+}
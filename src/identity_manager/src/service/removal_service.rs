@@ -0,0 +1,127 @@
+use ic_stable_structures::StableBTreeMap;
+use std::cell::RefCell;
+use std::time::Duration;
+
+use crate::container::container_wrapper::get_account_repo;
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::{AccountResponse, DeviceType};
+use crate::ic_service;
+use crate::mapper::account_mapper::account_to_account_response;
+use crate::repository::access_point_repo::AccessPoint;
+use crate::repository::account_event_repo::AccountEventType;
+use crate::repository::account_repo::{Account, AccountRepoTrait, PendingRemoval};
+use crate::repository::memory::{get_memory, Memory, PENDING_REMOVALS_MEMORY_ID};
+use crate::response_mapper::{to_success_response, HttpResponse};
+use crate::service::audit_service;
+use crate::service::certified_service::{certify_account, uncertify_account};
+
+thread_local! {
+    /// Root principals of accounts waiting out the removal grace period to the time they are purged.
+    /// Needed to schedule the purge timers again after an upgrade without scanning all accounts.
+    static PENDING_REMOVALS: RefCell<StableBTreeMap<String, u64, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(PENDING_REMOVALS_MEMORY_ID)));
+}
+
+/// Removing an account and cancelling the removal need the passkey if 2FA is enabled,
+/// otherwise the recovery phrase if the account has one, otherwise any access point.
+pub fn authorize(account: &Account, principal: &str) -> Result<(), IdentityManagerError> {
+    if account.is2fa_enabled {
+        match find_access_point(account, &DeviceType::Passkey) {
+            Some(device) if device.principal_id.eq(principal) => {}
+            _ => return Err(IdentityManagerError::PasskeyRequired),
+        }
+    } else if let Some(device) = find_access_point(account, &DeviceType::Recovery) {
+        if !device.principal_id.eq(principal) {
+            return Err(IdentityManagerError::RecoveryPhraseRequired);
+        }
+    }
+    Ok(())
+}
+
+/// Marks the account as pending removal and schedules the purge once the grace period ends.
+pub fn request_removal(mut account: Account, requested_by: String, grace_period: Duration) {
+    let requested_at = ic_service::get_time();
+    let purge_at = requested_at + grace_period.as_nanos() as u64;
+    account.pending_removal = Some(PendingRemoval {
+        requested_by,
+        requested_at,
+        purge_at,
+    });
+    account.base_fields = account.base_fields.with_modified_now();
+    get_account_repo().store_account(account.clone());
+    uncertify_account(&account);
+    PENDING_REMOVALS.with(|pending| {
+        pending
+            .borrow_mut()
+            .insert(account.principal_id.clone(), purge_at)
+    });
+    schedule_purge(account.principal_id, grace_period);
+}
+
+pub fn cancel_removal() -> HttpResponse<AccountResponse> {
+    let account_repo = get_account_repo();
+    let caller = ic_service::get_caller().to_text();
+    let mut account = match account_repo.get_account_pending_removal() {
+        None => return HttpResponse::from(IdentityManagerError::NoPendingRemoval),
+        Some(account) => account,
+    };
+    if let Err(error) = authorize(&account, &caller) {
+        return HttpResponse::from(error);
+    }
+    account.pending_removal = None;
+    account.base_fields = account.base_fields.with_modified_now();
+    account_repo.store_account(account.clone());
+    certify_account(&account);
+    PENDING_REMOVALS.with(|pending| pending.borrow_mut().remove(&account.principal_id));
+    audit_service::record_for_account(&account, Some(caller), AccountEventType::CancelRemoveAccount, None);
+    to_success_response(account_to_account_response(account))
+}
+
+/// Timers do not survive an upgrade, so purges of pending removals are scheduled again.
+pub fn post_upgrade() {
+    let now = ic_service::get_time();
+    let pending: Vec<(String, u64)> =
+        PENDING_REMOVALS.with(|pending| pending.borrow().iter().collect());
+    for (root, purge_at) in pending {
+        schedule_purge(root, Duration::from_nanos(purge_at.saturating_sub(now)));
+    }
+}
+
+/// Schedules the purge of a restored account pending removal, so it is purged like one requested here.
+pub fn restore_pending_removal(account: &Account) {
+    if let Some(pending) = account.pending_removal.as_ref() {
+        uncertify_account(account);
+        PENDING_REMOVALS.with(|removals| {
+            removals
+                .borrow_mut()
+                .insert(account.principal_id.clone(), pending.purge_at)
+        });
+        let delay = pending.purge_at.saturating_sub(ic_service::get_time());
+        schedule_purge(account.principal_id.clone(), Duration::from_nanos(delay));
+    }
+}
+
+fn schedule_purge(root: String, delay: Duration) {
+    ic_cdk_timers::set_timer(delay, move || purge(root));
+}
+
+/// Removes the account for good.
+/// Does nothing if the removal was cancelled or requested again in the meantime.
+fn purge(root: String) {
+    let account_repo = get_account_repo();
+    let account = match account_repo.get_account_by_root(root.clone()) {
+        None => return,
+        Some(account) => account,
+    };
+    match account.pending_removal {
+        None => return,
+        Some(pending) if pending.purge_at > ic_service::get_time() => return,
+        Some(_) => {}
+    }
+    PENDING_REMOVALS.with(|pending| pending.borrow_mut().remove(&root));
+    account_repo.remove_account_by_root(root);
+}
+
+fn find_access_point<'a>(account: &'a Account, device_type: &DeviceType) -> Option<&'a AccessPoint> {
+    account.access_points.iter().find(|ap| ap.device_type.eq(device_type))
+}
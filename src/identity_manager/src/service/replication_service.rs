@@ -0,0 +1,160 @@
+use std::cell::{Cell, RefCell};
+use std::ops::Bound::{Excluded, Unbounded};
+use std::time::Duration;
+
+use candid::{CandidType, Deserialize, Principal};
+use ic_cdk::api::instruction_counter;
+use ic_cdk_timers::TimerId;
+
+use crate::ic_service;
+use crate::repository::account_repo::{ACCOUNTS, PRINCIPAL_INDEX};
+use crate::repository::change_log_repo::{AccountChange, ChangeLogEntry, ChangeLogRepo};
+use crate::repository::repo::ConfigurationRepo;
+use crate::service::job_service::BatchOutcome;
+
+/// Changes sent to the backup canister in one call.
+const PUSH_BATCH_SIZE: usize = 500;
+/// Used when `heartbeat` is not configured.
+const DEFAULT_PUSH_INTERVAL_SECS: u64 = 60;
+
+thread_local! {
+    static PUSH_TIMER: RefCell<Option<TimerId>> = const { RefCell::new(None) };
+    /// Set while a push awaits the backup canister, so timer ticks do not send the same changes twice.
+    static PUSH_IN_FLIGHT: Cell<bool> = const { Cell::new(false) };
+}
+
+/// How far the backup canister lags behind the change log.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ReplicationStatus {
+    pub backup_canister_id: Option<String>,
+    pub next_seq: u64,
+    pub acked_seq: u64,
+    /// Changes not yet acknowledged by the backup canister.
+    pub pending: u64,
+    /// Time since the oldest unacknowledged change, zero when the backup is up to date.
+    pub lag_ns: u64,
+    pub last_attempt_at: Option<u64>,
+    pub last_ack_at: Option<u64>,
+    pub last_error: Option<String>,
+    pub failed_attempts: u32,
+}
+
+/// Pushes the change log every `heartbeat` seconds.
+/// The interval is read again before each tick, so configuration changes apply without a restart.
+pub fn schedule() {
+    let interval = ConfigurationRepo::get()
+        .heartbeat
+        .map_or(DEFAULT_PUSH_INTERVAL_SECS, |secs| secs.max(1) as u64);
+    let timer = ic_cdk_timers::set_timer(Duration::from_secs(interval), || {
+        ic_cdk::spawn(push());
+        schedule();
+    });
+    PUSH_TIMER.with(|previous| {
+        if let Some(previous) = previous.borrow_mut().replace(timer) {
+            ic_cdk_timers::clear_timer(previous);
+        }
+    });
+}
+
+/// Sends the oldest unacknowledged changes to the backup canister.
+/// Acknowledged changes are dropped from the log, a failed call is retried on the next tick.
+pub async fn push() {
+    let backup_canister = match ConfigurationRepo::get().backup_canister_id {
+        Some(id) => id,
+        None => return,
+    };
+    if PUSH_IN_FLIGHT.with(|in_flight| in_flight.replace(true)) {
+        return;
+    }
+    let batch = ChangeLogRepo::get_pending(PUSH_BATCH_SIZE);
+    if batch.is_empty() {
+        PUSH_IN_FLIGHT.with(|in_flight| in_flight.set(false));
+        return;
+    }
+    let full_batch = batch.len() == PUSH_BATCH_SIZE;
+    // The backup canister cannot have applied changes it was never sent.
+    let max_acked_seq = batch[batch.len() - 1].seq + 1;
+    ChangeLogRepo::record_attempt(ic_service::get_time());
+
+    let result = match Principal::from_text(&backup_canister) {
+        Ok(canister) => {
+            ic_cdk::call::<(Vec<ChangeLogEntry>,), (u64,)>(canister, "apply_changes", (batch,))
+                .await
+                .map_err(|(code, message)| format!("{:?}: {}", code, message))
+        }
+        Err(_) => Err(format!("Invalid backup canister id {}", backup_canister)),
+    };
+    PUSH_IN_FLIGHT.with(|in_flight| in_flight.set(false));
+
+    match result {
+        Ok((acked_seq,)) => {
+            ChangeLogRepo::acknowledge(acked_seq.min(max_acked_seq), ic_service::get_time());
+            // A full batch means the backup is behind, catch up without waiting for the next tick.
+            if full_batch {
+                ic_cdk_timers::set_timer(Duration::ZERO, || ic_cdk::spawn(push()));
+            }
+        }
+        Err(error) => ChangeLogRepo::record_failure(error),
+    }
+}
+
+pub fn get_status() -> ReplicationStatus {
+    let state = ChangeLogRepo::get_state();
+    let lag_ns = ChangeLogRepo::get_oldest_pending().map_or(0, |entry| {
+        ic_service::get_time().saturating_sub(entry.timestamp)
+    });
+    ReplicationStatus {
+        backup_canister_id: ConfigurationRepo::get().backup_canister_id,
+        next_seq: state.next_seq,
+        acked_seq: state.acked_seq,
+        pending: state.next_seq.saturating_sub(state.acked_seq),
+        lag_ns,
+        last_attempt_at: state.last_attempt_at,
+        last_ack_at: state.last_ack_at,
+        last_error: state.last_error,
+        failed_attempts: state.failed_attempts,
+    }
+}
+
+/// Appends every account after `cursor` with its `PRINCIPAL_INDEX` entries to the change log until the instruction limit is reached.
+/// Seeds a backup canister that was attached after accounts had been created.
+pub fn snapshot_batch(cursor: Option<String>, instruction_limit: u64) -> BatchOutcome {
+    let mut outcome = BatchOutcome {
+        cursor: cursor.clone(),
+        processed: 0,
+        finished: true,
+    };
+    let start = cursor.map_or(Unbounded, Excluded);
+
+    ACCOUNTS.with(|accounts_ref| {
+        PRINCIPAL_INDEX.with(|index_ref| {
+            let accounts = accounts_ref.borrow();
+            let index = index_ref.borrow();
+
+            for (root, account) in accounts.range((start, Unbounded)) {
+                if instruction_counter() > instruction_limit {
+                    outcome.finished = false;
+                    break;
+                }
+                ChangeLogRepo::append(AccountChange::StoreAccount {
+                    root: root.clone(),
+                    account: ChangeLogRepo::encode_account(&account),
+                });
+                let principals = std::iter::once(root.clone())
+                    .chain(account.access_points.iter().map(|ap| ap.principal_id.clone()));
+                for principal in principals {
+                    if index.get(&principal).as_ref() == Some(&root) {
+                        ChangeLogRepo::append(AccountChange::SetIndex {
+                            principal,
+                            root: root.clone(),
+                        });
+                    }
+                }
+                outcome.cursor = Some(root);
+                outcome.processed += 1;
+            }
+        })
+    });
+
+    outcome
+}
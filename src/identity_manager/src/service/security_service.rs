@@ -1,8 +1,12 @@
 use ic_cdk::{caller, trap};
 
 use crate::container::container_wrapper::get_account_repo;
+use crate::http::error::IdentityManagerError;
 use crate::http::requests::DeviceType;
-use crate::repository::account_repo::AccountRepoTrait;
+use crate::repository::account_event_repo::AccountEventType;
+use crate::repository::account_repo::{Account, AccountRepoTrait};
+use crate::repository::step_up_policy_repo::{StepUpOperation, StepUpPolicyRepo, StepUpRule};
+use crate::service::audit_service;
 
 pub fn secure_2fa() {
     let principal = caller().to_text();
@@ -10,21 +14,94 @@ pub fn secure_2fa() {
 }
 
 pub fn secure_principal_2fa(principal: &String) {
-    match get_account_repo().get_account() {
-        None => {}
-        Some(acc) => {
-            if acc.is2fa_enabled {
-                let requester_ap = acc.access_points.clone().iter()
-                    .find(|l| l.principal_id.eq(principal))
-                    .map(|l|l.device_type)
-                    .expect("Failed to extract the device type for the given principal.");
-                if requester_ap.eq(&DeviceType::Email) {
-                    trap("Unauthorised")
-                }
-                if requester_ap.eq(&DeviceType::Unknown) {
-                    trap("Unauthorised")
-                }
-            }
+    if let Some(acc) = get_account_repo().get_account() {
+        if check_account_operation(&acc, principal, StepUpOperation::SignIn, None).is_err() {
+            trap("Unauthorised")
         }
     }
-}
\ No newline at end of file
+}
+
+/// Traps unless the caller's access point is allowed to perform the operation by the step-up policy.
+/// `target` is the type of the access point the operation is applied to, if any.
+pub fn secure_operation(operation: StepUpOperation, target: Option<DeviceType>) {
+    if check_operation(operation, target).is_err() {
+        trap("Unauthorised")
+    }
+}
+
+pub fn check_operation(
+    operation: StepUpOperation,
+    target: Option<DeviceType>,
+) -> Result<(), IdentityManagerError> {
+    match get_account_repo().get_account() {
+        None => Ok(()),
+        Some(acc) => check_account_operation(&acc, &caller().to_text(), operation, target),
+    }
+}
+
+/// The operator rules and the account's own rules must all allow the access point,
+/// so the account rules can only tighten the operator rules.
+pub fn check_account_operation(
+    account: &Account,
+    principal: &String,
+    operation: StepUpOperation,
+    target: Option<DeviceType>,
+) -> Result<(), IdentityManagerError> {
+    let requester = account
+        .access_points
+        .iter()
+        .find(|ap| ap.principal_id.eq(principal))
+        .map(|ap| ap.device_type);
+    let account_rules = account.step_up_rules.clone().unwrap_or_default();
+    let denied = StepUpPolicyRepo::get()
+        .rules
+        .iter()
+        .chain(account_rules.iter())
+        .filter(|rule| rule.applies(operation, target, account.is2fa_enabled))
+        .any(|rule| !requester.is_some_and(|device_type| rule.allowed.contains(&device_type)));
+    if denied {
+        Err(IdentityManagerError::StepUpRequired)
+    } else {
+        Ok(())
+    }
+}
+
+/// The type of the caller's access point with the given principal, used as the target of access point operations.
+pub fn access_point_type(principal: &String) -> Option<DeviceType> {
+    get_account_repo().get_account().and_then(|acc| {
+        acc.access_points
+            .iter()
+            .find(|ap| ap.principal_id.eq(principal))
+            .map(|ap| ap.device_type)
+    })
+}
+
+pub fn get_account_step_up_rules() -> Result<Vec<StepUpRule>, IdentityManagerError> {
+    get_account_repo()
+        .get_account()
+        .map(|acc| acc.step_up_rules.unwrap_or_default())
+        .ok_or(IdentityManagerError::AccountNotFound)
+}
+
+/// Replaces the caller's own rules, they apply on top of the operator policy.
+pub fn set_account_step_up_rules(
+    rules: Vec<StepUpRule>,
+) -> Result<Vec<StepUpRule>, IdentityManagerError> {
+    if rules.iter().any(|rule| rule.allowed.is_empty()) {
+        return Err(IdentityManagerError::InvalidStepUpRule);
+    }
+    let account_repo = get_account_repo();
+    let mut acc = account_repo
+        .get_account()
+        .ok_or(IdentityManagerError::AccountNotFound)?;
+    acc.step_up_rules = Some(rules.clone());
+    acc.base_fields = acc.base_fields.with_modified_now();
+    account_repo.store_account(acc.clone());
+    audit_service::record_for_account(
+        &acc,
+        Some(caller().to_text()),
+        AccountEventType::UpdateStepUpRules,
+        None,
+    );
+    Ok(rules)
+}
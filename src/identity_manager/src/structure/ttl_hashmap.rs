@@ -24,14 +24,32 @@ where
         self.map.get(key).map(|(value, _)| value)
     }
 
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        self.map.get_mut(key).map(|(value, _)| value)
+    }
+
     pub fn remove(&mut self, key: &K) -> Option<V> {
         self.map.remove(key).map(|(value, _)| value)
     }
 
+    /// Iterates over the entries with the time each one was inserted at.
+    pub fn entries(&self) -> impl Iterator<Item = (&K, &V, u64)> {
+        self.map.iter().map(|(key, (value, timestamp))| (key, value, *timestamp))
+    }
+
     pub fn count(&self) -> usize {
         self.map.len()
     }
 
+    /// Counts the entries inserted within the ttl, expired ones are kept until cleaned.
+    pub fn count_unexpired(&self, timestamp: u64) -> usize {
+        let deletion_timestamp = timestamp.saturating_sub(self.ttl_millis);
+        self.map
+            .values()
+            .filter(|(_, inserted)| *inserted > deletion_timestamp)
+            .count()
+    }
+
     pub fn clean_expired_entries(&mut self, timestamp: u64) {
         let mut deletion_timestamp = timestamp - self.ttl_millis;
         self.map.retain(|_, (_, expiration)| expiration > &mut deletion_timestamp)
@@ -0,0 +1,231 @@
+use candid::Principal;
+use ic_representation_independent_hash::{representation_independent_hash, Value};
+
+/// Prepended to the signed challenge, so the signature cannot be replayed as anything else.
+const CHALLENGE_DOMAIN: &[u8] = b"\x1Bnfid-access-point-challenge";
+/// The domain the IC signs delegations with.
+const DELEGATION_DOMAIN: &[u8] = b"\x1Aic-request-auth-delegation";
+
+const ED25519_DER_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+const P256_DER_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+/// `SEQUENCE { SEQUENCE { OID 1.3.6.1.4.1.56387.1.2 } ...`, found after the outer sequence header.
+const CANISTER_SIG_ALGORITHM: [u8; 14] = [
+    0x30, 0x0c, 0x06, 0x0a, 0x2b, 0x06, 0x01, 0x04, 0x01, 0x83, 0xb8, 0x43, 0x01, 0x02,
+];
+/// DER of the IC mainnet root key, canister signatures are certified by it.
+const IC_ROOT_PUBLIC_KEY_DER: &str = "308182301d060d2b0601040182dc7c0503010201060c2b0601040182dc7c05030201036100814c0e6ec71fab583b08bd81373c255c3c371b2e84863c98a4f1e08b74235d14fb5d9c0cd546d9685f913a0c0b2cc5341583bf4b4392e467db96d65b9bb4cb717112f8472e0d5a4d14505ffd7484b01291091c5f87b98883463f98091a0baaae";
+const IC_ROOT_PUBLIC_KEY_LENGTH: usize = 96;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProofError {
+    PrincipalMismatch,
+    UnsupportedKey,
+    InvalidSignature,
+    DelegationMismatch,
+    DelegationExpired,
+}
+
+/// A delegation from the new key to the session key of the caller, as signed by the new key.
+#[derive(Clone, Debug)]
+pub struct SignedDelegation<'a> {
+    pub pubkey: &'a [u8],
+    pub expiration: u64,
+    pub targets: Option<&'a [Principal]>,
+}
+
+/// The bytes the new key signs to answer a challenge issued to `caller`.
+pub fn challenge_message(nonce: &[u8], caller: &Principal) -> Vec<u8> {
+    let mut message = CHALLENGE_DOMAIN.to_vec();
+    message.extend_from_slice(nonce);
+    message.extend_from_slice(caller.as_slice());
+    message
+}
+
+/// The bytes the new key signs when it delegates to another key, as defined by the IC interface specification.
+pub fn delegation_message(delegation: &SignedDelegation) -> Vec<u8> {
+    let mut fields = vec![
+        ("pubkey".to_string(), Value::Bytes(delegation.pubkey.to_vec())),
+        ("expiration".to_string(), Value::Number(delegation.expiration)),
+    ];
+    if let Some(targets) = delegation.targets {
+        fields.push((
+            "targets".to_string(),
+            Value::Array(
+                targets
+                    .iter()
+                    .map(|target| Value::Bytes(target.as_slice().to_vec()))
+                    .collect(),
+            ),
+        ));
+    }
+    let mut message = DELEGATION_DOMAIN.to_vec();
+    message.extend_from_slice(&representation_independent_hash(&fields));
+    message
+}
+
+/// Checks that the delegation targets the session key of `caller` and is still valid at `now`.
+pub fn check_delegation(
+    delegation: &SignedDelegation,
+    caller: &Principal,
+    now: u64,
+) -> Result<(), ProofError> {
+    if Principal::self_authenticating(delegation.pubkey).ne(caller) {
+        return Err(ProofError::DelegationMismatch);
+    }
+    if delegation.expiration <= now {
+        return Err(ProofError::DelegationExpired);
+    }
+    Ok(())
+}
+
+/// Verifies that `signature` over `message` was made by the DER encoded `public_key` of `principal`.
+/// Ed25519, ECDSA P-256 and canister signature keys are supported.
+pub fn verify_key_signature(
+    principal: &Principal,
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), ProofError> {
+    if Principal::self_authenticating(public_key).ne(principal) {
+        return Err(ProofError::PrincipalMismatch);
+    }
+    if let Some(key) = public_key.strip_prefix(&ED25519_DER_PREFIX[..]) {
+        verify_ed25519(key, message, signature)
+    } else if let Some(key) = public_key.strip_prefix(&P256_DER_PREFIX[..]) {
+        verify_p256(key, message, signature)
+    } else if public_key.get(2..16) == Some(&CANISTER_SIG_ALGORITHM[..]) {
+        verify_canister_signature(public_key, message, signature)
+    } else {
+        Err(ProofError::UnsupportedKey)
+    }
+}
+
+fn verify_ed25519(key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), ProofError> {
+    let key: [u8; 32] = key.try_into().map_err(|_| ProofError::UnsupportedKey)?;
+    let key = ed25519_dalek::VerifyingKey::from_bytes(&key).map_err(|_| ProofError::UnsupportedKey)?;
+    let signature =
+        ed25519_dalek::Signature::from_slice(signature).map_err(|_| ProofError::InvalidSignature)?;
+    key.verify_strict(message, &signature)
+        .map_err(|_| ProofError::InvalidSignature)
+}
+
+fn verify_p256(key: &[u8], message: &[u8], signature: &[u8]) -> Result<(), ProofError> {
+    use p256::ecdsa::signature::Verifier;
+    let key = p256::ecdsa::VerifyingKey::from_sec1_bytes(key).map_err(|_| ProofError::UnsupportedKey)?;
+    let signature =
+        p256::ecdsa::Signature::from_slice(signature).map_err(|_| ProofError::InvalidSignature)?;
+    key.verify(message, &signature)
+        .map_err(|_| ProofError::InvalidSignature)
+}
+
+fn verify_canister_signature(
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<(), ProofError> {
+    let root_key = hex::decode(IC_ROOT_PUBLIC_KEY_DER).expect("Failed to decode the IC root key.");
+    let root_key = &root_key[root_key.len() - IC_ROOT_PUBLIC_KEY_LENGTH..];
+    ic_signature_verification::verify_canister_sig(message, signature, public_key, root_key)
+        .map_err(|_| ProofError::InvalidSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::Signer;
+
+    fn ed25519_key() -> (ed25519_dalek::SigningKey, Vec<u8>) {
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let mut der = ED25519_DER_PREFIX.to_vec();
+        der.extend_from_slice(key.verifying_key().as_bytes());
+        (key, der)
+    }
+
+    fn p256_key() -> (p256::ecdsa::SigningKey, Vec<u8>) {
+        let key = p256::ecdsa::SigningKey::from_slice(&[9; 32]).unwrap();
+        let mut der = P256_DER_PREFIX.to_vec();
+        der.extend_from_slice(key.verifying_key().to_encoded_point(false).as_bytes());
+        (key, der)
+    }
+
+    fn caller() -> Principal {
+        Principal::self_authenticating([1, 2, 3])
+    }
+
+    #[test]
+    fn verifies_ed25519_challenge() {
+        let (key, der) = ed25519_key();
+        let principal = Principal::self_authenticating(&der);
+        let message = challenge_message(b"nonce", &caller());
+        let signature = key.sign(&message).to_bytes();
+
+        assert_eq!(verify_key_signature(&principal, &der, &message, &signature), Ok(()));
+        let other_message = challenge_message(b"other", &caller());
+        assert_eq!(
+            verify_key_signature(&principal, &der, &other_message, &signature),
+            Err(ProofError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn verifies_p256_challenge() {
+        use p256::ecdsa::signature::Signer;
+        let (key, der) = p256_key();
+        let principal = Principal::self_authenticating(&der);
+        let message = challenge_message(b"nonce", &caller());
+        let signature: p256::ecdsa::Signature = key.sign(&message);
+
+        assert_eq!(
+            verify_key_signature(&principal, &der, &message, &signature.to_bytes()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_key_of_another_principal_and_unknown_keys() {
+        let (key, der) = ed25519_key();
+        let message = challenge_message(b"nonce", &caller());
+        let signature = key.sign(&message).to_bytes();
+
+        assert_eq!(
+            verify_key_signature(&caller(), &der, &message, &signature),
+            Err(ProofError::PrincipalMismatch)
+        );
+        let unknown = vec![0x30, 0x03, 0x01, 0x02, 0x03];
+        assert_eq!(
+            verify_key_signature(&Principal::self_authenticating(&unknown), &unknown, &message, &signature),
+            Err(ProofError::UnsupportedKey)
+        );
+    }
+
+    #[test]
+    fn verifies_delegation_to_caller_session() {
+        let (key, der) = ed25519_key();
+        let principal = Principal::self_authenticating(&der);
+        let session_key = vec![4, 5, 6];
+        let session = Principal::self_authenticating(&session_key);
+        let delegation = SignedDelegation {
+            pubkey: &session_key,
+            expiration: 1_000,
+            targets: None,
+        };
+        let message = delegation_message(&delegation);
+        let signature = key.sign(&message).to_bytes();
+
+        assert_eq!(check_delegation(&delegation, &session, 999), Ok(()));
+        assert_eq!(verify_key_signature(&principal, &der, &message, &signature), Ok(()));
+        assert_eq!(
+            check_delegation(&delegation, &caller(), 999),
+            Err(ProofError::DelegationMismatch)
+        );
+        assert_eq!(
+            check_delegation(&delegation, &session, 1_000),
+            Err(ProofError::DelegationExpired)
+        );
+    }
+}
@@ -7,7 +7,8 @@ use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use candid::Principal;
 use ic_cdk::api::time;
-use crate::http::requests::Challenge;
+use crate::http::error::IdentityManagerError;
+use crate::http::requests::{Challenge, ChallengeAttempt, ChallengeType, ProofOfWorkChallenge};
 use crate::repository::repo::{CAPTCHA_CAHLLENGES, CONFIGURATION};
 
 // Some time helpers
@@ -22,6 +23,27 @@ pub type Salt = [u8; 32];
 #[derive(Clone, Debug)]
 pub struct Base64(pub String);
 
+const DEFAULT_POW_DIFFICULTY: u8 = 16;
+const MAX_POW_DIFFICULTY: u8 = 28;
+const POW_NONCE_LENGTH: usize = 16;
+const POW_MAX_SOLUTION_LENGTH: usize = 64;
+const DEFAULT_MAX_CHALLENGE_ATTEMPTS: u8 = 3;
+
+/// The expected answer to an issued challenge.
+#[derive(Clone, Debug)]
+pub enum ChallengeSolution {
+    /// Issued while under the free quota, any attempt is accepted.
+    Free,
+    Image(String),
+    ProofOfWork(ProofOfWorkChallenge),
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptchaChallenge {
+    pub solution: ChallengeSolution,
+    pub wrong_attempts: u8,
+}
+
 lazy_static! {
     /// Problematic characters that are easily mixed up by humans to "normalized" replacement.
     /// I.e. the captcha will only contain a "replaced" character (values below in map) if the
@@ -67,7 +89,7 @@ lazy_static! {
 
 }
 
-pub async fn generate_captcha() -> Challenge{
+pub async fn generate_captcha() -> Challenge {
     let time = time();
     let mut rng = &mut make_rng().await;
     let key = random_string(&mut rng, 10);
@@ -75,35 +97,122 @@ pub async fn generate_captcha() -> Challenge{
         challenges.borrow_mut().clean_expired_entries(time);
         challenges.borrow().count()
     });
-    let chars: Option<String>;
-    let challenge: Challenge;
-    let max_free_captcha_per_minute = CONFIGURATION.with(|config| config.borrow().max_free_captcha_per_minute);
-    if challenges_in_progress <= max_free_captcha_per_minute as usize {
-        challenge = Challenge {
-            png_base64: None,
-            challenge_key: key.to_string(),
-        };
-        chars = None;
+    let config = CONFIGURATION.with(|config| config.borrow().get().clone());
+    let mut challenge = Challenge {
+        png_base64: None,
+        challenge_key: key.to_string(),
+        proof_of_work: None,
+    };
+    let solution = if challenges_in_progress <= config.max_free_captcha_per_minute as usize {
+        ChallengeSolution::Free
     } else {
-        let (Base64(png_base64), res_chars) = create_captcha(rng);
-
-        challenge = Challenge {
-            png_base64: Some(png_base64),
-            challenge_key: key.to_string(),
-        };
-        chars = Some(res_chars);
-    }
+        match config.challenge_type.unwrap_or(ChallengeType::Image) {
+            ChallengeType::Image => {
+                let (Base64(png_base64), res_chars) = create_captcha(rng);
+                challenge.png_base64 = Some(png_base64);
+                ChallengeSolution::Image(res_chars)
+            }
+            ChallengeType::ProofOfWork => {
+                let proof_of_work = ProofOfWorkChallenge {
+                    nonce: random_string(&mut rng, POW_NONCE_LENGTH),
+                    difficulty: proof_of_work_difficulty(
+                        config.proof_of_work_difficulty.unwrap_or(DEFAULT_POW_DIFFICULTY),
+                        challenges_in_progress,
+                        config.max_free_captcha_per_minute,
+                    ),
+                };
+                challenge.proof_of_work = Some(proof_of_work.clone());
+                ChallengeSolution::ProofOfWork(proof_of_work)
+            }
+        }
+    };
     CAPTCHA_CAHLLENGES.with(|challenges| {
         challenges.borrow_mut().clean_expired_entries(time);
         challenges.borrow_mut().insert(
             key.clone(),
-            chars,
+            CaptchaChallenge {
+                solution,
+                wrong_attempts: 0,
+            },
             time,
         );
     });
     challenge
 }
 
+/// Checks the attempt against the issued challenge.
+/// A solved challenge is consumed, a wrong solution is counted and the challenge is dropped once the limit is reached.
+pub fn verify_challenge_attempt(challenge_attempt: ChallengeAttempt) -> Result<(), IdentityManagerError> {
+    let max_attempts = CONFIGURATION.with(|config| {
+        config
+            .borrow()
+            .get()
+            .max_challenge_attempts
+            .unwrap_or(DEFAULT_MAX_CHALLENGE_ATTEMPTS)
+    });
+    CAPTCHA_CAHLLENGES.with(|challenges| {
+        let mut challenges = challenges.borrow_mut();
+        challenges.clean_expired_entries(time());
+
+        let challenge = challenges
+            .get_mut(&challenge_attempt.challenge_key)
+            .ok_or(IdentityManagerError::IncorrectCaptchaKey)?;
+        let solved = match &challenge.solution {
+            ChallengeSolution::Free => true,
+            ChallengeSolution::Image(solution) => {
+                let solution_attempt = challenge_attempt
+                    .chars
+                    .clone()
+                    .ok_or(IdentityManagerError::SolutionRequired)?;
+                check_captcha_solution(solution_attempt, solution.clone()).is_ok()
+            }
+            ChallengeSolution::ProofOfWork(proof_of_work) => {
+                let solution_attempt = challenge_attempt
+                    .chars
+                    .as_ref()
+                    .ok_or(IdentityManagerError::SolutionRequired)?;
+                check_proof_of_work(proof_of_work, solution_attempt)
+            }
+        };
+        if solved {
+            challenges.remove(&challenge_attempt.challenge_key);
+            return Ok(());
+        }
+        challenge.wrong_attempts += 1;
+        if challenge.wrong_attempts >= max_attempts {
+            challenges.remove(&challenge_attempt.challenge_key);
+        }
+        Err(IdentityManagerError::IncorrectCaptchaSolution)
+    })
+}
+
+/// The base difficulty grows by one bit each time the challenges in flight double past the free quota.
+fn proof_of_work_difficulty(base: u8, challenges_in_progress: usize, max_free: u16) -> u8 {
+    let load = challenges_in_progress / (max_free as usize).max(1);
+    let extra = (load + 1).ilog2() as u8;
+    base.saturating_add(extra).min(MAX_POW_DIFFICULTY)
+}
+
+fn check_proof_of_work(challenge: &ProofOfWorkChallenge, solution_attempt: &str) -> bool {
+    if solution_attempt.len() > POW_MAX_SOLUTION_LENGTH {
+        return false;
+    }
+    let hash = hex::decode(sha256::digest(format!("{}{}", challenge.nonce, solution_attempt)))
+        .expect("Failed to decode the SHA-256 digest of the proof of work.");
+    leading_zero_bits(&hash) >= challenge.difficulty as u32
+}
+
+fn leading_zero_bits(bytes: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in bytes {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
 // Get a random number generator based on 'raw_rand'
 pub async fn make_rng() -> rand_chacha::ChaCha20Rng {
     let seed = random_salt().await;
@@ -139,7 +248,7 @@ pub fn create_captcha<T: RngCore>(rng: T) -> (Base64, String) {
 
     let mut captcha = captcha::new_captcha_with(rng, CAPTCHA_FONT.clone());
 
-    let is_test = CONFIGURATION.with(|c| c.borrow().test_captcha);
+    let is_test = CONFIGURATION.with(|c| c.borrow().get().test_captcha);
 
     let captcha = captcha
         .set_charset(if is_test { &TEST_CAPTCHA } else { &CHALLENGE_CHARSET })
@@ -186,7 +295,7 @@ pub fn check_captcha_solution(solution_attempt: String, solution: String) -> Res
 
 
 /// Calls raw rand to retrieve a random salt (32 bytes).
-async fn random_salt() -> Salt {
+pub async fn random_salt() -> Salt {
     let res: Vec<u8> = match call(Principal::management_canister(), "raw_rand", ()).await {
         Ok((res,)) => res,
         Err((_, err)) => trap(&format!("failed to get salt: {err}")),
@@ -199,3 +308,53 @@ async fn random_salt() -> Salt {
     });
     salt
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(challenge: &ProofOfWorkChallenge) -> String {
+        (0u64..)
+            .map(|counter| counter.to_string())
+            .find(|attempt| check_proof_of_work(challenge, attempt))
+            .expect("The proof of work has a solution.")
+    }
+
+    #[test]
+    fn proof_of_work_accepts_only_solutions_of_the_required_difficulty() {
+        let challenge = ProofOfWorkChallenge {
+            nonce: "nonce".to_string(),
+            difficulty: 8,
+        };
+        let solution = solve(&challenge);
+        assert!(check_proof_of_work(&challenge, &solution));
+
+        let harder = ProofOfWorkChallenge {
+            difficulty: 255,
+            ..challenge.clone()
+        };
+        assert!(!check_proof_of_work(&harder, &solution));
+        assert!(!check_proof_of_work(&challenge, &"1".repeat(POW_MAX_SOLUTION_LENGTH + 1)));
+    }
+
+    #[test]
+    fn proof_of_work_difficulty_scales_with_challenges_in_progress() {
+        assert_eq!(proof_of_work_difficulty(16, 0, 10), 16);
+        assert_eq!(proof_of_work_difficulty(16, 10, 10), 17);
+        assert_eq!(proof_of_work_difficulty(16, 30, 10), 18);
+        assert_eq!(proof_of_work_difficulty(16, 10_000, 0), MAX_POW_DIFFICULTY);
+    }
+
+    #[test]
+    fn leading_zero_bits_counts_across_bytes() {
+        assert_eq!(leading_zero_bits(&[0, 0, 0b0001_0000]), 19);
+        assert_eq!(leading_zero_bits(&[0b1000_0000]), 0);
+        assert_eq!(leading_zero_bits(&[0, 0]), 16);
+    }
+
+    #[test]
+    fn image_solution_is_normalized() {
+        assert!(check_captcha_solution("Cs1".to_string(), "csi".to_string()).is_ok());
+        assert!(check_captcha_solution("abc".to_string(), "abd".to_string()).is_err());
+    }
+}
@@ -1,2 +1,4 @@
 pub mod validation_util;
-pub mod captcha;
\ No newline at end of file
+pub mod access_point_proof;
+pub mod captcha;
+pub mod openid;
\ No newline at end of file
@@ -0,0 +1,288 @@
+use crate::repository::openid_repo::OpenIdProvider;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use candid::Principal;
+use rsa::pkcs1v15::{Signature, VerifyingKey};
+use rsa::signature::Verifier;
+use rsa::{BigUint, RsaPublicKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+/// Tolerated difference between the clock of the issuer and the canister time.
+const CLOCK_SKEW_SECS: u64 = 60;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum IdTokenError {
+    Malformed,
+    UnsupportedAlgorithm,
+    UnknownIssuer,
+    UnknownKey,
+    InvalidSignature,
+    InvalidAudience,
+    Expired,
+    InvalidNonce,
+}
+
+#[derive(Deserialize)]
+struct IdTokenHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::Single(aud) => aud == client_id,
+            Audience::Multiple(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct RawClaims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    exp: u64,
+    nonce: Option<String>,
+    email: Option<String>,
+}
+
+/// Claims of a verified ID token the identity manager relies on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub email: Option<String>,
+}
+
+impl IdTokenClaims {
+    /// `sub` is only unique per issuer, so accounts are bound to both.
+    pub fn subject_key(&self) -> String {
+        format!("{}#{}", self.iss, self.sub)
+    }
+}
+
+/// The nonce the client has to request the ID token with, binding the token to the principal that presents it.
+pub fn expected_nonce(principal: &Principal) -> String {
+    hex::encode(Sha256::digest(principal.as_slice()))
+}
+
+/// Verifies the RS256 signature of a compact JWT against the keys of its issuer,
+/// then checks that it is meant for one of the issuer's client ids, not expired and issued for `caller`.
+pub fn verify_id_token(
+    token: &str,
+    providers: &[OpenIdProvider],
+    caller: &Principal,
+    now_secs: u64,
+) -> Result<IdTokenClaims, IdTokenError> {
+    let mut parts = token.split('.');
+    let (header, payload, signature) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(header), Some(payload), Some(signature), None) => (header, payload, signature),
+        _ => return Err(IdTokenError::Malformed),
+    };
+    let header: IdTokenHeader = decode_json(header)?;
+    if header.alg != "RS256" {
+        return Err(IdTokenError::UnsupportedAlgorithm);
+    }
+    let claims: RawClaims = decode_json(payload)?;
+
+    let provider = providers
+        .iter()
+        .find(|provider| provider.issuer == claims.iss)
+        .ok_or(IdTokenError::UnknownIssuer)?;
+    let key = provider
+        .keys
+        .iter()
+        .find(|key| header.kid.as_ref().map_or(provider.keys.len() == 1, |kid| kid == &key.kid))
+        .ok_or(IdTokenError::UnknownKey)?;
+    let public_key = RsaPublicKey::new(
+        BigUint::from_bytes_be(&decode_base64(&key.n)?),
+        BigUint::from_bytes_be(&decode_base64(&key.e)?),
+    )
+    .map_err(|_| IdTokenError::UnknownKey)?;
+    let signature = Signature::try_from(decode_base64(signature)?.as_slice())
+        .map_err(|_| IdTokenError::Malformed)?;
+    let signed = &token[..header_and_payload_len(token)];
+    VerifyingKey::<Sha256>::new(public_key)
+        .verify(signed.as_bytes(), &signature)
+        .map_err(|_| IdTokenError::InvalidSignature)?;
+
+    if !provider.client_ids.iter().any(|client_id| claims.aud.contains(client_id)) {
+        return Err(IdTokenError::InvalidAudience);
+    }
+    if claims.exp.saturating_add(CLOCK_SKEW_SECS) <= now_secs {
+        return Err(IdTokenError::Expired);
+    }
+    if claims.nonce.as_deref() != Some(expected_nonce(caller).as_str()) {
+        return Err(IdTokenError::InvalidNonce);
+    }
+    Ok(IdTokenClaims {
+        iss: claims.iss,
+        sub: claims.sub,
+        email: claims.email,
+    })
+}
+
+fn header_and_payload_len(token: &str) -> usize {
+    token.rfind('.').unwrap_or(token.len())
+}
+
+fn decode_base64(part: &str) -> Result<Vec<u8>, IdTokenError> {
+    URL_SAFE_NO_PAD
+        .decode(part)
+        .map_err(|_| IdTokenError::Malformed)
+}
+
+fn decode_json<T: for<'de> Deserialize<'de>>(part: &str) -> Result<T, IdTokenError> {
+    serde_json::from_slice(&decode_base64(part)?).map_err(|_| IdTokenError::Malformed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::repository::openid_repo::Jwk;
+    use rsa::pkcs1v15::SigningKey;
+    use rsa::signature::{SignatureEncoding, Signer};
+    use rsa::traits::PublicKeyParts;
+    use rsa::RsaPrivateKey;
+    use serde_json::json;
+
+    const ISSUER: &str = "https://accounts.example.com";
+    const CLIENT_ID: &str = "nfid-client";
+    const NOW: u64 = 1_700_000_000;
+
+    fn caller() -> Principal {
+        Principal::from_text("2vxsx-fae").unwrap()
+    }
+
+    fn generate_key() -> RsaPrivateKey {
+        RsaPrivateKey::new(&mut rand::thread_rng(), 2048).expect("Failed to generate a key.")
+    }
+
+    fn provider(key: &RsaPrivateKey) -> OpenIdProvider {
+        OpenIdProvider {
+            issuer: ISSUER.to_string(),
+            client_ids: vec![CLIENT_ID.to_string()],
+            keys: vec![Jwk {
+                kid: "key-1".to_string(),
+                n: URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                e: URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+            }],
+        }
+    }
+
+    fn sign(key: &RsaPrivateKey, header: serde_json::Value, claims: serde_json::Value) -> String {
+        let signed = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = SigningKey::<Sha256>::new(key.clone()).sign(signed.as_bytes());
+        format!("{}.{}", signed, URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+    }
+
+    fn claims() -> serde_json::Value {
+        json!({
+            "iss": ISSUER,
+            "sub": "user-42",
+            "aud": CLIENT_ID,
+            "exp": NOW + 600,
+            "nonce": expected_nonce(&caller()),
+            "email": "user@example.com",
+        })
+    }
+
+    fn header() -> serde_json::Value {
+        json!({ "alg": "RS256", "kid": "key-1", "typ": "JWT" })
+    }
+
+    #[test]
+    fn accepts_valid_token() {
+        let key = generate_key();
+        let token = sign(&key, header(), claims());
+
+        let claims = verify_id_token(&token, &[provider(&key)], &caller(), NOW).unwrap();
+
+        assert_eq!(claims.sub, "user-42");
+        assert_eq!(claims.subject_key(), format!("{}#user-42", ISSUER));
+        assert_eq!(claims.email, Some("user@example.com".to_string()));
+    }
+
+    #[test]
+    fn rejects_token_signed_by_another_key() {
+        let key = generate_key();
+        let token = sign(&generate_key(), header(), claims());
+
+        let result = verify_id_token(&token, &[provider(&key)], &caller(), NOW);
+
+        assert_eq!(result, Err(IdTokenError::InvalidSignature));
+    }
+
+    #[test]
+    fn rejects_unknown_issuer_and_key() {
+        let key = generate_key();
+        let mut other_issuer = claims();
+        other_issuer["iss"] = json!("https://evil.example.com");
+        let token = sign(&key, header(), other_issuer);
+        assert_eq!(
+            verify_id_token(&token, &[provider(&key)], &caller(), NOW),
+            Err(IdTokenError::UnknownIssuer)
+        );
+
+        let token = sign(&key, json!({ "alg": "RS256", "kid": "key-2" }), claims());
+        assert_eq!(
+            verify_id_token(&token, &[provider(&key)], &caller(), NOW),
+            Err(IdTokenError::UnknownKey)
+        );
+    }
+
+    #[test]
+    fn rejects_wrong_audience_expired_and_foreign_nonce() {
+        let key = generate_key();
+        let providers = [provider(&key)];
+
+        let mut wrong_audience = claims();
+        wrong_audience["aud"] = json!(["another-client"]);
+        let token = sign(&key, header(), wrong_audience);
+        assert_eq!(
+            verify_id_token(&token, &providers, &caller(), NOW),
+            Err(IdTokenError::InvalidAudience)
+        );
+
+        let token = sign(&key, header(), claims());
+        assert_eq!(
+            verify_id_token(&token, &providers, &caller(), NOW + 600 + CLOCK_SKEW_SECS),
+            Err(IdTokenError::Expired)
+        );
+
+        let other_caller = Principal::management_canister();
+        assert_eq!(
+            verify_id_token(&token, &providers, &other_caller, NOW),
+            Err(IdTokenError::InvalidNonce)
+        );
+    }
+
+    #[test]
+    fn rejects_other_algorithms_and_malformed_tokens() {
+        let key = generate_key();
+        let providers = [provider(&key)];
+
+        let token = sign(&key, json!({ "alg": "none" }), claims());
+        assert_eq!(
+            verify_id_token(&token, &providers, &caller(), NOW),
+            Err(IdTokenError::UnsupportedAlgorithm)
+        );
+        assert_eq!(
+            verify_id_token("not-a-token", &providers, &caller(), NOW),
+            Err(IdTokenError::Malformed)
+        );
+    }
+}
@@ -0,0 +1,236 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+
+use candid::{candid_method, CandidType, Principal};
+use canister_metrics::{CanisterStats, HttpRequest, HttpResponse};
+use ic_cdk::api::time;
+use ic_cdk::{caller, trap};
+use ic_cdk_macros::{init, query, update};
+use ic_stable_structures::memory_manager::{MemoryId, MemoryManager, VirtualMemory};
+use ic_stable_structures::storable::Bound;
+use ic_stable_structures::{DefaultMemoryImpl, StableBTreeMap, StableCell, Storable};
+use serde::Deserialize;
+use serde_bytes::ByteBuf;
+
+type Memory = VirtualMemory<DefaultMemoryImpl>;
+
+const STATE_MEMORY_ID: MemoryId = MemoryId::new(0);
+const ACCOUNTS_MEMORY_ID: MemoryId = MemoryId::new(1);
+const INDEX_MEMORY_ID: MemoryId = MemoryId::new(2);
+
+// Everything lives in stable memory, so upgrades do not serialize the replica.
+thread_local! {
+    static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
+        RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
+    static STATE: RefCell<StableCell<State, Memory>> = RefCell::new(
+        StableCell::init(get_memory(STATE_MEMORY_ID), State::default())
+            .expect("Failed to initialize the state stable cell.")
+    );
+    /// Root principal to the CBOR encoded account, as exported by `export_accounts` in the `Cbor` format.
+    static ACCOUNTS: RefCell<StableBTreeMap<String, Vec<u8>, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(ACCOUNTS_MEMORY_ID)));
+    /// Access point principal to root principal.
+    static INDEX: RefCell<StableBTreeMap<String, String, Memory>> =
+        RefCell::new(StableBTreeMap::init(get_memory(INDEX_MEMORY_ID)));
+}
+
+fn get_memory(id: MemoryId) -> Memory {
+    MEMORY_MANAGER.with(|manager| manager.borrow().get(id))
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct InitArgs {
+    pub im_canister: Principal,
+}
+
+/// Mirrors the change log entries of the identity manager.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub enum AccountChange {
+    CreateAccount { root: String, account: ByteBuf },
+    StoreAccount { root: String, account: ByteBuf },
+    RemoveAccount { root: String, account: ByteBuf },
+    SetIndex { principal: String, root: String },
+    RemoveIndex { principal: String },
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ChangeLogEntry {
+    pub seq: u64,
+    pub timestamp: u64,
+    pub change: AccountChange,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ReplicaStatus {
+    pub im_canister: Option<Principal>,
+    pub next_seq: Option<u64>,
+    pub accounts: u64,
+    pub index_entries: u64,
+    /// Timestamp of the last applied change as recorded by the identity manager.
+    pub last_change_at: Option<u64>,
+    pub last_applied_at: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, CandidType, Deserialize)]
+struct State {
+    im_canister: Option<Principal>,
+    /// The sequence number of the next change to apply, unset until the first change arrives.
+    next_seq: Option<u64>,
+    last_change_at: Option<u64>,
+    last_applied_at: Option<u64>,
+}
+
+impl Storable for State {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        Cow::Owned(candid::encode_one(self).expect("Failed to encode the state to candid."))
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        candid::decode_one(bytes.as_ref()).expect("Failed to decode the state from candid.")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Invoked when the canister starts.
+/// Initializes the application with `InitArgs` parameters and stores them in persistent storage.
+#[init]
+#[candid_method(init)]
+fn init(maybe_arg: Option<InitArgs>) {
+    if let Some(arg) = maybe_arg {
+        update_state(|state| state.im_canister = Some(arg.im_canister));
+    }
+}
+
+/// Applies changes pushed by the identity manager in sequence order and returns the sequence number of the next expected change.
+/// Changes that were already applied are skipped, so retried batches are harmless.
+/// A gap stops the batch, the identity manager resends from the returned sequence number.
+/// A fresh replica starts from the first change it receives, earlier accounts come from the `ReplicateAccounts` job.
+/// Accessible only to the identity manager.
+#[update]
+#[candid_method(update)]
+fn apply_changes(entries: Vec<ChangeLogEntry>) -> u64 {
+    let mut state = get_state();
+    if state.im_canister != Some(caller()) {
+        trap("Unauthorized");
+    }
+    for entry in entries {
+        let expected = state.next_seq.unwrap_or(entry.seq);
+        if entry.seq < expected {
+            continue;
+        }
+        if entry.seq > expected {
+            break;
+        }
+        match entry.change {
+            AccountChange::CreateAccount { root, account }
+            | AccountChange::StoreAccount { root, account }
+            | AccountChange::RemoveAccount { root, account } => {
+                ACCOUNTS.with(|accounts| accounts.borrow_mut().insert(root, account.into_vec()));
+            }
+            AccountChange::SetIndex { principal, root } => {
+                INDEX.with(|index| index.borrow_mut().insert(principal, root));
+            }
+            AccountChange::RemoveIndex { principal } => {
+                INDEX.with(|index| index.borrow_mut().remove(&principal));
+            }
+        }
+        state.next_seq = Some(entry.seq + 1);
+        state.last_change_at = Some(entry.timestamp);
+        state.last_applied_at = Some(time());
+    }
+    let next_seq = state.next_seq.unwrap_or_default();
+    update_state(|stored| *stored = state);
+    next_seq
+}
+
+/// Returns the replication progress of this replica.
+#[query]
+#[candid_method(query)]
+fn get_replica_status() -> ReplicaStatus {
+    let state = get_state();
+    ReplicaStatus {
+        im_canister: state.im_canister,
+        next_seq: state.next_seq,
+        accounts: ACCOUNTS.with(|accounts| accounts.borrow().len()),
+        index_entries: INDEX.with(|index| index.borrow().len()),
+        last_change_at: state.last_change_at,
+        last_applied_at: state.last_applied_at,
+    }
+}
+
+/// Returns the CBOR encoded account of the root principal.
+/// Accessible only to the identity manager and the controllers.
+#[query]
+#[candid_method(query)]
+fn get_account(root: String) -> Option<ByteBuf> {
+    authorize();
+    ACCOUNTS.with(|accounts| accounts.borrow().get(&root).map(ByteBuf::from))
+}
+
+/// Returns the root principal an access point principal belongs to.
+/// Accessible only to the identity manager and the controllers.
+#[query]
+#[candid_method(query)]
+fn get_root_by_principal(principal: String) -> Option<String> {
+    authorize();
+    INDEX.with(|index| index.borrow().get(&principal))
+}
+
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+#[candid_method(query)]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(
+        ic_cdk::api::canister_balance128(),
+        ic_cdk::api::stable::stable64_size(),
+    );
+    canister_metrics::http_request(&request, time(), stats, |w| {
+        let accounts = ACCOUNTS.with(|accounts| accounts.borrow().len());
+        let index_entries = INDEX.with(|index| index.borrow().len());
+        let next_seq = get_state().next_seq.unwrap_or_default();
+        w.encode_gauge("replica_accounts", accounts as f64, "Number of replicated accounts.")?;
+        w.encode_gauge(
+            "replica_index_entries",
+            index_entries as f64,
+            "Number of replicated principal index entries.",
+        )?;
+        w.encode_gauge(
+            "replica_next_seq",
+            next_seq as f64,
+            "Sequence number of the next change log entry to apply.",
+        )
+    })
+}
+
+fn get_state() -> State {
+    STATE.with(|state| state.borrow().get().clone())
+}
+
+fn update_state(update: impl FnOnce(&mut State)) {
+    STATE.with(|cell| {
+        let mut state = cell.borrow().get().clone();
+        update(&mut state);
+        cell.borrow_mut()
+            .set(state)
+            .expect("Failed to save the state to stable memory.");
+    });
+}
+
+fn authorize() {
+    let caller = caller();
+    let is_im = get_state().im_canister == Some(caller);
+    if !is_im && !ic_cdk::api::is_controller(&caller) {
+        trap("Unauthorized");
+    }
+}
+
+fn main() {}
+
+// Order dependent: do not move above any function annotated with #[candid_method]!
+candid::export_service!();
+
+#[ic_cdk_macros::query(name = "__get_candid_interface_tmp_hack")]
+fn export_candid() -> String {
+    __export_service()
+}
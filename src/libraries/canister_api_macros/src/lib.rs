@@ -1,15 +1,71 @@
 use proc_macro::TokenStream;
 
-use proc_macro2::Ident;
+use proc_macro2::{Ident, Span};
 use quote::{format_ident, quote};
-use syn::{FnArg, ItemFn, parse_macro_input, Pat, PatIdent, PatType, Signature};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{Expr, FnArg, ItemFn, parse_macro_input, Pat, PatIdent, PatType, Signature, Token};
 
+/// Suffixes given to the inner functions by the wrapping macros.
+const TWO_F_A_SUFFIX: &str = "_log_error";
+const GUARD_SUFFIX: &str = "_admin";
+
+/// Attributes of this crate that emit a guard function.
+const GUARD_ATTRIBUTES: [&str; 7] = [
+    "two_f_a",
+    "admin",
+    "lambda",
+    "operator",
+    "requires_role",
+    "paused",
+    "authenticated",
+];
+
+/// Emits `__guard_<function>(caller: &Principal) -> bool` with the ingress check of the guard,
+/// called by the `inspect_message` of `generate_inspect_message!`.
+/// A guard stacked below this one still has to expand on `inner`, so its guard function is named after
+/// `inner` and called from this one.
+fn guard_fn(function: &Ident, inner: &ItemFn, check: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let guard_name = guard_ident(function);
+    let nested = inner.attrs.iter().any(|attr| {
+        attr.path()
+            .segments
+            .last()
+            .is_some_and(|segment| GUARD_ATTRIBUTES.contains(&segment.ident.to_string().as_str()))
+    });
+    let nested_check = if nested {
+        let nested_guard = guard_ident(&inner.sig.ident);
+        quote! { && #nested_guard(caller) }
+    } else {
+        quote! {}
+    };
+    quote! {
+        #[doc(hidden)]
+        fn #guard_name(caller: &candid::Principal) -> bool {
+            #check #nested_check
+        }
+    }
+}
+
+fn guard_ident(function: &Ident) -> Ident {
+    format_ident!("__guard_{}", function)
+}
+
+fn authenticated_check() -> proc_macro2::TokenStream {
+    quote! { *caller != candid::Principal::anonymous() }
+}
+
+/// Checks the step-up policy before the endpoint runs.
+/// `#[two_f_a]` checks the sign-in rules, `#[two_f_a(RemoveAccessPoint)]` the rules of a `StepUpOperation`
+/// and `#[two_f_a(RemoveAccessPoint, target = expr)]` those for an access point of the `Option<DeviceType>` the expression evaluates to.
 #[proc_macro_attribute]
-pub fn two_f_a(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn two_f_a(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let step_up = parse_macro_input!(attr as StepUpArgs);
     let mut inner = parse_macro_input!(item as ItemFn);
     let wrapper_sig = inner.sig.clone();
-    let inner_method_name = format_ident!("{}_log_error", inner.sig.ident);
+    let inner_method_name = format_ident!("{}{}", inner.sig.ident, TWO_F_A_SUFFIX);
     inner.sig.ident = inner_method_name.clone();
+    let guard = guard_fn(&wrapper_sig.ident, &inner, authenticated_check());
 
     let is_async = inner.sig.asyncness.is_some();
     let arg_names = get_arg_names(&inner.sig);
@@ -20,23 +76,98 @@ pub fn two_f_a(_: TokenStream, item: TokenStream) -> TokenStream {
         quote! { #inner_method_name ( #(#arg_names),* ) }
     };
 
+    let check = match step_up.operation {
+        None => quote! { secure_2fa(); },
+        Some(operation) => {
+            let target = step_up
+                .target
+                .map_or_else(|| quote! { None }, |target| quote! { #target });
+            quote! { secure_operation(StepUpOperation::#operation, #target); }
+        }
+    };
+
     let expanded = quote!(
         #[allow(unused_mut)]
         #wrapper_sig {
-            secure_2fa();
+            #check
             #function_call
         }
         #inner
+        #guard
     );
     TokenStream::from(expanded)
 }
 
+#[derive(Default)]
+struct StepUpArgs {
+    operation: Option<Ident>,
+    target: Option<Expr>,
+}
+
+impl Parse for StepUpArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.is_empty() {
+            return Ok(StepUpArgs::default());
+        }
+        let operation = input.parse::<Ident>()?;
+        let mut target = None;
+        if input.parse::<Option<Token![,]>>()?.is_some() {
+            let key = input.parse::<Ident>()?;
+            if key != "target" {
+                return Err(syn::Error::new(key.span(), "expected `target = <expr>`"));
+            }
+            input.parse::<Token![=]>()?;
+            target = Some(input.parse::<Expr>()?);
+        }
+        Ok(StepUpArgs {
+            operation: Some(operation),
+            target,
+        })
+    }
+}
+
+/// Allows administrators and controllers.
 #[proc_macro_attribute]
 pub fn admin(_: TokenStream, item: TokenStream) -> TokenStream {
+    role_guard(&[format_ident!("Admin")], item)
+}
+
+/// Allows lambdas and administrators.
+#[proc_macro_attribute]
+pub fn lambda(_: TokenStream, item: TokenStream) -> TokenStream {
+    role_guard(&[format_ident!("Admin"), format_ident!("Lambda")], item)
+}
+
+/// Allows operators.
+#[proc_macro_attribute]
+pub fn operator(_: TokenStream, item: TokenStream) -> TokenStream {
+    role_guard(&[format_ident!("Operator")], item)
+}
+
+/// Allows callers holding any of the listed roles, e.g. `#[requires_role(Operator, Admin)]`.
+/// The roles are variants of the `Role` enum in scope, checked with `RoleRepo::has_any_role`.
+#[proc_macro_attribute]
+pub fn requires_role(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let roles = parse_macro_input!(attr with Punctuated::<Ident, Token![,]>::parse_terminated);
+    if roles.is_empty() {
+        return syn::Error::new(Span::call_site(), "requires_role expects at least one role")
+            .to_compile_error()
+            .into();
+    }
+    role_guard(&roles.into_iter().collect::<Vec<_>>(), item)
+}
+
+#[proc_macro_attribute]
+pub fn paused(_: TokenStream, item: TokenStream) -> TokenStream {
     let mut inner = parse_macro_input!(item as ItemFn);
     let wrapper_sig = inner.sig.clone();
-    let inner_method_name = format_ident!("{}_admin", inner.sig.ident);
+    let inner_method_name = format_ident!("{}{}", inner.sig.ident, GUARD_SUFFIX);
     inner.sig.ident = inner_method_name.clone();
+    let guard = guard_fn(
+        &wrapper_sig.ident,
+        &inner,
+        quote! { !ConfigurationRepo::get().account_creation_paused },
+    );
 
     let is_async = inner.sig.asyncness.is_some();
     let arg_names = get_arg_names(&inner.sig);
@@ -50,25 +181,27 @@ pub fn admin(_: TokenStream, item: TokenStream) -> TokenStream {
     let expanded = quote!(
         #[allow(unused_mut)]
         #wrapper_sig {
-            let caller = get_caller();
-            if AdminRepo::get().eq(&caller)
-                || ControllersRepo::contains(&caller) {
-                #function_call
+            if ConfigurationRepo::get().account_creation_paused {
+                trap("Account creation is paused due to high demand. Please try again later.")
             } else {
-                trap("Unauthorized")
+                #function_call
             }
         }
         #inner
+        #guard
     );
     TokenStream::from(expanded)
 }
 
+/// Marks an endpoint that needs an account, so anonymous ingress calls to it are rejected by the
+/// generated `inspect_message`. The endpoint itself is left as is and keeps returning its own error.
 #[proc_macro_attribute]
-pub fn lambda(_: TokenStream, item: TokenStream) -> TokenStream {
+pub fn authenticated(_: TokenStream, item: TokenStream) -> TokenStream {
     let mut inner = parse_macro_input!(item as ItemFn);
     let wrapper_sig = inner.sig.clone();
-    let inner_method_name = format_ident!("{}_admin", inner.sig.ident);
+    let inner_method_name = format_ident!("{}{}", inner.sig.ident, GUARD_SUFFIX);
     inner.sig.ident = inner_method_name.clone();
+    let guard = guard_fn(&wrapper_sig.ident, &inner, authenticated_check());
 
     let is_async = inner.sig.asyncness.is_some();
     let arg_names = get_arg_names(&inner.sig);
@@ -82,56 +215,54 @@ pub fn lambda(_: TokenStream, item: TokenStream) -> TokenStream {
     let expanded = quote!(
         #[allow(unused_mut)]
         #wrapper_sig {
-            let caller = get_caller();
-                    if AdminRepo::get().eq(&caller)
-                    || ConfigurationRepo::get().lambda.eq(&caller) {
-                #function_call
-            } else {
-                trap("Unauthorized")
-            }
+            #function_call
         }
         #inner
+        #guard
     );
     TokenStream::from(expanded)
 }
 
-#[proc_macro_attribute]
-pub fn operator(_: TokenStream, item: TokenStream) -> TokenStream {
-    let mut inner = parse_macro_input!(item as ItemFn);
-    let wrapper_sig = inner.sig.clone();
-    let inner_method_name = format_ident!("{}_admin", inner.sig.ident);
-    inner.sig.ident = inner_method_name.clone();
-
-    let is_async = inner.sig.asyncness.is_some();
-    let arg_names = get_arg_names(&inner.sig);
-
-    let function_call = if is_async {
-        quote! { #inner_method_name ( #(#arg_names),* ) .await }
-    } else {
-        quote! { #inner_method_name ( #(#arg_names),* ) }
-    };
+/// Generates the `inspect_message` handler calling the guard functions of the listed endpoints, e.g.
+/// `generate_inspect_message!(configure, create_account);`. Every listed endpoint needs at least one
+/// guard attribute above. Ingress calls failing a guard are not accepted, so they are rejected before execution.
+/// The checks inside the methods stay authoritative, the handler only saves the cycles of executing calls
+/// that would trap anyway.
+#[proc_macro]
+pub fn generate_inspect_message(input: TokenStream) -> TokenStream {
+    let endpoints = parse_macro_input!(input with Punctuated::<Ident, Token![,]>::parse_terminated);
+    let arms = endpoints.iter().map(|endpoint| {
+        let name = endpoint.to_string();
+        let guard = guard_ident(endpoint);
+        quote! { #name => #guard(&caller) }
+    });
 
     let expanded = quote!(
-        #[allow(unused_mut)]
-        #wrapper_sig {
-            let caller = get_caller();
-                    if ConfigurationRepo::get().operator.eq(&caller) {
-                #function_call
-            } else {
-                trap("Unauthorized")
+        #[ic_cdk_macros::inspect_message]
+        fn inspect_message_guards() {
+            let caller = ic_cdk::api::caller();
+            let accepted = match ic_cdk::api::call::method_name().as_str() {
+                #(#arms,)*
+                _ => true,
+            };
+            if accepted {
+                ic_cdk::api::call::accept_message();
             }
         }
-        #inner
     );
     TokenStream::from(expanded)
 }
 
-#[proc_macro_attribute]
-pub fn paused(_: TokenStream, item: TokenStream) -> TokenStream {
+fn role_guard(roles: &[Ident], item: TokenStream) -> TokenStream {
     let mut inner = parse_macro_input!(item as ItemFn);
     let wrapper_sig = inner.sig.clone();
-    let inner_method_name = format_ident!("{}_admin", inner.sig.ident);
+    let inner_method_name = format_ident!("{}{}", inner.sig.ident, GUARD_SUFFIX);
     inner.sig.ident = inner_method_name.clone();
+    let guard = guard_fn(
+        &wrapper_sig.ident,
+        &inner,
+        quote! { RoleRepo::has_any_role(caller, &[#(Role::#roles),*]) },
+    );
 
     let is_async = inner.sig.asyncness.is_some();
     let arg_names = get_arg_names(&inner.sig);
@@ -145,13 +276,15 @@ pub fn paused(_: TokenStream, item: TokenStream) -> TokenStream {
     let expanded = quote!(
         #[allow(unused_mut)]
         #wrapper_sig {
-            if ConfigurationRepo::get().account_creation_paused {
-                trap("Account creation is paused due to high demand. Please try again later.")
-            } else {
+            let caller = get_caller();
+            if RoleRepo::has_any_role(&caller, &[#(Role::#roles),*]) {
                 #function_call
+            } else {
+                trap("Unauthorized")
             }
         }
         #inner
+        #guard
     );
     TokenStream::from(expanded)
 }
@@ -0,0 +1,40 @@
+use std::io;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+
+use crate::{serve, CanisterStats, MetricsEncoder};
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
+/// Handles the `http_request` query, see `serve`.
+pub fn http_request<F>(
+    request: &HttpRequest,
+    now: u64,
+    stats: CanisterStats,
+    encode: F,
+) -> HttpResponse
+where
+    F: FnOnce(&mut MetricsEncoder<Vec<u8>>) -> io::Result<()>,
+{
+    let response = serve(&request.url, now, stats, encode);
+    HttpResponse {
+        status_code: response.status_code,
+        headers: response.headers,
+        body: ByteBuf::from(response.body),
+    }
+}
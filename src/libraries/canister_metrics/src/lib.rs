@@ -0,0 +1,156 @@
+//! Serves canister metrics in the Prometheus text format at `/metrics` of the `http_request` query.
+//!
+//! Every canister exports the cycles balance and the heap and stable memory sizes,
+//! next to its own gauges written by the `encode` function passed to `serve`.
+
+use std::io;
+
+pub use ic_metrics_encoder::MetricsEncoder;
+
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+pub use crate::http::{http_request, HttpRequest, HttpResponse};
+
+pub const METRICS_PATH: &str = "/metrics";
+
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+/// The gauges every canister exports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CanisterStats {
+    pub cycles_balance: u128,
+    pub heap_memory_bytes: u64,
+    pub stable_memory_bytes: u64,
+}
+
+impl CanisterStats {
+    /// Reads the heap size from the Wasm memory, which is only known inside the canister.
+    pub fn new(cycles_balance: u128, stable_memory_pages: u64) -> CanisterStats {
+        CanisterStats {
+            cycles_balance,
+            heap_memory_bytes: heap_memory_pages() * WASM_PAGE_SIZE,
+            stable_memory_bytes: stable_memory_pages * WASM_PAGE_SIZE,
+        }
+    }
+}
+
+/// An `http_request` response independent of the candid version of the canister.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetricsResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Serves the metrics at `/metrics` and 404 at any other path.
+/// `now` is the current time in nanoseconds.
+pub fn serve<F>(url: &str, now: u64, stats: CanisterStats, encode: F) -> MetricsResponse
+where
+    F: FnOnce(&mut MetricsEncoder<Vec<u8>>) -> io::Result<()>,
+{
+    let path = url.split('?').next().unwrap_or_default();
+    if path != METRICS_PATH {
+        return text_response(404, "Not found".to_string());
+    }
+    match encode_metrics(now, stats, encode) {
+        Ok(body) => MetricsResponse {
+            status_code: 200,
+            headers: vec![
+                (
+                    "Content-Type".to_string(),
+                    "text/plain; version=0.0.4".to_string(),
+                ),
+                ("Content-Length".to_string(), body.len().to_string()),
+                ("Cache-Control".to_string(), "no-store".to_string()),
+            ],
+            body,
+        },
+        Err(err) => text_response(500, format!("Failed to encode metrics: {}", err)),
+    }
+}
+
+fn encode_metrics<F>(now: u64, stats: CanisterStats, encode: F) -> io::Result<Vec<u8>>
+where
+    F: FnOnce(&mut MetricsEncoder<Vec<u8>>) -> io::Result<()>,
+{
+    let mut w = MetricsEncoder::new(vec![], (now / 1_000_000) as i64);
+    w.encode_gauge(
+        "canister_cycles_balance",
+        stats.cycles_balance as f64,
+        "Cycles balance of the canister.",
+    )?;
+    w.encode_gauge(
+        "canister_heap_memory_bytes",
+        stats.heap_memory_bytes as f64,
+        "Size of the heap memory in bytes.",
+    )?;
+    w.encode_gauge(
+        "canister_stable_memory_bytes",
+        stats.stable_memory_bytes as f64,
+        "Size of the stable memory in bytes.",
+    )?;
+    encode(&mut w)?;
+    Ok(w.into_inner())
+}
+
+fn text_response(status_code: u16, body: String) -> MetricsResponse {
+    MetricsResponse {
+        status_code,
+        headers: vec![("Content-Type".to_string(), "text/plain".to_string())],
+        body: body.into_bytes(),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn heap_memory_pages() -> u64 {
+    core::arch::wasm32::memory_size(0) as u64
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn heap_memory_pages() -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats() -> CanisterStats {
+        CanisterStats {
+            cycles_balance: 1_000,
+            heap_memory_bytes: 2 * WASM_PAGE_SIZE,
+            stable_memory_bytes: 3 * WASM_PAGE_SIZE,
+        }
+    }
+
+    #[test]
+    fn serves_common_and_canister_metrics() {
+        let response = serve("/metrics?time=1", 5_000_000, stats(), |w| {
+            w.gauge_vec("accounts", "Number of accounts.")?
+                .value(&[("wallet", "NFID")], 7.0)?;
+            Ok(())
+        });
+        assert_eq!(200, response.status_code);
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("canister_cycles_balance 1000 5\n"));
+        assert!(body.contains("canister_heap_memory_bytes 131072 5\n"));
+        assert!(body.contains("canister_stable_memory_bytes 196608 5\n"));
+        assert!(body.contains("accounts{wallet=\"NFID\"} 7 5\n"));
+    }
+
+    #[test]
+    fn other_paths_are_not_found() {
+        let response = serve("/", 0, stats(), |_| Ok(()));
+        assert_eq!(404, response.status_code);
+    }
+
+    #[test]
+    fn encoding_errors_are_server_errors() {
+        let response = serve(METRICS_PATH, 0, stats(), |_| {
+            Err(io::Error::other("broken"))
+        });
+        assert_eq!(500, response.status_code);
+        assert_eq!(b"Failed to encode metrics: broken".to_vec(), response.body);
+    }
+}
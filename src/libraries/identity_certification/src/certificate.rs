@@ -0,0 +1,198 @@
+use candid::Principal;
+use serde_cbor::Value;
+
+use crate::hash_tree::{decode_cbor, with_domain, HashTree, Lookup};
+use crate::VerificationError;
+
+/// The DER prefix of a BLS12-381 public key as used for the root and subnet keys.
+const BLS_KEY_DER_PREFIX: &str =
+    "308182301d060d2b0601040182dc7c0503010201060c2b0601040182dc7c05030201036100";
+const BLS_KEY_LENGTH: usize = 96;
+
+/// A certificate returned by `ic0.data_certificate` and handed out by the certified queries.
+#[derive(Clone, Debug)]
+pub struct Certificate {
+    pub tree: HashTree,
+    pub signature: Vec<u8>,
+    pub delegation: Option<Delegation>,
+}
+
+/// Present if the certificate is signed by the subnet key instead of the root key.
+#[derive(Clone, Debug)]
+pub struct Delegation {
+    pub subnet_id: Vec<u8>,
+    pub certificate: Vec<u8>,
+}
+
+impl Certificate {
+    pub fn from_cbor(bytes: &[u8]) -> Result<Certificate, VerificationError> {
+        let value = decode_cbor(bytes)?;
+        let malformed = || VerificationError::MalformedCertificate;
+        let fields = match &value {
+            Value::Map(fields) => fields,
+            _ => return Err(malformed()),
+        };
+        let field = |name: &str| fields.get(&Value::Text(name.to_string()));
+        let tree = HashTree::from_value(field("tree").ok_or_else(malformed)?)?;
+        let signature = match field("signature") {
+            Some(Value::Bytes(signature)) => signature.clone(),
+            _ => return Err(malformed()),
+        };
+        let delegation = match field("delegation") {
+            None => None,
+            Some(Value::Map(delegation)) => {
+                let bytes = |name: &str| match delegation.get(&Value::Text(name.to_string())) {
+                    Some(Value::Bytes(bytes)) => Ok(bytes.clone()),
+                    _ => Err(malformed()),
+                };
+                Some(Delegation {
+                    subnet_id: bytes("subnet_id")?,
+                    certificate: bytes("certificate")?,
+                })
+            }
+            Some(_) => return Err(malformed()),
+        };
+        Ok(Certificate {
+            tree,
+            signature,
+            delegation,
+        })
+    }
+
+    /// Checks the signature with the root key, or with the key of the delegated subnet
+    /// if the subnet is allowed to sign for the canister.
+    pub fn verify(
+        &self,
+        canister_id: &Principal,
+        root_key: &[u8],
+    ) -> Result<(), VerificationError> {
+        let key = match &self.delegation {
+            None => root_key.to_vec(),
+            Some(delegation) => delegation.subnet_key(canister_id, root_key)?,
+        };
+        let mut message = with_domain(b"ic-state-root");
+        message.extend_from_slice(&self.tree.reconstruct());
+        ic_verify_bls_signature::verify_bls_signature(&self.signature, &message, &bls_key(&key)?)
+            .map_err(|_| VerificationError::InvalidSignature)
+    }
+
+    /// The time the certificate was created at, in nanoseconds since the epoch.
+    pub fn time(&self) -> Result<u64, VerificationError> {
+        match self.tree.lookup_path(&[b"time".as_slice()]) {
+            Lookup::Found(time) => decode_leb128(time),
+            _ => Err(VerificationError::MalformedCertificate),
+        }
+    }
+
+    pub fn certified_data(&self, canister_id: &Principal) -> Result<&[u8], VerificationError> {
+        match self.tree.lookup_path(&[
+            b"canister".as_slice(),
+            canister_id.as_slice(),
+            b"certified_data".as_slice(),
+        ]) {
+            Lookup::Found(data) => Ok(data),
+            _ => Err(VerificationError::MissingCertifiedData),
+        }
+    }
+}
+
+impl Delegation {
+    /// The key of the subnet, from a certificate that has to be signed by the root key itself.
+    fn subnet_key(
+        &self,
+        canister_id: &Principal,
+        root_key: &[u8],
+    ) -> Result<Vec<u8>, VerificationError> {
+        let certificate = Certificate::from_cbor(&self.certificate)?;
+        if certificate.delegation.is_some() {
+            return Err(VerificationError::InvalidDelegation);
+        }
+        certificate.verify(canister_id, root_key)?;
+        let subnet = |label: &'static [u8]| match certificate.tree.lookup_path(&[
+            b"subnet".as_slice(),
+            self.subnet_id.as_slice(),
+            label,
+        ]) {
+            Lookup::Found(value) => Ok(value.to_vec()),
+            _ => Err(VerificationError::InvalidDelegation),
+        };
+        let ranges = subnet(b"canister_ranges")?;
+        if !is_in_ranges(canister_id, &ranges)? {
+            return Err(VerificationError::InvalidDelegation);
+        }
+        subnet(b"public_key")
+    }
+}
+
+/// Canister ranges are a CBOR array of inclusive `[start, end]` principal pairs.
+fn is_in_ranges(canister_id: &Principal, ranges: &[u8]) -> Result<bool, VerificationError> {
+    let ranges = match decode_cbor(ranges)? {
+        Value::Array(ranges) => ranges,
+        _ => return Err(VerificationError::InvalidDelegation),
+    };
+    let id = canister_id.as_slice();
+    for range in ranges.iter() {
+        match range {
+            Value::Array(bounds) => match bounds.as_slice() {
+                [Value::Bytes(start), Value::Bytes(end)] => {
+                    if start.as_slice() <= id && id <= end.as_slice() {
+                        return Ok(true);
+                    }
+                }
+                _ => return Err(VerificationError::InvalidDelegation),
+            },
+            _ => return Err(VerificationError::InvalidDelegation),
+        }
+    }
+    Ok(false)
+}
+
+fn bls_key(der: &[u8]) -> Result<Vec<u8>, VerificationError> {
+    let prefix = hex::decode(BLS_KEY_DER_PREFIX).expect("Failed to decode the DER prefix.");
+    match der.strip_prefix(prefix.as_slice()) {
+        Some(key) if key.len() == BLS_KEY_LENGTH => Ok(key.to_vec()),
+        _ => Err(VerificationError::InvalidRootKey),
+    }
+}
+
+fn decode_leb128(bytes: &[u8]) -> Result<u64, VerificationError> {
+    let mut value: u64 = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if i >= 10 {
+            break;
+        }
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(VerificationError::MalformedCertificate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_leb128_time() {
+        assert_eq!(Ok(624485), decode_leb128(&[0xe5, 0x8e, 0x26]));
+        assert_eq!(
+            Err(VerificationError::MalformedCertificate),
+            decode_leb128(&[0x80])
+        );
+    }
+
+    #[test]
+    fn checks_canister_ranges() {
+        let canister = Principal::from_slice(&[0, 0, 0, 0, 0, 0, 0, 5, 1, 1]);
+        let encode = |start: u8, end: u8| {
+            serde_cbor::to_vec(&Value::Array(vec![Value::Array(vec![
+                Value::Bytes(vec![0, 0, 0, 0, 0, 0, 0, start, 1, 1]),
+                Value::Bytes(vec![0, 0, 0, 0, 0, 0, 0, end, 1, 1]),
+            ])]))
+            .unwrap()
+        };
+        assert_eq!(Ok(true), is_in_ranges(&canister, &encode(1, 5)));
+        assert_eq!(Ok(false), is_in_ranges(&canister, &encode(6, 9)));
+    }
+}
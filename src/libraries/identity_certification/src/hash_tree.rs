@@ -0,0 +1,213 @@
+use serde_cbor::Value;
+use sha2::{Digest, Sha256};
+
+use crate::VerificationError;
+
+pub type Hash = [u8; 32];
+
+/// A hash tree as encoded in certificates and witnesses, see the Internet Computer interface specification.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashTree {
+    Empty,
+    Fork(Box<HashTree>, Box<HashTree>),
+    Labeled(Vec<u8>, Box<HashTree>),
+    Leaf(Vec<u8>),
+    Pruned(Hash),
+}
+
+/// The outcome of looking up a path, `Unknown` if the witness pruned the part that would decide it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Lookup<'a> {
+    Found(&'a [u8]),
+    Absent,
+    Unknown,
+}
+
+impl HashTree {
+    /// Decodes a CBOR encoded tree, with or without the self-describe tag.
+    pub fn from_cbor(bytes: &[u8]) -> Result<HashTree, VerificationError> {
+        let value = decode_cbor(bytes)?;
+        HashTree::from_value(&value)
+    }
+
+    pub(crate) fn from_value(value: &Value) -> Result<HashTree, VerificationError> {
+        let malformed = || VerificationError::MalformedTree;
+        let items = match value {
+            Value::Array(items) => items,
+            _ => return Err(malformed()),
+        };
+        match items.as_slice() {
+            [Value::Integer(0)] => Ok(HashTree::Empty),
+            [Value::Integer(1), left, right] => Ok(HashTree::Fork(
+                Box::new(HashTree::from_value(left)?),
+                Box::new(HashTree::from_value(right)?),
+            )),
+            [Value::Integer(2), Value::Bytes(label), subtree] => Ok(HashTree::Labeled(
+                label.clone(),
+                Box::new(HashTree::from_value(subtree)?),
+            )),
+            [Value::Integer(3), Value::Bytes(leaf)] => Ok(HashTree::Leaf(leaf.clone())),
+            [Value::Integer(4), Value::Bytes(hash)] => hash
+                .as_slice()
+                .try_into()
+                .map(HashTree::Pruned)
+                .map_err(|_| malformed()),
+            _ => Err(malformed()),
+        }
+    }
+
+    /// The root hash the tree stands for, compared against the certified data.
+    pub fn reconstruct(&self) -> Hash {
+        match self {
+            HashTree::Empty => hash_with_domain(b"ic-hashtree-empty", &[]),
+            HashTree::Fork(left, right) => hash_with_domain(
+                b"ic-hashtree-fork",
+                &[&left.reconstruct(), &right.reconstruct()],
+            ),
+            HashTree::Labeled(label, subtree) => hash_with_domain(
+                b"ic-hashtree-labeled",
+                &[label.as_slice(), &subtree.reconstruct()],
+            ),
+            HashTree::Leaf(leaf) => hash_with_domain(b"ic-hashtree-leaf", &[leaf.as_slice()]),
+            HashTree::Pruned(hash) => *hash,
+        }
+    }
+
+    /// Looks up a path of labels following the lookup algorithm of the interface specification.
+    pub fn lookup_path<'a>(&'a self, path: &[&[u8]]) -> Lookup<'a> {
+        match path.split_first() {
+            None => match self {
+                HashTree::Leaf(leaf) => Lookup::Found(leaf),
+                HashTree::Pruned(_) => Lookup::Unknown,
+                _ => Lookup::Absent,
+            },
+            Some((label, rest)) => match self.find_label(label) {
+                LabelLookup::Found(subtree) => subtree.lookup_path(rest),
+                LabelLookup::Absent => Lookup::Absent,
+                LabelLookup::Unknown => Lookup::Unknown,
+            },
+        }
+    }
+
+    fn find_label(&self, label: &[u8]) -> LabelLookup<'_> {
+        let mut nodes = vec![];
+        self.flatten_forks(&mut nodes);
+        if let Some(subtree) = nodes.iter().find_map(|node| match node {
+            HashTree::Labeled(node_label, subtree) if node_label.as_slice() == label => {
+                Some(subtree.as_ref())
+            }
+            _ => None,
+        }) {
+            return LabelLookup::Found(subtree);
+        }
+        // The label is absent only if its neighbours are both revealed, otherwise it may be pruned.
+        let mut previous_revealed = true;
+        for node in nodes.iter() {
+            match node {
+                HashTree::Labeled(node_label, _) if node_label.as_slice() > label => {
+                    return if previous_revealed {
+                        LabelLookup::Absent
+                    } else {
+                        LabelLookup::Unknown
+                    };
+                }
+                HashTree::Labeled(..) => previous_revealed = true,
+                _ => previous_revealed = false,
+            }
+        }
+        if previous_revealed {
+            LabelLookup::Absent
+        } else {
+            LabelLookup::Unknown
+        }
+    }
+
+    fn flatten_forks<'a>(&'a self, nodes: &mut Vec<&'a HashTree>) {
+        match self {
+            HashTree::Empty => {}
+            HashTree::Fork(left, right) => {
+                left.flatten_forks(nodes);
+                right.flatten_forks(nodes);
+            }
+            node => nodes.push(node),
+        }
+    }
+}
+
+enum LabelLookup<'a> {
+    Found(&'a HashTree),
+    Absent,
+    Unknown,
+}
+
+pub(crate) fn decode_cbor(bytes: &[u8]) -> Result<Value, VerificationError> {
+    const SELF_DESCRIBE_TAG: [u8; 3] = [0xd9, 0xd9, 0xf7];
+    let bytes = bytes.strip_prefix(&SELF_DESCRIBE_TAG).unwrap_or(bytes);
+    serde_cbor::from_slice(bytes).map_err(|_| VerificationError::MalformedCbor)
+}
+
+/// The domain separator, the length byte of the domain followed by the domain.
+pub(crate) fn with_domain(domain: &[u8]) -> Vec<u8> {
+    let mut separator = vec![domain.len() as u8];
+    separator.extend_from_slice(domain);
+    separator
+}
+
+/// Hashes the domain separator followed by the parts.
+pub(crate) fn hash_with_domain(domain: &[u8], parts: &[&[u8]]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update(with_domain(domain));
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_certified_map::{fork, labeled, AsHashTree, RbTree};
+    use serde::Serialize;
+
+    fn encode(tree: ic_certified_map::HashTree) -> Vec<u8> {
+        let mut bytes = vec![];
+        let mut serializer = serde_cbor::Serializer::new(&mut bytes);
+        serializer.self_describe().unwrap();
+        tree.serialize(&mut serializer).unwrap();
+        bytes
+    }
+
+    fn tree() -> RbTree<String, Vec<u8>> {
+        let mut tree = RbTree::new();
+        for key in ["a", "c", "e"] {
+            tree.insert(key.to_string(), key.as_bytes().to_vec());
+        }
+        tree
+    }
+
+    #[test]
+    fn reconstructs_the_hash_of_a_certified_map_witness() {
+        let tree = tree();
+        let witness = fork(
+            tree.witness(b"c"),
+            labeled(b"principals", tree.as_hash_tree()),
+        );
+        let expected = witness.reconstruct();
+        let decoded = HashTree::from_cbor(&encode(witness)).unwrap();
+        assert_eq!(expected, decoded.reconstruct());
+    }
+
+    #[test]
+    fn looks_up_revealed_and_absent_labels() {
+        let tree = tree();
+        let witness = HashTree::from_cbor(&encode(tree.witness(b"c"))).unwrap();
+        assert_eq!(
+            Lookup::Found(b"c".as_slice()),
+            witness.lookup_path(&[b"c".as_slice()])
+        );
+
+        let absence = HashTree::from_cbor(&encode(tree.witness(b"b"))).unwrap();
+        assert_eq!(Lookup::Absent, absence.lookup_path(&[b"b".as_slice()]));
+        assert_eq!(Lookup::Unknown, absence.lookup_path(&[b"e".as_slice()]));
+    }
+}
@@ -0,0 +1,223 @@
+//! Verifies the access point principal to root and anchor mapping certified by the identity_manager canister,
+//! so canisters and clients can trust a mapping handed to them without calling the identity_manager.
+//!
+//! The certified data of the identity_manager is the hash of
+//! `fork(fork(before, labeled("principals", principals)), after)`, where `before` and `after` map the principals
+//! sorting before and after the `principals` label to the SHA-256 of their root,
+//! and `principals` maps a principal to `fork(labeled("anchor", anchor), labeled("root", root))`.
+//! Certificates and witnesses are returned by the `get_roots_certified` query.
+
+use std::fmt;
+
+use candid::Principal;
+
+pub mod certificate;
+pub mod hash_tree;
+
+use crate::certificate::Certificate;
+use crate::hash_tree::{HashTree, Lookup};
+
+pub const LABEL_PRINCIPALS: &[u8] = b"principals";
+pub const LABEL_ANCHOR: &[u8] = b"anchor";
+pub const LABEL_ROOT: &[u8] = b"root";
+
+/// The DER encoded root key of the Internet Computer mainnet, local replicas have their own key.
+const MAINNET_ROOT_KEY: &str = "308182301d060d2b0601040182dc7c0503010201060c2b0601040182dc7c05030201036100814c0e6ec71fab583b08bd81373c255c3c371b2e84863c98a4f1e08b74235d14fb5d9c0cd546d9685f913a0c0b2cc5341583bf4b4392e467db96d65b9bb4cb717112f8472e0d5a4d14505ffd7484b01291091c5f87b98883463f98091a0baaae";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CertifiedPrincipal {
+    pub root: String,
+    pub anchor: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationError {
+    MalformedCbor,
+    MalformedTree,
+    MalformedCertificate,
+    InvalidRootKey,
+    InvalidSignature,
+    InvalidDelegation,
+    MissingCertifiedData,
+    CertifiedDataMismatch,
+    CertificateTooOld,
+    /// The witness does not reveal the principal, nor proves its absence.
+    PrincipalNotInWitness,
+    MalformedPrincipalEntry,
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            VerificationError::MalformedCbor => "The CBOR encoding is invalid.",
+            VerificationError::MalformedTree => "The hash tree is invalid.",
+            VerificationError::MalformedCertificate => "The certificate is invalid.",
+            VerificationError::InvalidRootKey => "The root key is not a DER encoded BLS key.",
+            VerificationError::InvalidSignature => "The certificate signature is invalid.",
+            VerificationError::InvalidDelegation => {
+                "The certificate delegation is invalid or not valid for the canister."
+            }
+            VerificationError::MissingCertifiedData => {
+                "The certificate has no certified data for the canister."
+            }
+            VerificationError::CertifiedDataMismatch => {
+                "The witness does not match the certified data."
+            }
+            VerificationError::CertificateTooOld => "The certificate is too old.",
+            VerificationError::PrincipalNotInWitness => "The witness does not cover the principal.",
+            VerificationError::MalformedPrincipalEntry => {
+                "The certified entry of the principal is invalid."
+            }
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for VerificationError {}
+
+pub fn mainnet_root_key() -> Vec<u8> {
+    hex::decode(MAINNET_ROOT_KEY).expect("Failed to decode the mainnet root key.")
+}
+
+/// Verifies certificates of one identity_manager canister.
+#[derive(Clone, Debug)]
+pub struct Verifier {
+    pub canister_id: Principal,
+    /// DER encoded, `mainnet_root_key()` on mainnet.
+    pub root_key: Vec<u8>,
+    /// Certificates older than this many nanoseconds are rejected.
+    pub max_certificate_age: u64,
+}
+
+/// A witness checked against a valid certificate, principals are looked up in it.
+#[derive(Clone, Debug)]
+pub struct VerifiedWitness {
+    tree: HashTree,
+}
+
+impl Verifier {
+    /// Checks the certificate signature, its age relative to `now` in nanoseconds since the epoch,
+    /// and that the witness hashes to the certified data of the canister.
+    pub fn verify(
+        &self,
+        certificate: &[u8],
+        witness: &[u8],
+        now: u64,
+    ) -> Result<VerifiedWitness, VerificationError> {
+        let certificate = Certificate::from_cbor(certificate)?;
+        certificate.verify(&self.canister_id, &self.root_key)?;
+        if now.saturating_sub(certificate.time()?) > self.max_certificate_age {
+            return Err(VerificationError::CertificateTooOld);
+        }
+        let tree = HashTree::from_cbor(witness)?;
+        if certificate.certified_data(&self.canister_id)? != tree.reconstruct().as_slice() {
+            return Err(VerificationError::CertifiedDataMismatch);
+        }
+        Ok(VerifiedWitness { tree })
+    }
+
+    /// Returns the certified root and anchor of the principal, `None` if it is certified as not registered.
+    pub fn verify_principal(
+        &self,
+        certificate: &[u8],
+        witness: &[u8],
+        principal: &str,
+        now: u64,
+    ) -> Result<Option<CertifiedPrincipal>, VerificationError> {
+        self.verify(certificate, witness, now)?.lookup(principal)
+    }
+}
+
+impl VerifiedWitness {
+    /// Returns the certified root and anchor of the principal, `None` if it is certified as not registered.
+    pub fn lookup(&self, principal: &str) -> Result<Option<CertifiedPrincipal>, VerificationError> {
+        let anchor = self
+            .tree
+            .lookup_path(&[LABEL_PRINCIPALS, principal.as_bytes(), LABEL_ANCHOR]);
+        let root = self
+            .tree
+            .lookup_path(&[LABEL_PRINCIPALS, principal.as_bytes(), LABEL_ROOT]);
+        match (anchor, root) {
+            (Lookup::Found(anchor), Lookup::Found(root)) => {
+                let anchor = anchor
+                    .try_into()
+                    .map(u64::from_be_bytes)
+                    .map_err(|_| VerificationError::MalformedPrincipalEntry)?;
+                let root = String::from_utf8(root.to_vec())
+                    .map_err(|_| VerificationError::MalformedPrincipalEntry)?;
+                Ok(Some(CertifiedPrincipal { root, anchor }))
+            }
+            (Lookup::Absent, Lookup::Absent) => Ok(None),
+            _ => Err(VerificationError::PrincipalNotInWitness),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ic_certified_map::{fork, labeled, AsHashTree, RbTree};
+    use serde::Serialize;
+
+    fn entry(anchor: u64, root: &str) -> ic_certified_map::HashTree<'static> {
+        fork(
+            labeled(
+                LABEL_ANCHOR,
+                ic_certified_map::HashTree::Leaf(anchor.to_be_bytes().to_vec().into()),
+            ),
+            labeled(
+                LABEL_ROOT,
+                ic_certified_map::HashTree::Leaf(root.as_bytes().to_vec().into()),
+            ),
+        )
+    }
+
+    fn witness(principals: ic_certified_map::HashTree) -> VerifiedWitness {
+        let roots: RbTree<String, Vec<u8>> = RbTree::new();
+        let mut bytes = vec![];
+        let mut serializer = serde_cbor::Serializer::new(&mut bytes);
+        serializer.self_describe().unwrap();
+        fork(
+            fork(roots.as_hash_tree(), labeled(LABEL_PRINCIPALS, principals)),
+            roots.as_hash_tree(),
+        )
+        .serialize(&mut serializer)
+        .unwrap();
+        VerifiedWitness {
+            tree: HashTree::from_cbor(&bytes).unwrap(),
+        }
+    }
+
+    #[test]
+    fn looks_up_a_certified_principal() {
+        let witness = witness(fork(
+            labeled(b"aaaaa-aa", entry(100_000_000, "root-a")),
+            labeled(b"ccccc-cc", entry(200_000_001, "root-c")),
+        ));
+        assert_eq!(
+            Ok(Some(CertifiedPrincipal {
+                root: "root-c".to_string(),
+                anchor: 200_000_001,
+            })),
+            witness.lookup("ccccc-cc")
+        );
+        assert_eq!(Ok(None), witness.lookup("bbbbb-bb"));
+    }
+
+    #[test]
+    fn rejects_a_principal_the_witness_does_not_cover() {
+        let witness = witness(fork(
+            labeled(b"aaaaa-aa", entry(100_000_000, "root-a")),
+            ic_certified_map::HashTree::Pruned([0; 32]),
+        ));
+        assert_eq!(
+            Err(VerificationError::PrincipalNotInWitness),
+            witness.lookup("ccccc-cc")
+        );
+    }
+
+    #[test]
+    fn mainnet_root_key_is_a_bls_key() {
+        assert_eq!(133, mainnet_root_key().len());
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::{HashMap, VecDeque};
+
+/// Principal to root results kept for `ttl` nanoseconds, at most `capacity` of them.
+/// When full, the oldest entry is evicted first.
+#[derive(Clone, Debug)]
+pub struct TtlCache {
+    ttl: u64,
+    capacity: usize,
+    entries: HashMap<String, Entry>,
+    /// Keys in insertion order with the expiry they were inserted with, an entry inserted again leaves a stale key behind.
+    order: VecDeque<(String, u64)>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    root: String,
+    expires_at: u64,
+}
+
+impl TtlCache {
+    pub fn new(ttl: u64, capacity: usize) -> TtlCache {
+        TtlCache {
+            ttl,
+            capacity,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn get(&mut self, principal: &str, now: u64) -> Option<String> {
+        match self.entries.get(principal) {
+            Some(entry) if entry.expires_at > now => Some(entry.root.clone()),
+            Some(_) => {
+                self.entries.remove(principal);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&mut self, principal: String, root: String, now: u64) {
+        if self.ttl == 0 || self.capacity == 0 {
+            return;
+        }
+        while let Some((front, expires_at)) = self.order.front() {
+            let is_current = self
+                .entries
+                .get(front)
+                .is_some_and(|entry| entry.expires_at == *expires_at);
+            if is_current && *expires_at > now && self.entries.len() < self.capacity {
+                break;
+            }
+            self.evict_front();
+        }
+        let expires_at = now.saturating_add(self.ttl);
+        self.order.push_back((principal.clone(), expires_at));
+        self.entries.insert(principal, Entry { root, expires_at });
+    }
+
+    pub fn remove(&mut self, principal: &str) {
+        self.entries.remove(principal);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn evict_front(&mut self) {
+        if let Some((principal, expires_at)) = self.order.pop_front() {
+            if self
+                .entries
+                .get(&principal)
+                .is_some_and(|entry| entry.expires_at == expires_at)
+            {
+                self.entries.remove(&principal);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let mut cache = TtlCache::new(10, 5);
+        cache.insert("a".to_string(), "root".to_string(), 100);
+        assert_eq!(Some("root".to_string()), cache.get("a", 109));
+        assert_eq!(None, cache.get("a", 110));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn evicts_the_oldest_entry_when_full() {
+        let mut cache = TtlCache::new(100, 2);
+        cache.insert("a".to_string(), "root-a".to_string(), 1);
+        cache.insert("b".to_string(), "root-b".to_string(), 2);
+        cache.insert("c".to_string(), "root-c".to_string(), 3);
+        assert_eq!(2, cache.len());
+        assert_eq!(None, cache.get("a", 4));
+        assert_eq!(Some("root-b".to_string()), cache.get("b", 4));
+        assert_eq!(Some("root-c".to_string()), cache.get("c", 4));
+    }
+
+    #[test]
+    fn reinserted_entry_survives_eviction_of_its_stale_key() {
+        let mut cache = TtlCache::new(100, 2);
+        cache.insert("a".to_string(), "root-a".to_string(), 1);
+        cache.insert("a".to_string(), "root-a".to_string(), 2);
+        cache.insert("b".to_string(), "root-b".to_string(), 3);
+        assert_eq!(Some("root-a".to_string()), cache.get("a", 4));
+        assert_eq!(Some("root-b".to_string()), cache.get("b", 4));
+    }
+
+    #[test]
+    fn zero_ttl_disables_the_cache() {
+        let mut cache = TtlCache::new(0, 2);
+        cache.insert("a".to_string(), "root-a".to_string(), 1);
+        assert_eq!(None, cache.get("a", 1));
+    }
+}
@@ -0,0 +1,33 @@
+use candid::Principal;
+use ic_cdk::api::call::call;
+use ic_cdk::{caller, trap};
+
+use crate::{resolve_with, RootIdError};
+
+/// Resolves the principal by calling `get_root_by_principal` of the configured identity_manager.
+pub async fn root_id(principal: Principal) -> Result<String, RootIdError> {
+    resolve_with(
+        &principal.to_text(),
+        ic_cdk::api::time(),
+        |im_canister, principal| async move {
+            let im_canister = Principal::from_text(im_canister)
+                .map_err(|err| format!("Invalid IM canister: {}", err))?;
+            call::<(String,), (Option<String>,)>(im_canister, "get_root_by_principal", (principal,))
+                .await
+                .map(|(root,)| root)
+                .map_err(|(_, err)| err)
+        },
+    )
+    .await
+}
+
+pub async fn caller_root_id() -> Result<String, RootIdError> {
+    root_id(caller()).await
+}
+
+/// The root of the caller, traps if it cannot be resolved.
+pub async fn caller_root_id_or_trap() -> String {
+    caller_root_id()
+        .await
+        .unwrap_or_else(|err| trap(&err.to_string()))
+}
@@ -0,0 +1,219 @@
+//! Resolves access point principals to the root principal of their identity_manager account,
+//! the key the storage canisters keep user data under.
+//!
+//! Positive results are cached for a short time, so a removed access point keeps resolving
+//! until its entry expires. Canisters configure the resolver from their init arguments on
+//! `init` and `post_upgrade` with `configure`.
+
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+
+pub mod cache;
+#[cfg(feature = "client")]
+pub mod client;
+
+use crate::cache::TtlCache;
+
+pub const DEFAULT_CACHE_TTL_NS: u64 = 60 * 1_000_000_000;
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+thread_local! {
+    static RESOLVER: RefCell<Resolver> = RefCell::new(Resolver {
+        mode: ResolverMode::Unconfigured,
+        cache: TtlCache::new(DEFAULT_CACHE_TTL_NS, DEFAULT_CACHE_CAPACITY),
+    });
+}
+
+struct Resolver {
+    mode: ResolverMode,
+    cache: TtlCache,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ResolverMode {
+    /// Every resolution fails, the canister was deployed without an identity_manager.
+    Unconfigured,
+    /// Asks the identity_manager canister with this principal text.
+    IdentityManager(String),
+    /// Every principal is its own root, for local deployments without an identity_manager.
+    Test,
+}
+
+impl ResolverMode {
+    /// The identity_manager wins over the test mode if both are set.
+    pub fn from_config(im_canister: Option<String>, test_mode: bool) -> ResolverMode {
+        match im_canister {
+            Some(canister) => ResolverMode::IdentityManager(canister),
+            None if test_mode => ResolverMode::Test,
+            None => ResolverMode::Unconfigured,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RootIdError {
+    NotConfigured,
+    NotRegistered,
+    CallFailed(String),
+}
+
+impl fmt::Display for RootIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RootIdError::NotConfigured => write!(f, "IM canister is not configured"),
+            RootIdError::NotRegistered => write!(f, "No root found for this principal"),
+            RootIdError::CallFailed(err) => write!(f, "Failed to request IM: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RootIdError {}
+
+/// Sets the mode and drops the cached results, which may come from another identity_manager.
+pub fn configure(mode: ResolverMode) {
+    RESOLVER.with(|resolver| {
+        let mut resolver = resolver.borrow_mut();
+        resolver.mode = mode;
+        resolver.cache.clear();
+    })
+}
+
+pub fn mode() -> ResolverMode {
+    RESOLVER.with(|resolver| resolver.borrow().mode.clone())
+}
+
+/// Replaces the cache, a zero `ttl` disables caching.
+pub fn configure_cache(ttl: u64, capacity: usize) {
+    RESOLVER.with(|resolver| resolver.borrow_mut().cache = TtlCache::new(ttl, capacity))
+}
+
+/// Drops the cached root of the principal, e.g. after the access point was removed.
+pub fn invalidate(principal: &str) {
+    RESOLVER.with(|resolver| resolver.borrow_mut().cache.remove(principal))
+}
+
+/// Resolves the principal with `fetch`, called with the identity_manager and the principal
+/// on a cache miss and returning the `get_root_by_principal` result or the call error.
+/// `now` is the current time in nanoseconds.
+pub async fn resolve_with<F, Fut>(
+    principal: &str,
+    now: u64,
+    fetch: F,
+) -> Result<String, RootIdError>
+where
+    F: FnOnce(String, String) -> Fut,
+    Fut: Future<Output = Result<Option<String>, String>>,
+{
+    let im_canister = match mode() {
+        ResolverMode::Unconfigured => return Err(RootIdError::NotConfigured),
+        ResolverMode::Test => return Ok(principal.to_string()),
+        ResolverMode::IdentityManager(im_canister) => im_canister,
+    };
+    if let Some(root) = RESOLVER.with(|resolver| resolver.borrow_mut().cache.get(principal, now)) {
+        return Ok(root);
+    }
+    match fetch(im_canister, principal.to_string()).await {
+        Ok(Some(root)) => {
+            RESOLVER.with(|resolver| {
+                resolver
+                    .borrow_mut()
+                    .cache
+                    .insert(principal.to_string(), root.clone(), now)
+            });
+            Ok(root)
+        }
+        Ok(None) => Err(RootIdError::NotRegistered),
+        Err(err) => Err(RootIdError::CallFailed(err)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::pin::pin;
+    use std::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+    /// Runs a future whose fetch completes right away, as with the stubs below.
+    fn run<T>(future: impl Future<Output = T>) -> T {
+        fn raw_waker() -> RawWaker {
+            fn clone(_: *const ()) -> RawWaker {
+                raw_waker()
+            }
+            fn noop(_: *const ()) {}
+            static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+            RawWaker::new(std::ptr::null(), &VTABLE)
+        }
+        let waker = unsafe { Waker::from_raw(raw_waker()) };
+        match pin!(future).poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(value) => value,
+            Poll::Pending => panic!("The future did not complete."),
+        }
+    }
+
+    fn resolve(
+        principal: &str,
+        now: u64,
+        root: Option<&str>,
+        calls: &Cell<u32>,
+    ) -> Result<String, RootIdError> {
+        let root = root.map(str::to_string);
+        run(resolve_with(principal, now, |_, _| async move {
+            calls.set(calls.get() + 1);
+            Ok(root)
+        }))
+    }
+
+    #[test]
+    fn mode_follows_the_config() {
+        assert_eq!(
+            ResolverMode::IdentityManager("im".to_string()),
+            ResolverMode::from_config(Some("im".to_string()), true)
+        );
+        assert_eq!(ResolverMode::Test, ResolverMode::from_config(None, true));
+        assert_eq!(
+            ResolverMode::Unconfigured,
+            ResolverMode::from_config(None, false)
+        );
+    }
+
+    #[test]
+    fn fails_unless_configured_and_resolves_to_itself_in_test_mode() {
+        let calls = Cell::new(0);
+        configure(ResolverMode::Unconfigured);
+        assert_eq!(
+            Err(RootIdError::NotConfigured),
+            resolve("ap", 0, Some("root"), &calls)
+        );
+        configure(ResolverMode::Test);
+        assert_eq!(Ok("ap".to_string()), resolve("ap", 0, Some("root"), &calls));
+        assert_eq!(0, calls.get());
+    }
+
+    #[test]
+    fn caches_roots_but_not_unregistered_principals() {
+        let calls = Cell::new(0);
+        configure(ResolverMode::IdentityManager("im".to_string()));
+        assert_eq!(
+            Err(RootIdError::NotRegistered),
+            resolve("ap", 0, None, &calls)
+        );
+        assert_eq!(
+            Ok("root".to_string()),
+            resolve("ap", 1, Some("root"), &calls)
+        );
+        assert_eq!(
+            Ok("root".to_string()),
+            resolve("ap", 2, Some("other"), &calls)
+        );
+        assert_eq!(2, calls.get());
+
+        invalidate("ap");
+        assert_eq!(
+            Ok("other".to_string()),
+            resolve("ap", 3, Some("other"), &calls)
+        );
+        assert_eq!(3, calls.get());
+    }
+}
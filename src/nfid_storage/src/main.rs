@@ -3,6 +3,7 @@ use std::collections::HashMap;
 
 use candid::{candid_method, Principal};
 use candid::CandidType;
+use canister_metrics::{CanisterStats, HttpRequest, HttpResponse};
 use ic_cdk::{call, id, storage, trap};
 use ic_cdk::api::call::{CallResult, call_with_payment128};
 use ic_cdk::api::management_canister::main::{
@@ -94,6 +95,26 @@ async fn get_passkey_by_anchor(anchor: u64) -> Vec<PasskeyData> {
     response
 }
 
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+#[candid_method(query)]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(
+        ic_cdk::api::canister_balance128(),
+        ic_cdk::api::stable::stable64_size(),
+    );
+    canister_metrics::http_request(&request, ic_cdk::api::time(), stats, |w| {
+        let passkeys = PASSKEYS.with(|passkeys| passkeys.borrow().len());
+        w.encode_gauge("passkeys", passkeys as f64, "Number of stored passkeys.")?;
+        let anchors = USER_KEYS.with(|user_keys| user_keys.borrow().len());
+        w.encode_gauge(
+            "passkey_anchors",
+            anchors as f64,
+            "Number of anchors with stored passkeys.",
+        )
+    })
+}
+
 /// Persists the public data of a passkey using the specified key.
 #[update]
 #[candid_method(update)]
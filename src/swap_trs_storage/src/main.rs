@@ -4,8 +4,11 @@ use std::hash::Hash;
 
 use candid::{candid_method, Principal};
 use candid::{CandidType, Nat};
-use ic_cdk::{call, caller, storage, trap};
+use canister_metrics::{CanisterStats, HttpRequest, HttpResponse};
+use ic_cdk::{storage, trap};
 use ic_cdk_macros::{init, post_upgrade, pre_upgrade, query, update};
+use root_id_resolver::client::caller_root_id_or_trap;
+use root_id_resolver::ResolverMode;
 use serde::{Deserialize, Serialize};
 
 thread_local! {
@@ -113,7 +116,7 @@ async fn get_transactions(caller: String) -> HashSet<SwapTransaction> {
 #[update]
 #[candid_method(update)]
 async fn store_transaction(data: SwapTransaction) {
-    let id = get_root_id().await;
+    let id = caller_root_id_or_trap().await;
     TRANSACTIONS.with(|trss| {
         let mut transactions = trss.borrow_mut();
         transactions.entry(id).or_insert(HashSet::new()).replace(data.clone());
@@ -142,7 +145,7 @@ fn get_notes(keys: Vec<Vec<u8>>) -> Vec<NoteEntry> {
 #[update]
 #[candid_method(update)]
 async fn store_note(key: Vec<u8>, value: String) {
-    get_root_id().await;
+    caller_root_id_or_trap().await;
     if key.len() != 32 {
         trap("Key must be exactly 32 bytes");
     }
@@ -158,6 +161,30 @@ async fn store_note(key: Vec<u8>, value: String) {
     });
 }
 
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+#[candid_method(query)]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(
+        ic_cdk::api::canister_balance128(),
+        ic_cdk::api::stable::stable64_size(),
+    );
+    canister_metrics::http_request(&request, ic_cdk::api::time(), stats, |w| {
+        let (users, transactions) = TRANSACTIONS.with(|trss| {
+            let trss = trss.borrow();
+            (trss.len(), trss.values().map(HashSet::len).sum::<usize>())
+        });
+        w.encode_gauge("swap_users", users as f64, "Number of users with swap transactions.")?;
+        w.encode_gauge(
+            "swap_transactions",
+            transactions as f64,
+            "Number of swap transactions over all users.",
+        )?;
+        let notes = NOTES.with(|notes| notes.borrow().len());
+        w.encode_gauge("notes", notes as f64, "Number of stored notes.")
+    })
+}
+
 /// Applies changes after the canister upgrade.
 #[post_upgrade]
 async fn post_upgrade(_: Option<InitArgs>) {
@@ -175,6 +202,7 @@ pub fn init_im_canister(im_canister: Principal) {
     STATE.with(|s| {
         s.borrow().im_canister.set(Some(im_canister))
     });
+    configure_resolver(Some(im_canister));
 }
 
 fn main() {}
@@ -233,6 +261,7 @@ pub async fn init_from_memory() {
     STATE.with(|s| {
         s.borrow_mut().im_canister.set(mo.im_canister);
     });
+    configure_resolver(mo.im_canister);
     TRANSACTIONS.with(|trss| {
         let mut map = trss.borrow_mut();
         if let Some(transactions) = mo.transactions {
@@ -308,16 +337,9 @@ fn export_candid() -> String {
 }
 
 
-async fn get_root_id() -> String {
-    match STATE.with(|c| c.borrow_mut().im_canister.get()) {
-        None => caller().to_text(), // Return caller for testing purposes when im_canister is None
-        Some(canister) => {
-            let princ = caller();
-            match call(canister, "get_root_by_principal", (princ.to_text(), 0)).await {
-                Ok((Some(root_id), )) => root_id,
-                Ok((None, )) => trap("No root found for this principal"),
-                Err((_, err)) => trap(&format!("Failed to request IM: {}", err)),
-            }
-        }
-    }
+fn configure_resolver(im_canister: Option<Principal>) {
+    root_id_resolver::configure(ResolverMode::from_config(
+        im_canister.map(|canister| canister.to_text()),
+        false,
+    ));
 }
@@ -4,9 +4,12 @@ use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::hash::Hash;
 
-use candid::{CandidType, Principal};
+use candid::CandidType;
+use canister_metrics::{CanisterStats, HttpRequest, HttpResponse, MetricsEncoder};
 use candid::export_service;
-use ic_cdk::{call, caller, storage, trap};
+use ic_cdk::storage;
+use root_id_resolver::client::caller_root_id_or_trap;
+use root_id_resolver::ResolverMode;
 use ic_cdk_macros::*;
 use serde::{Deserialize, Serialize};
 
@@ -15,6 +18,8 @@ pub use address_book::*;
 #[derive(CandidType, Deserialize, Clone, Serialize, Debug, Hash, PartialEq)]
 pub struct Conf {
     pub im_canister: Option<String>,
+    /// Treats every caller as its own root when `im_canister` is not set, for local deployments.
+    pub test_mode: Option<bool>,
 }
 
 #[derive(CandidType, Deserialize, Clone, Serialize, Debug, Hash, PartialEq, Eq)]
@@ -50,7 +55,8 @@ const DEFAULT_ADDRESS_BOOK_CONFIG: AddressBookConf = AddressBookConf {
 
 thread_local! {
      static CONFIG: RefCell<Conf> = const { RefCell::new( Conf {
-        im_canister: None
+        im_canister: None,
+        test_mode: None,
     }) };
     pub static ICRC_REGISTRY: RefCell<HashMap<String, HashSet<ICRC1>>> = RefCell::new(HashMap::default());
 
@@ -62,7 +68,7 @@ thread_local! {
 /// Persists the ICRC1 canister metadata for a specified user ledger ID principal.
 #[update]
 pub async fn store_icrc1_canister(ledger_id: String, state: ICRC1State, network: Option<u32>) {
-    let caller = get_root_id().await;
+    let caller = caller_root_id_or_trap().await;
     ICRC_REGISTRY.with(|registry| {
         let mut registry = registry.borrow_mut();
         let canister = ICRC1 {
@@ -79,7 +85,7 @@ pub async fn store_icrc1_canister(ledger_id: String, state: ICRC1State, network:
 
 #[update]
 pub async fn remove_icrc1_canister(ledger_id: String, network: Option<u32>) {
-    let caller = get_root_id().await;
+    let caller = caller_root_id_or_trap().await;
     ICRC_REGISTRY.with(|registry| {
         let mut registry = registry.borrow_mut();
         if let Some(canisters) = registry.get_mut(&caller) {
@@ -92,6 +98,7 @@ pub async fn remove_icrc1_canister(ledger_id: String, network: Option<u32>) {
 /// Initializes the application with `Conf` parameters and saves them to storage.
 #[init]
 pub async fn init(conf: Conf) {
+    configure_resolver(&conf);
     CONFIG.with(|c| c.replace(conf));
 }
 
@@ -107,25 +114,25 @@ pub async fn get_canisters_by_root(root: String) -> Vec<ICRC1> {
 
 #[update]
 pub async fn address_book_save(user_address: AddressBookUserAddress) -> Result<Vec<AddressBookUserAddress>, AddressBookError> {
-    let root_id = get_root_id().await;
+    let root_id = caller_root_id_or_trap().await;
     address_book::service::save(root_id, user_address).await
 }
 
 #[update]
 pub async fn address_book_delete(id: String) -> Result<Vec<AddressBookUserAddress>, AddressBookError> {
-    let root_id = get_root_id().await;
+    let root_id = caller_root_id_or_trap().await;
     address_book::service::delete(root_id, id).await
 }
 
 #[update]
 pub async fn address_book_delete_all() -> Result<(), AddressBookError> {
-    let root_id = get_root_id().await;
+    let root_id = caller_root_id_or_trap().await;
     address_book::service::delete_all(root_id).await
 }
 
 #[query(composite = true)]
 pub async fn address_book_find_all() -> Result<Vec<AddressBookUserAddress>, AddressBookError> {
-    let root_id = get_root_id().await;
+    let root_id = caller_root_id_or_trap().await;
     address_book::service::find_all(root_id).await
 }
 
@@ -139,6 +146,49 @@ pub async fn address_book_set_config(config: AddressBookConf) -> Result<(), Addr
     address_book::service::set_config(config).await
 }
 
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+pub fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(
+        ic_cdk::api::canister_balance128(),
+        ic_cdk::api::stable::stable64_size(),
+    );
+    canister_metrics::http_request(&request, ic_cdk::api::time(), stats, encode_metrics)
+}
+
+fn encode_metrics(w: &mut MetricsEncoder<Vec<u8>>) -> std::io::Result<()> {
+    let (users, canisters) = ICRC_REGISTRY.with(|registry| {
+        let registry = registry.borrow();
+        (registry.len(), registry.values().map(HashSet::len).sum::<usize>())
+    });
+    w.encode_gauge(
+        "icrc1_registry_users",
+        users as f64,
+        "Number of users with ICRC1 canisters in the registry.",
+    )?;
+    w.encode_gauge(
+        "icrc1_canisters",
+        canisters as f64,
+        "Number of ICRC1 canisters in the registry over all users.",
+    )?;
+    let (book_users, addresses) = ADDRESS_BOOK.with(|book| {
+        let book = book.borrow();
+        let addresses = book.values().map(|user| user.user_addresses.len()).sum::<usize>();
+        (book.len(), addresses)
+    });
+    w.encode_gauge(
+        "address_book_users",
+        book_users as f64,
+        "Number of users with an address book.",
+    )?;
+    w.encode_gauge(
+        "address_book_addresses",
+        addresses as f64,
+        "Number of addresses over all address books.",
+    )?;
+    Ok(())
+}
+
 
 #[derive(CandidType, Deserialize, Clone, Serialize, Debug, PartialEq, Eq, Hash)]
 pub struct ICRC1Memory {
@@ -191,6 +241,7 @@ pub fn stable_restore() {
 
     let Memory { config, registry, address_book, address_book_config } = mem;
 
+    configure_resolver(&config);
     CONFIG.with(|c| {
         *c.borrow_mut() = config.clone();
     });
@@ -221,19 +272,9 @@ fn export_candid() -> String {
 }
 
 
-async fn get_root_id() -> String {
-    match CONFIG.with(|c| c.borrow_mut().im_canister.clone()) {
-        None => caller().to_text(), // Return caller for testing purposes when im_canister is None
-        Some(canister) => {
-            let princ = caller();
-            let im_canister = Principal::from_text(canister)
-                .expect("Unable to obtain Principal from im_canister.");
-
-            match call(im_canister, "get_root_by_principal", (princ.to_text(), 0)).await {
-                Ok((Some(root_id), )) => root_id,
-                Ok((None, )) => trap("No root found for this principal"),
-                Err((_, err)) => trap(&format!("Failed to request IM: {}", err)),
-            }
-        }
-    }
+fn configure_resolver(conf: &Conf) {
+    root_id_resolver::configure(ResolverMode::from_config(
+        conf.im_canister.clone(),
+        conf.test_mode.unwrap_or(false),
+    ));
 }
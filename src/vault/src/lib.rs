@@ -8,12 +8,14 @@ use ic_cdk::api::management_canister::main::CanisterStatusResponse;
 use ic_cdk::{call, caller, id, trap};
 use ic_cdk::export::candid;
 use ic_cdk_macros::*;
+use canister_metrics::CanisterStats;
+use serde_bytes::ByteBuf;
 
 use crate::enums::{Backup, TransactionState};
 use crate::memory::{Conf, CONF};
 use crate::policy_service::{Policy, PolicyType, ThresholdPolicy};
 use crate::policy_service::Currency::ICP;
-use crate::request::{CanisterIdRequest, PolicyRegisterRequest, TransactionApproveRequest, TransactionRegisterRequest, VaultMemberRequest, VaultRegisterRequest, WalletRegisterRequest};
+use crate::request::{CanisterIdRequest, HttpRequest, HttpResponse, PolicyRegisterRequest, TransactionApproveRequest, TransactionRegisterRequest, VaultMemberRequest, VaultRegisterRequest, WalletRegisterRequest};
 use crate::security_service::{trap_if_not_permitted, verify_wallets};
 use crate::transaction_service::Transaction;
 use crate::TransactionState::Approved;
@@ -232,6 +234,27 @@ async fn count(obj: Backup) -> u64 {
     memory::count(obj) as u64
 }
 
+/// Serves the canister metrics in the Prometheus format at `/metrics`.
+#[query]
+#[candid_method(query)]
+fn http_request(request: HttpRequest) -> HttpResponse {
+    let stats = CanisterStats::new(ic_cdk::api::canister_balance128(), ic_cdk::api::stable::stable64_size());
+    let response = canister_metrics::serve(&request.url, ic_cdk::api::time(), stats, |w| {
+        let counts = transaction_service::count_by_state();
+        let mut builder = w.gauge_vec("vault_transactions", "Number of transactions by state.")?;
+        for state in [TransactionState::Approved, TransactionState::Rejected, TransactionState::Pending, TransactionState::Canceled] {
+            let count = counts.get(&state).copied().unwrap_or_default();
+            builder = builder.value(&[("state", &format!("{:?}", state))], count as f64)?;
+        }
+        Ok(())
+    });
+    HttpResponse {
+        status_code: response.status_code,
+        headers: response.headers,
+        body: ByteBuf::from(response.body),
+    }
+}
+
 #[test]
 fn sub_account_test() {}
 export_service!();
@@ -1,5 +1,6 @@
 use candid::Principal;
 use ic_cdk::export::{candid::{CandidType, Deserialize}};
+use serde_bytes::ByteBuf;
 
 use crate::{PolicyType, TransactionState, VaultRole};
 use crate::enums::ObjectState;
@@ -48,4 +49,19 @@ pub struct PolicyRegisterRequest {
 pub struct CanisterIdRequest {
     #[serde(rename = "canister_id")]
     pub canister_id: Principal,
-}
\ No newline at end of file
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
+
+#[derive(CandidType, Deserialize, Clone)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+}
@@ -1,4 +1,4 @@
-use std::collections::{HashSet};
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 
 use candid::CandidType;
@@ -152,6 +152,16 @@ pub fn get_all(vaults: HashSet<u64>) -> Vec<Transaction> {
 }
 
 
+pub fn count_by_state() -> HashMap<TransactionState, usize> {
+    TRANSACTIONS.with(|transactions| {
+        let mut counts = HashMap::new();
+        for transaction in transactions.borrow().values() {
+            *counts.entry(transaction.state.clone()).or_insert(0) += 1;
+        }
+        counts
+    })
+}
+
 pub fn get_by_id(id: u64) -> Transaction {
     TRANSACTIONS.with(|transactions| {
         match transactions.borrow_mut().get(&id) {
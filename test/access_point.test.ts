@@ -16,6 +16,7 @@ import {fail} from "assert";
 import { _SERVICE as IdentityManagerType } from "./idl/identity_manager"
 import { verifyCertifiedResponse } from "./util/cert_verification";
 import {call} from "./util/call.util";
+import {getAccessPointProof} from "./util/access_point.util";
 
 describe("Access Point", () => {
 
@@ -39,7 +40,8 @@ describe("Access Point", () => {
             device_type: {
                 Email: null
             },
-            credential_id: []
+            credential_id: [],
+            proof: []
         }
         var accountRequest: HTTPAccountRequest = {
             access_point: [passKeyEmailRequest],
@@ -47,6 +49,7 @@ describe("Access Point", () => {
             anchor: 0n,
             email: ["test@test.test"],
             name: [],
+            id_token: [],
             challenge_attempt: []
         };
 
@@ -67,8 +70,23 @@ describe("Access Point", () => {
             device_type: {
                 Recovery: null
             },
-            credential_id: []
+            credential_id: [],
+            proof: []
         };
+        let rejected = (await actor.create_access_point(
+            request
+        )) as HTTPAccessPointResponse
+        expect(rejected.status_code).eq(400)
+        expect(rejected.error_code[0]).to.have.property("AccessPointProofRequired")
+
+        request.proof = await getAccessPointProof(actor, Ed25519KeyIdentity.generate());
+        rejected = (await actor.create_access_point(
+            request
+        )) as HTTPAccessPointResponse
+        expect(rejected.status_code).eq(403)
+        expect(rejected.error_code[0]).to.have.property("InvalidAccessPointProof")
+
+        request.proof = await getAccessPointProof(actor, recoveryIdentity);
         let ap = (await actor.create_access_point(
             request
         ) )as HTTPAccessPointResponse
@@ -83,14 +101,11 @@ describe("Access Point", () => {
         var recoveryRemoveRequest: AccessPointRemoveRequest = {
             pub_key: recoveryIdentity.getPrincipal().toText(),
         };
-        try {
-            await actor.remove_access_point(
-                recoveryRemoveRequest
-            )
-            fail("")
-        } catch (e) {
-            expect(e.message).contains("Recovery phrase is protected")
-        }
+        let protectedResponse = await actor.remove_access_point(
+            recoveryRemoveRequest
+        ) as HTTPAccessPointResponse
+        expect(protectedResponse.status_code).eq(403)
+        expect(protectedResponse.error_code[0]).to.have.property("RecoveryPhraseProtected")
 
 
         let pkIdentity = Ed25519KeyIdentity.generate()
@@ -103,7 +118,8 @@ describe("Access Point", () => {
             device_type: {
                 Passkey: null
             },
-            credential_id: []
+            credential_id: [],
+            proof: await getAccessPointProof(actor, pkIdentity)
         }
         ap = (await actor.create_access_point(
             passKeyRequest
@@ -168,7 +184,8 @@ describe("Access Point", () => {
             device_type: {
                 Email: null
             },
-            credential_id: []
+            credential_id: [],
+            proof: []
         }
 
         var accountRequest: HTTPAccountRequest = {
@@ -177,6 +194,7 @@ describe("Access Point", () => {
             anchor: 0n,
             email: [email],
             name: [],
+            id_token: [],
                 challenge_attempt: []
         };
 
@@ -200,7 +218,8 @@ describe("Access Point", () => {
             device_type: {
                 Recovery: null
             },
-            credential_id: []
+            credential_id: [],
+            proof: await getAccessPointProof(actor, recoveryIdentity)
         };
         let accessPointResponse = await actor.create_access_point(request);
         expect(accessPointResponse.status_code).eq(200)
@@ -7,7 +7,6 @@ import {register} from "./util/internet_identity.util";
 import {
     _SERVICE as IdentityManagerType,
     AccessPointRequest,
-    AccountResponse,
     BoolHttpResponse,
     CertifiedResponse, Challenge, ConfigurationRequest, ConfigurationResponse,
     HTTPAccessPointResponse,
@@ -21,6 +20,7 @@ import {Expected} from "./constanst/expected.const";
 import {Ed25519KeyIdentity} from "@dfinity/identity";
 import {_SERVICE as InternetIdentityTest, DeviceData} from "./idl/internet_identity_test";
 import {fail} from "assert";
+import {getAccessPointProof} from "./util/access_point.util";
 
 const PHONE = "123456";
 const PHONE_SHA2 = "123456_SHA2";
@@ -44,13 +44,13 @@ describe("Account", () => {
 
         it("should throw error due to existing anchor.", async function () {
             DFX.TOKEN(PHONE, PHONE_SHA2, TOKEN, dfx.root);
-            expect(DFX.CREATE_ACCOUNT_FULL()).eq(Expected.ERROR("Impossible to link this II anchor, please try another one.", "404"));
+            expect(DFX.CREATE_ACCOUNT_FULL()).eq(Expected.ERROR("Impossible to link this II anchor, please try another one.", "409", "opt variant { AccountExists }"));
         });
 
         it("should remove account and block re-registration with same anchor.", async function () {
             expect(DFX.REMOVE_ACCOUNT("identity_manager")).eq(Expected.BOOL("true", "200"));
-            expect(DFX.REMOVE_ACCOUNT("identity_manager")).eq(Expected.ERROR("Unable to remove Account", "404"));
-            expect(DFX.CREATE_ACCOUNT_2()).eq(Expected.ERROR("Impossible to link this II anchor, please try another one.", "404"));
+            expect(DFX.REMOVE_ACCOUNT("identity_manager")).eq(Expected.ERROR("Unable to find Account.", "404", "opt variant { AccountNotFound }"));
+            expect(DFX.CREATE_ACCOUNT_2()).eq(Expected.ERROR("Impossible to link this II anchor, please try another one.", "409", "opt variant { AccountExists }"));
         });
 
         it("should create new account with email and receive a validation error.", async function () {
@@ -78,15 +78,13 @@ describe("Account", () => {
                 anchor: 0n,
                 email: ["testdefault@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
 
-            try {
-                await dfx.im.actor.create_account(accountRequest);
-                fail("Have to fail");
-            } catch (e) {
-                expect(e.message).contains("Device Data required");
-            }
+            const accountResponse = await dfx.im.actor.create_account(accountRequest) as HTTPAccountResponse;
+            expect(accountResponse.status_code).eq(400);
+            expect(accountResponse.error_code[0]).to.have.property("DeviceDataRequired");
         });
 
         it("should return an error by adding new email with bigger timestamp due to self cleaning of the service", async function () {
@@ -102,16 +100,13 @@ describe("Account", () => {
                 anchor: 0n,
                 email: ["testdefault@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
 
-            try {
-                await dfx.im.actor.create_account(accountRequest);
-                fail("Have to fail");
-            } catch (e) {
-                console.log({e})
-                expect(e.message).contains("Email and principal are not valid");
-            }
+            const accountResponse = await dfx.im.actor.create_account(accountRequest) as HTTPAccountResponse;
+            expect(accountResponse.status_code).eq(400);
+            expect(accountResponse.error_code[0]).to.have.property("EmailNotValidated");
         });
 
         it("should return an error when adding an email address exceeding 320 characters", async function () {
@@ -138,6 +133,7 @@ describe("Account", () => {
                     Email: null,
                 },
                 credential_id: [],
+                proof: [],
             };
             var accountRequest: HTTPAccountRequest = {
                 access_point: [dd],
@@ -145,6 +141,7 @@ describe("Account", () => {
                 anchor: 0n,
                 email: ["test@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
             const actor = await getActor(dfx.im.id, identity, imIdl);
@@ -174,6 +171,7 @@ describe("Account", () => {
                 anchor: iiAnchor + 1n,
                 email: [],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
             try {
@@ -190,6 +188,7 @@ describe("Account", () => {
                 anchor: iiAnchor,
                 email: [],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
 
@@ -208,6 +207,7 @@ describe("Account", () => {
                 anchor: iiAnchor,
                 email: ["invalid@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
 
@@ -225,6 +225,7 @@ describe("Account", () => {
                 anchor: iiAnchor,
                 email: ["test@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
 
@@ -245,6 +246,7 @@ describe("Account", () => {
                     Email: null,
                 },
                 credential_id: [],
+                proof: [],
             };
 
             try {
@@ -264,6 +266,7 @@ describe("Account", () => {
                     Email: null,
                 },
                 credential_id: ["test_id"],
+                proof: [],
             };
 
             var response: HTTPAccessPointResponse = (await dfx.im.actor.create_access_point(
@@ -296,11 +299,11 @@ describe("Account", () => {
             const getResponse: HTTPAccountResponse = (await actor.get_account()) as HTTPAccountResponse;
             expect(getResponse.status_code).eq(404);
             expect(getResponse.data).empty;
-            expect(getResponse.error[0]).eq("Unable to find Account");
+            expect(getResponse.error_code[0]).to.have.property("AccountNotFound");
 
             const removeAgainResponse: BoolHttpResponse = (await actor.remove_account()) as BoolHttpResponse;
             expect(removeAgainResponse.status_code).eq(404);
-            expect(removeAgainResponse.error[0]).eq("Unable to remove Account");
+            expect(removeAgainResponse.error_code[0]).to.have.property("AccountNotFound");
         });
 
         it("should remove account when called from an access point identity.", async function () {
@@ -312,14 +315,15 @@ describe("Account", () => {
 
             await dfx.im.actor.add_email_and_principal_for_create_account_validation("remove-ap@test.test", ownerIdentity.getPrincipal().toText(), 25n);
             await ownerActor.create_account({
-                access_point: [{ icon: "Icon", device: "Device", pub_key: ownerIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [] }],
+                access_point: [{ icon: "Icon", device: "Device", pub_key: ownerIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [], proof: [] }],
                 wallet: [{ NFID: null }],
                 anchor: 0n,
                 email: ["remove-ap@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: [],
             });
-            await ownerActor.create_access_point({ icon: "Icon", device: "Device", pub_key: accessPointIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Recovery: null }, credential_id: [] });
+            await ownerActor.create_access_point({ icon: "Icon", device: "Device", pub_key: accessPointIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Recovery: null }, credential_id: [], proof: await getAccessPointProof(ownerActor, accessPointIdentity) });
 
             // When: access point identity triggers removal
             const removeResponse: BoolHttpResponse = (await accessPointActor.remove_account()) as BoolHttpResponse;
@@ -331,12 +335,12 @@ describe("Account", () => {
             const ownerResponse: HTTPAccountResponse = (await ownerActor.get_account()) as HTTPAccountResponse;
             expect(ownerResponse.status_code).eq(404);
             expect(ownerResponse.data).empty;
-            expect(ownerResponse.error[0]).eq("Unable to find Account");
+            expect(ownerResponse.error_code[0]).to.have.property("AccountNotFound");
 
             const accessPointResponse: HTTPAccountResponse = (await accessPointActor.get_account()) as HTTPAccountResponse;
             expect(accessPointResponse.status_code).eq(404);
             expect(accessPointResponse.data).empty;
-            expect(accessPointResponse.error[0]).eq("Unable to find Account");
+            expect(accessPointResponse.error_code[0]).to.have.property("AccountNotFound");
         });
 
         it("should not reuse NFID anchor after account deletion.", async function () {
@@ -348,11 +352,12 @@ describe("Account", () => {
 
             await dfx.im.actor.add_email_and_principal_for_create_account_validation("first-anchor@test.test", firstIdentity.getPrincipal().toText(), 25n);
             const firstCreate: HTTPAccountResponse = (await firstActor.create_account({
-                access_point: [{ icon: "Icon", device: "Device", pub_key: firstIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [] }],
+                access_point: [{ icon: "Icon", device: "Device", pub_key: firstIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [], proof: [] }],
                 wallet: [{ NFID: null }],
                 anchor: 0n,
                 email: ["first-anchor@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: [],
             })) as HTTPAccountResponse;
             const firstAnchor = firstCreate.data[0].anchor;
@@ -362,11 +367,12 @@ describe("Account", () => {
 
             await dfx.im.actor.add_email_and_principal_for_create_account_validation("second-anchor@test.test", secondIdentity.getPrincipal().toText(), 25n);
             const secondCreate: HTTPAccountResponse = (await secondActor.create_account({
-                access_point: [{ icon: "Icon", device: "Device", pub_key: secondIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [] }],
+                access_point: [{ icon: "Icon", device: "Device", pub_key: secondIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [], proof: [] }],
                 wallet: [{ NFID: null }],
                 anchor: 0n,
                 email: ["second-anchor@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: [],
             })) as HTTPAccountResponse;
             const secondAnchor = secondCreate.data[0].anchor;
@@ -384,6 +390,7 @@ describe("Account", () => {
                 wallet: [],
                 email: [],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
             await dfx.im.actor.create_account(accountRequest as any);
@@ -402,6 +409,7 @@ describe("Account", () => {
                     Email: null,
                 },
                 credential_id: [],
+                proof: [],
             };
             var accountRequest: HTTPAccountRequest = {
                 access_point: [dd],
@@ -409,6 +417,7 @@ describe("Account", () => {
                 anchor: 0n,
                 email: ["test2@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
             const actor = await getActor(dfx.im.id, identity, imIdl);
@@ -428,12 +437,14 @@ describe("Account", () => {
                     Passkey: null,
                 },
                 credential_id: ["some_id"],
+                proof: await getAccessPointProof(actor, identityDevice),
             };
             await actor.create_access_point(deviceData2);
 
             //enable 2fa
-            let account = (await actor.update_2fa(true)) as AccountResponse;
-            expect(account.is2fa_enabled).eq(true);
+            let account = (await actor.update_2fa(true)) as HTTPAccountResponse;
+            expect(account.status_code).eq(200);
+            expect(account.data[0].is2fa_enabled).eq(true);
             const actorDevice = await getActor(dfx.im.id, identityDevice, imIdl);
             //try to update from Email
             try {
@@ -449,8 +460,9 @@ describe("Account", () => {
             } catch (e) {
                 expect(e.message).contains("Unauthorised");
             }
-            let updated2fa = (await actorDevice.update_2fa(false)) as AccountResponse;
-            expect(updated2fa.is2fa_enabled).eq(false);
+            let updated2fa = (await actorDevice.update_2fa(false)) as HTTPAccountResponse;
+            expect(updated2fa.status_code).eq(200);
+            expect(updated2fa.data[0].is2fa_enabled).eq(false);
         });
 
 
@@ -468,7 +480,8 @@ describe("Account", () => {
                 device_type: {
                     Unknown: null
                 },
-                credential_id: []
+                credential_id: [],
+                proof: []
             }
 
             var accountRequest: HTTPAccountRequest = {
@@ -477,6 +490,7 @@ describe("Account", () => {
                 anchor,
                 email: [],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
 
@@ -515,7 +529,8 @@ describe("Account", () => {
                 device_type: {
                     Recovery: null
                 },
-                credential_id: []
+                credential_id: [],
+                proof: []
             }
             await actor.create_access_point(recoveryPhraseOldAccessPointRequest);
 
@@ -562,6 +577,40 @@ describe("Account", () => {
             expect(recoveryDevice.principal_id).to.eq(recoveryPhrasePrincipal);
         });
 
+        it("should migrate Internet Identity account to NFID", async function () {
+            const identity = getIdentity("87654321876543218765432187654918");
+            const principalId = identity.getPrincipal().toText();
+            const actorII = await getTypedActor<InternetIdentityTest>(dfx.iit.id, identity, iitIdl);
+            const anchor = await register(actorII, identity);
+
+            const accountRequest: HTTPAccountRequest = {
+                access_point: [],
+                wallet: [{II: null}],
+                anchor,
+                email: [],
+                name: [],
+                id_token: [],
+                challenge_attempt: []
+            };
+            const actor = await getTypedActor<IdentityManagerType>(dfx.im.id, identity, imIdl);
+            const accountResponse = await actor.create_account(accountRequest);
+            expect(accountResponse.status_code).eq(200);
+
+            const migrated = await actor.migrate_to_nfid();
+            expect(migrated.status_code).eq(200);
+            expect(migrated.data[0].wallet).deep.eq({NFID: null});
+            expect(migrated.data[0].principal_id).eq(principalId);
+            expect(migrated.data[0].anchor).not.eq(anchor);
+
+            const account = await actor.get_account();
+            expect(account.data[0].anchor).eq(migrated.data[0].anchor);
+            expect(account.data[0].wallet).deep.eq({NFID: null});
+
+            const again = await actor.migrate_to_nfid();
+            expect(again.status_code).eq(400);
+            expect(again.error_code[0]).to.have.property("InternetIdentityOnly");
+        });
+
         it("should create NFID account with passkey", async function () {
             const tempIdentity = getIdentity("87654321876543218765432187654312");
             let actor = await getActor(dfx.im.id, tempIdentity, imIdl);
@@ -577,6 +626,7 @@ describe("Account", () => {
                     Passkey: null,
                 },
                 credential_id: ["someId"],
+                proof: [],
             };
 
             const captcha = await actor.get_captcha() as Challenge;
@@ -587,6 +637,7 @@ describe("Account", () => {
                 anchor: 0n,
                 email: [],
                 name: ["TestWallet"],
+                id_token: [],
                 challenge_attempt: [{
                     chars: ["aaaaa"],
                     challenge_key: captcha.challenge_key
@@ -625,6 +676,7 @@ describe("Account", () => {
                     Passkey: null,
                 },
                 credential_id: ["someId"],
+                proof: [],
             };
 
             var accountRequest = {
@@ -633,17 +685,15 @@ describe("Account", () => {
                 anchor: 0n,
                 email: [],
                 name: [],
+                id_token: [],
                 challenge_attempt: []
             };
 
-            try {
-                await actor.create_account(
-                    accountRequest
-                );
-                fail("Should fail");
-            } catch (e) {
-                expect(e.message).contains("Name is empty");
-            }
+            const accountResponse = await actor.create_account(
+                accountRequest
+            ) as HTTPAccountResponse;
+            expect(accountResponse.status_code).eq(400);
+            expect(accountResponse.error_code[0]).to.have.property("NameRequired");
 
         });
 
@@ -681,6 +731,7 @@ describe("Account", () => {
                     Passkey: null,
                 },
                 credential_id: ["someId"],
+                proof: [],
             };
 
             const emptyCaptcha = await actor.get_captcha() as Challenge;
@@ -694,6 +745,7 @@ describe("Account", () => {
                 anchor: 0n,
                 email: [],
                 name: ["aaa"],
+                id_token: [],
                 challenge_attempt: [ {
                     chars: ["aaaaa"],
                     challenge_key: captcha.challenge_key
@@ -712,14 +764,15 @@ describe("Account", () => {
 
             await dfx.im!.actor.add_email_and_principal_for_create_account_validation("passkey-delete-fail@test.test", emailIdentity.getPrincipal().toText(), 25n);
             await emailActor.create_account({
-                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [] }],
+                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [], proof: [] }],
                 wallet: [{ NFID: null }],
                 anchor: 0n,
                 email: ["passkey-delete-fail@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: [],
             });
-            await emailActor.create_access_point({ icon: "Icon", device: "Passkey", pub_key: passkeyIdentity.getPrincipal().toText(), browser: "", device_type: { Passkey: null }, credential_id: ["pk-cred-1"] });
+            await emailActor.create_access_point({ icon: "Icon", device: "Passkey", pub_key: passkeyIdentity.getPrincipal().toText(), browser: "", device_type: { Passkey: null }, credential_id: ["pk-cred-1"], proof: await getAccessPointProof(emailActor, passkeyIdentity) });
             await emailActor.update_2fa(true);
 
             // When: email calls remove_account
@@ -727,7 +780,7 @@ describe("Account", () => {
 
             // Then: 403 with passkey required
             expect(removeResponse.status_code).eq(403);
-            expect(removeResponse.error[0]).contains("Unauthorised: passkey required to delete account");
+            expect(removeResponse.error_code[0]).to.have.property("PasskeyRequired");
         });
 
         it("should allow remove_account from passkey when passkey and 2FA are enabled.", async function () {
@@ -739,14 +792,15 @@ describe("Account", () => {
 
             await dfx.im!.actor.add_email_and_principal_for_create_account_validation("passkey-delete-ok@test.test", emailIdentity.getPrincipal().toText(), 25n);
             await emailActor.create_account({
-                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [] }],
+                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [], proof: [] }],
                 wallet: [{ NFID: null }],
                 anchor: 0n,
                 email: ["passkey-delete-ok@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: [],
             });
-            await emailActor.create_access_point({ icon: "Icon", device: "Passkey", pub_key: passkeyIdentity.getPrincipal().toText(), browser: "", device_type: { Passkey: null }, credential_id: ["pk-cred-2"] });
+            await emailActor.create_access_point({ icon: "Icon", device: "Passkey", pub_key: passkeyIdentity.getPrincipal().toText(), browser: "", device_type: { Passkey: null }, credential_id: ["pk-cred-2"], proof: await getAccessPointProof(emailActor, passkeyIdentity) });
             await emailActor.update_2fa(true);
 
             // When: passkey calls remove_account
@@ -765,21 +819,22 @@ describe("Account", () => {
 
             await dfx.im!.actor.add_email_and_principal_for_create_account_validation("recovery-delete-fail@test.test", emailIdentity.getPrincipal().toText(), 25n);
             await emailActor.create_account({
-                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [] }],
+                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [], proof: [] }],
                 wallet: [{ NFID: null }],
                 anchor: 0n,
                 email: ["recovery-delete-fail@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: [],
             });
-            await emailActor.create_access_point({ icon: "document", device: "seedphrase", pub_key: recoveryIdentity.getPrincipal().toText(), browser: "", device_type: { Recovery: null }, credential_id: [] });
+            await emailActor.create_access_point({ icon: "document", device: "seedphrase", pub_key: recoveryIdentity.getPrincipal().toText(), browser: "", device_type: { Recovery: null }, credential_id: [], proof: await getAccessPointProof(emailActor, recoveryIdentity) });
 
             // When: email calls remove_account
             const removeResponse: BoolHttpResponse = (await emailActor.remove_account()) as BoolHttpResponse;
 
             // Then: 403 with seed phrase required
             expect(removeResponse.status_code).eq(403);
-            expect(removeResponse.error[0]).contains("Unauthorised: seed phrase required to delete account");
+            expect(removeResponse.error_code[0]).to.have.property("RecoveryPhraseRequired");
         });
 
         it("should allow remove_account from recovery when seed phrase exists.", async function () {
@@ -791,14 +846,15 @@ describe("Account", () => {
 
             await dfx.im!.actor.add_email_and_principal_for_create_account_validation("recovery-delete-ok@test.test", emailIdentity.getPrincipal().toText(), 25n);
             await emailActor.create_account({
-                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [] }],
+                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [], proof: [] }],
                 wallet: [{ NFID: null }],
                 anchor: 0n,
                 email: ["recovery-delete-ok@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: [],
             });
-            await emailActor.create_access_point({ icon: "document", device: "seedphrase", pub_key: recoveryIdentity.getPrincipal().toText(), browser: "", device_type: { Recovery: null }, credential_id: [] });
+            await emailActor.create_access_point({ icon: "document", device: "seedphrase", pub_key: recoveryIdentity.getPrincipal().toText(), browser: "", device_type: { Recovery: null }, credential_id: [], proof: await getAccessPointProof(emailActor, recoveryIdentity) });
 
             // When: recovery calls remove_account
             const removeResponse: BoolHttpResponse = (await recoveryActor.remove_account()) as BoolHttpResponse;
@@ -819,24 +875,25 @@ describe("Account", () => {
 
             await dfx.im!.actor.add_email_and_principal_for_create_account_validation("no-passkey-2fa@test.test", emailIdentity.getPrincipal().toText(), 25n);
             await emailActor.create_account({
-                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [] }],
+                access_point: [{ icon: "Icon", device: "Device", pub_key: emailIdentity.getPrincipal().toText(), browser: "Browser", device_type: { Email: null }, credential_id: [], proof: [] }],
                 wallet: [{ NFID: null }],
                 anchor: 0n,
                 email: ["no-passkey-2fa@test.test"],
                 name: [],
+                id_token: [],
                 challenge_attempt: [],
             });
-            await emailActor.create_access_point({ icon: "Icon", device: "Passkey", pub_key: passkeyIdentity.getPrincipal().toText(), browser: "", device_type: { Passkey: null }, credential_id: ["pk-cred-3"] });
-            await emailActor.create_access_point({ icon: "document", device: "seedphrase", pub_key: recoveryIdentity.getPrincipal().toText(), browser: "", device_type: { Recovery: null }, credential_id: [] });
+            await emailActor.create_access_point({ icon: "Icon", device: "Passkey", pub_key: passkeyIdentity.getPrincipal().toText(), browser: "", device_type: { Passkey: null }, credential_id: ["pk-cred-3"], proof: await getAccessPointProof(emailActor, passkeyIdentity) });
+            await emailActor.create_access_point({ icon: "document", device: "seedphrase", pub_key: recoveryIdentity.getPrincipal().toText(), browser: "", device_type: { Recovery: null }, credential_id: [], proof: await getAccessPointProof(emailActor, recoveryIdentity) });
             await emailActor.update_2fa(true);
 
             // When: passkey removes itself, leaving 2FA enabled with no passkey
             await passkeyActor.remove_access_point({ pub_key: passkeyIdentity.getPrincipal().toText() });
 
-            // Then: recovery calls remove_account and gets 403 no passkey found
+            // Then: recovery calls remove_account and gets 403 passkey required
             const removeResponse: BoolHttpResponse = (await recoveryActor.remove_account()) as BoolHttpResponse;
             expect(removeResponse.status_code).eq(403);
-            expect(removeResponse.error[0]).contains("Unauthorised: no passkey found");
+            expect(removeResponse.error_code[0]).to.have.property("PasskeyRequired");
         });
 
         it("Not test captcha should fail on create acc attempt", async function () {
@@ -873,6 +930,7 @@ describe("Account", () => {
                     Passkey: null,
                 },
                 credential_id: ["someId"],
+                proof: [],
             };
             await actor.get_captcha()
             const captcha = await actor.get_captcha() as Challenge;
@@ -884,18 +942,16 @@ describe("Account", () => {
                 anchor: 0n,
                 email: [],
                 name: ["aaa"],
+                id_token: [],
                 challenge_attempt: [ {
                     chars: ["aaaaa"],
                     challenge_key: captcha.challenge_key
                 }]
             };
 
-            try {
-                await actor.create_account(accountRequest) as HTTPAccountResponse;
-                fail("Should fail");
-            } catch (error) {
-                expect((error as any).message).contains("Incorrect captcha solution");
-            }
+            let captchaResponse = await actor.create_account(accountRequest) as HTTPAccountResponse;
+            expect(captchaResponse.status_code).eq(400);
+            expect(captchaResponse.error_code[0]).to.have.property("IncorrectCaptchaSolution");
 
             accountRequest = {
                 access_point: [dd],
@@ -903,18 +959,16 @@ describe("Account", () => {
                 anchor: 0n,
                 email: [],
                 name: ["aaa"],
+                id_token: [],
                 challenge_attempt: [ {
                     chars: [],
                     challenge_key: captcha.challenge_key
                 }]
             };
 
-            try {
-                await actor.create_account(accountRequest) as HTTPAccountResponse;
-                fail("Should fail");
-            } catch (error) {
-                expect((error as any).message).contains("Solution is required");
-            }
+            captchaResponse = await actor.create_account(accountRequest) as HTTPAccountResponse;
+            expect(captchaResponse.status_code).eq(400);
+            expect(captchaResponse.error_code[0]).to.have.property("SolutionRequired");
 
             accountRequest = {
                 access_point: [dd],
@@ -922,18 +976,16 @@ describe("Account", () => {
                 anchor: 0n,
                 email: [],
                 name: ["aaa"],
+                id_token: [],
                 challenge_attempt: [ {
                     chars: [],
                     challenge_key: "asdasd"
                 }]
             };
 
-            try {
-                await actor.create_account(accountRequest) as HTTPAccountResponse;
-                fail("Should fail");
-            } catch (error) {
-                expect((error as any).message).contains("Incorrect captcha key");
-            }
+            captchaResponse = await actor.create_account(accountRequest) as HTTPAccountResponse;
+            expect(captchaResponse.status_code).eq(400);
+            expect(captchaResponse.error_code[0]).to.have.property("IncorrectCaptchaKey");
         });
     });
 });
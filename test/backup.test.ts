@@ -0,0 +1,63 @@
+import "mocha";
+import {expect} from "chai";
+import {AccountsChunk, HTTPAccountsImportResponse} from "./idl/identity_manager";
+import {createIdentityManagerAccount, deploy} from "./util/deployment.util";
+import {Dfx} from "./type/dfx";
+import {App} from "./constanst/app.enum";
+
+describe("Backup", () => {
+    var dfx: Dfx;
+
+    before(async () => {
+        dfx = await deploy({apps: [App.IdentityManager]});
+        await createIdentityManagerAccount(dfx);
+    });
+
+    it("should export accounts in chunks and import them back", async function () {
+        const chunks: AccountsChunk[] = [];
+        let cursor: [] | [string] = [];
+        let checksum: [] | [Uint8Array | number[]] = [];
+        do {
+            const chunk = await dfx.im.actor.export_accounts({
+                cursor,
+                limit: [1],
+                format: {Cbor: null},
+                checksum,
+            });
+            chunks.push(chunk);
+            cursor = chunk.next_cursor;
+            checksum = [chunk.checksum];
+        } while (cursor.length > 0);
+
+        const exported = chunks.reduce((count, chunk) => count + chunk.count, 0);
+        expect(exported).eq(Number(await dfx.im.actor.count_anchors()));
+
+        const tampered = await dfx.im.actor.import_accounts({
+            data: new Uint8Array([...chunks[0].data, 0]),
+            format: {Cbor: null},
+            checksum: chunks[0].checksum,
+            first_chunk: [true],
+        }) as HTTPAccountsImportResponse;
+        expect(tampered.status_code).eq(409);
+        expect(tampered.error_code[0]).to.have.property("BackupChecksumMismatch");
+
+        const importAll = async (): Promise<number> => {
+            let imported = 0;
+            for (const [i, chunk] of chunks.entries()) {
+                const response = await dfx.im.actor.import_accounts({
+                    data: chunk.data,
+                    format: {Cbor: null},
+                    checksum: chunk.checksum,
+                    first_chunk: [i === 0],
+                }) as HTTPAccountsImportResponse;
+                expect(response.status_code).eq(200);
+                expect(response.data[0].checksum).deep.eq(chunk.checksum);
+                imported += response.data[0].imported;
+            }
+            return imported;
+        };
+        expect(await importAll()).eq(exported);
+        // A flagged first chunk starts a new chain, so the same export can be imported again.
+        expect(await importAll()).eq(exported);
+    });
+});
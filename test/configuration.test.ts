@@ -114,6 +114,7 @@ describe("Configuration", () => {
                 Email: null,
             },
             credential_id: [],
+            proof: [],
         };
         var accountRequest: HTTPAccountRequest = {
             access_point: [dd],
@@ -121,6 +122,7 @@ describe("Configuration", () => {
             anchor: 0n,
             email: ["test@test.test"],
             name: [],
+            id_token: [],
                 challenge_attempt: [],
         };
         const actor = await getActor(dfx.im.id, identity, imIdl);
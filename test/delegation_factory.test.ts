@@ -37,6 +37,7 @@ describe("Delegation Factory test", () => {
                 Email: null,
             },
             credential_id: [],
+            proof: [],
         };
         var accountRequest: HTTPAccountRequest = {
             access_point: [dd],
@@ -44,6 +45,7 @@ describe("Delegation Factory test", () => {
             anchor: 0n,
             email: ["test@test.test"],
             name: [],
+            id_token: [],
                 challenge_attempt: [],
         };
         const actor = await getActor(dfx.im.id, identity, imIdl);
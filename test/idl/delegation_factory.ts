@@ -20,6 +20,18 @@ export interface SignedDelegation {
 export type Timestamp = bigint;
 export type UserKey = PublicKey;
 export type UserNumber = bigint;
+export type HeaderField = [string, string];
+export interface HttpRequest {
+    'url' : string,
+    'method' : string,
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+}
+export interface HttpResponse {
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+    'status_code' : number,
+}
 export interface _SERVICE {
     'clean_memory' : ActorMethod<[], undefined>,
     'get_delegation' : ActorMethod<
@@ -33,6 +45,7 @@ export interface _SERVICE {
         GetDelegationResponse
     >,
     'get_principal' : ActorMethod<[UserNumber, FrontendHostname], Principal>,
+    'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
     'init_salt' : ActorMethod<[], undefined>,
     'prepare_delegation' : ActorMethod<
         [
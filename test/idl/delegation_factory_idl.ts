@@ -1,4 +1,16 @@
 export const idlFactory = ({ IDL }) => {
+    const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
+    const HttpRequest = IDL.Record({
+        'url' : IDL.Text,
+        'method' : IDL.Text,
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+    });
+    const HttpResponse = IDL.Record({
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+        'status_code' : IDL.Nat16,
+    });
     const InitArgs = IDL.Record({ 'im_canister' : IDL.Principal });
     const UserNumber = IDL.Nat64;
     const FrontendHostname = IDL.Text;
@@ -37,6 +49,7 @@ export const idlFactory = ({ IDL }) => {
             [IDL.Principal],
             ['query'],
         ),
+        'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
         'init_salt' : IDL.Func([], [], []),
         'prepare_delegation' : IDL.Func(
             [
@@ -20,12 +20,25 @@ export type Result = { 'Ok' : SignatureReply } |
 export type Result_1 = { 'Ok' : PublicKeyReply } |
     { 'Err' : string };
 export interface SignatureReply { 'signature' : Uint8Array }
+export type HeaderField = [string, string];
+export interface HttpRequest {
+    'url' : string,
+    'method' : string,
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+}
+export interface HttpResponse {
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+    'status_code' : number,
+}
 export interface _SERVICE {
     'add_kp' : ActorMethod<[KeyPair], undefined>,
     'count' : ActorMethod<[], bigint>,
     'get_all_json' : ActorMethod<[number, number], string>,
     'get_kp' : ActorMethod<[], KeyPairResponse>,
     'get_public_key' : ActorMethod<[string], [] | [string]>,
+    'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
     'get_principal' : ActorMethod<[[] | [string]], [string, [] | [string]]>,
     'get_signature' : ActorMethod<[string], Result>,
     'prepare_signature' : ActorMethod<[Uint8Array], string>,
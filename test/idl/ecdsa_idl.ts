@@ -1,4 +1,16 @@
 export const idlFactory = ({ IDL }) => {
+    const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
+    const HttpRequest = IDL.Record({
+        'url' : IDL.Text,
+        'method' : IDL.Text,
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+    });
+    const HttpResponse = IDL.Record({
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+        'status_code' : IDL.Nat16,
+    });
     const KeyPair = IDL.Record({
         'public_key' : IDL.Text,
         'private_key_encrypted' : IDL.Text,
@@ -22,6 +34,7 @@ export const idlFactory = ({ IDL }) => {
         'get_all_json' : IDL.Func([IDL.Nat32, IDL.Nat32], [IDL.Text], ['query']),
         'get_kp' : IDL.Func([], [KeyPairResponse], []),
         'get_public_key' : IDL.Func([IDL.Text], [IDL.Opt(IDL.Text)], ['query']),
+        'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
         'get_principal' : IDL.Func(
             [IDL.Opt(IDL.Text)],
             [IDL.Text, IDL.Opt(IDL.Text)],
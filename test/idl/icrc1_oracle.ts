@@ -12,6 +12,7 @@ export type Category = { 'Sns' : null } |
 export interface Conf {
     'operator' : [] | [Principal],
     'im_canister' : [] | [Principal],
+    'test_mode' : [] | [boolean],
 }
 export interface ICRC1 {
     'fee' : bigint,
@@ -106,9 +107,22 @@ export type PlaceBidError =
     | { 'TransferFailed' : string }
     | { 'NotConfigured' : null };
 export type PlaceBidResult = { 'Ok' : FeaturedSlot } | { 'Err' : PlaceBidError };
+export type HeaderField = [string, string];
+export interface HttpRequest {
+    'url' : string,
+    'method' : string,
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+}
+export interface HttpResponse {
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+    'status_code' : number,
+}
 export interface _SERVICE {
     'count_icrc1_canisters' : ActorMethod<[], bigint>,
     'get_all_icrc1_canisters' : ActorMethod<[], Array<ICRC1>>,
+    'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
     'get_all_neurons' : ActorMethod<[], Array<NeuronData>>,
     'get_icrc1_paginated' : ActorMethod<[bigint, bigint], Array<ICRC1>>,
     'remove_icrc1_canister' : ActorMethod<[string], undefined>,
@@ -1,7 +1,20 @@
 export const idlFactory = ({ IDL }) => {
+    const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
+    const HttpRequest = IDL.Record({
+        'url' : IDL.Text,
+        'method' : IDL.Text,
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+    });
+    const HttpResponse = IDL.Record({
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+        'status_code' : IDL.Nat16,
+    });
     const Conf = IDL.Record({
         'operator' : IDL.Opt(IDL.Principal),
         'im_canister' : IDL.Opt(IDL.Principal),
+        'test_mode' : IDL.Opt(IDL.Bool),
     });
     const Category = IDL.Variant({
         'Sns' : IDL.Null,
@@ -112,6 +125,7 @@ export const idlFactory = ({ IDL }) => {
     return IDL.Service({
         'count_icrc1_canisters' : IDL.Func([], [IDL.Nat64], ['query']),
         'get_all_icrc1_canisters' : IDL.Func([], [IDL.Vec(ICRC1)], ['query']),
+        'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
         'get_all_neurons' : IDL.Func([], [IDL.Vec(NeuronData)], ['query']),
         'get_icrc1_paginated' : IDL.Func(
             [IDL.Nat64, IDL.Nat64],
@@ -151,6 +165,7 @@ export const init = ({ IDL }) => {
     const Conf = IDL.Record({
         'operator' : IDL.Opt(IDL.Principal),
         'im_canister' : IDL.Opt(IDL.Principal),
+        'test_mode' : IDL.Opt(IDL.Bool),
     });
     return [IDL.Opt(Conf)];
 };
@@ -2,7 +2,46 @@ import type { Principal } from '@dfinity/principal';
 import type { ActorMethod } from '@dfinity/agent';
 import type { IDL } from '@dfinity/candid';
 
+export interface AccessPointChallenge {
+    'nonce' : Uint8Array | number[],
+    'expires_at' : bigint,
+}
+export interface AccessPointProof {
+    'public_key' : Uint8Array | number[],
+    'signature' : Uint8Array | number[],
+    'delegation' : [] | [ProofDelegation],
+}
 export interface AccessPointRemoveRequest { 'pub_key' : string }
+export type BackupFormat = { 'Candid' : null } |
+    { 'Cbor' : null };
+export interface AccountsExportRequest {
+    'cursor' : [] | [string],
+    'limit' : [] | [number],
+    'format' : BackupFormat,
+    'checksum' : [] | [Uint8Array | number[]],
+}
+export interface AccountsChunk {
+    'data' : Uint8Array | number[],
+    'count' : number,
+    'next_cursor' : [] | [string],
+    'checksum' : Uint8Array | number[],
+}
+export interface AccountsImportRequest {
+    'data' : Uint8Array | number[],
+    'format' : BackupFormat,
+    'checksum' : Uint8Array | number[],
+    'first_chunk' : [] | [boolean],
+}
+export interface AccountsImportResponse {
+    'imported' : number,
+    'checksum' : Uint8Array | number[],
+}
+export interface HTTPAccountsImportResponse {
+    'data' : [] | [AccountsImportResponse],
+    'error' : [] | [Error],
+    'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
+}
 export interface AccessPointRequest {
     'icon' : string,
     'device_type' : DeviceType,
@@ -10,6 +49,7 @@ export interface AccessPointRequest {
     'pub_key' : string,
     'browser' : string,
     'credential_id' : [] | [string],
+    'proof' : [] | [AccessPointProof],
 }
 export interface AccessPointResponse {
     'icon' : string,
@@ -56,10 +96,51 @@ export interface BasicEntity {
     'modified_date' : bigint,
     'created_date' : bigint,
 }
+export type IdentityManagerError =
+    { 'AccountNotFound' : null }
+    | { 'AnchorNotRegistered' : null }
+    | { 'PrincipalNotRegistered' : null }
+    | { 'AccountExists' : null }
+    | { 'AnonymousCaller' : null }
+    | { 'NfidOnly' : null }
+    | { 'AccessPointNotFound' : null }
+    | { 'AccessPointExists' : null }
+    | { 'NotAnAccessPoint' : null }
+    | { 'Forbidden' : null }
+    | { 'PasskeyRequired' : null }
+    | { 'RecoveryPhraseRequired' : null }
+    | { 'RecoveryPhraseProtected' : null }
+    | { 'DeviceDataRequired' : null }
+    | { 'NameRequired' : null }
+    | { 'EmailRequired' : null }
+    | { 'EmailNotValidated' : null }
+    | { 'ChallengeRequired' : null }
+    | { 'SolutionRequired' : null }
+    | { 'IncorrectCaptchaKey' : null }
+    | { 'IncorrectCaptchaSolution' : null }
+    | { 'RecoveryPending' : null }
+    | { 'NoPendingRecovery' : null }
+    | { 'RecoveryNotAllowed' : null }
+    | { 'NoRecoveryPhrase' : null }
+    | { 'ConfigurationVersionNotFound' : null }
+    | { 'IdTokenRequired' : null }
+    | { 'InvalidIdToken' : null }
+    | { 'AccessPointProofRequired' : null }
+    | { 'InvalidAccessPointProof' : null }
+    | { 'BackupChecksumMismatch' : null }
+    | { 'InvalidBackup' : null }
+    | { 'NoPendingRemoval' : null }
+    | { 'InvalidAnchorRange' : null }
+    | { 'AnchorRangeExhausted' : null }
+    | { 'InternetIdentityOnly' : null }
+    | { 'StepUpRequired' : null }
+    | { 'InvalidStepUpRule' : null }
+    | { 'InvalidPublicKey' : null };
 export interface BoolHttpResponse {
     'data' : [] | [boolean],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export type CanisterCyclesAggregatedData = BigUint64Array | bigint[];
 export type CanisterHeapMemoryAggregatedData = BigUint64Array | bigint[];
@@ -89,6 +170,23 @@ export interface CertifiedResponse {
     'witness' : Uint8Array | number[],
     'response' : string,
 }
+export interface CertifiedResolutionsResponse {
+    'certificate' : Uint8Array | number[],
+    'witness' : Uint8Array | number[],
+    'response' : Array<PrincipalResolution>,
+}
+export type HeaderField = [string, string];
+export interface HttpRequest {
+    'url' : string,
+    'method' : string,
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+}
+export interface HttpResponse {
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+    'status_code' : number,
+}
 export interface Challenge {
     'png_base64' : [] | [string],
     'challenge_key' : string,
@@ -170,6 +268,7 @@ export interface HTTPAccessPointResponse {
     'data' : [] | [Array<AccessPointResponse>],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export interface HTTPAccountRequest {
     'name' : [] | [string],
@@ -178,11 +277,13 @@ export interface HTTPAccountRequest {
     'access_point' : [] | [AccessPointRequest],
     'wallet' : [] | [WalletVariant],
     'challenge_attempt' : [] | [ChallengeAttempt],
+    'id_token' : [] | [string],
 }
 export interface HTTPAccountResponse {
     'data' : [] | [AccountResponse],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export interface HTTPAccountUpdateRequest {
     'name' : [] | [string],
@@ -192,26 +293,31 @@ export interface HTTPAnchorsResponse {
     'data' : [] | [BigUint64Array | bigint[]],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export interface HTTPAppResponse {
     'data' : [] | [Application],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export interface HTTPApplicationResponse {
     'data' : [] | [Array<Application>],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export interface HTTPOneAccessPointResponse {
     'data' : [] | [AccessPointResponse],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export interface HTTPPersonasResponse {
     'data' : [] | [Array<PersonaResponse>],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export interface HourlyMetricsData {
     'updateCalls' : UpdateCallsAggregatedData,
@@ -220,6 +326,11 @@ export interface HourlyMetricsData {
     'canisterMemorySize' : CanisterMemoryAggregatedData,
     'timeMillis' : bigint,
 }
+export interface Jwk {
+    'kid' : string,
+    'n' : string,
+    'e' : string,
+}
 export interface LogMessagesData { 'timeNanos' : Nanos, 'message' : string }
 export type MetricsGranularity = { 'hourly' : null } |
     { 'daily' : null };
@@ -231,6 +342,16 @@ export interface NumericEntity {
     'first' : bigint,
     'last' : bigint,
 }
+export interface ProofDelegation {
+    'pubkey' : Uint8Array | number[],
+    'expiration' : bigint,
+    'targets' : [] | [Array<Principal>],
+}
+export interface OpenIdProvider {
+    'issuer' : string,
+    'client_ids' : Array<string>,
+    'keys' : Array<Jwk>,
+}
 export interface PersonaRequest {
     'domain' : string,
     'persona_name' : string,
@@ -250,10 +371,18 @@ export interface StringHttpResponse {
     'data' : [] | [string],
     'error' : [] | [Error],
     'status_code' : number,
+    'error_code' : [] | [IdentityManagerError],
 }
 export type UpdateCallsAggregatedData = BigUint64Array | bigint[];
+export interface PrincipalResolution {
+    'principal' : string,
+    'root' : [] | [string],
+    'anchor' : [] | [bigint],
+    'wallet' : [] | [WalletVariant],
+}
 export type WalletVariant = { 'II' : null } |
-    { 'NFID' : null };
+    { 'NFID' : null } |
+    { 'OpenID' : null };
 export interface _SERVICE {
     'add_email_and_principal_for_create_account_validation' : ActorMethod<
         [string, string, bigint],
@@ -270,13 +399,19 @@ export interface _SERVICE {
     'get_account_by_anchor' : ActorMethod<[bigint], HTTPAccountResponse>,
     'get_account_by_principal' : ActorMethod<[string], HTTPAccountResponse>,
     'get_all_accounts_json' : ActorMethod<[number, number], string>,
+    'export_accounts' : ActorMethod<[AccountsExportRequest], AccountsChunk>,
+    'import_accounts' : ActorMethod<[AccountsImportRequest], HTTPAccountsImportResponse>,
     'get_captcha' : ActorMethod<[], Challenge>,
+    'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
+    'get_access_point_challenge' : ActorMethod<[], AccessPointChallenge>,
     'get_config' : ActorMethod<[], ConfigurationResponse>,
     'get_remaining_size_after_rebuild_device_index_slice_from_temp_stack' : ActorMethod<
         [[] | [bigint]],
         bigint
     >,
     'get_root_certified' : ActorMethod<[], CertifiedResponse>,
+    'get_roots_by_principals' : ActorMethod<[Array<string>], Array<PrincipalResolution>>,
+    'get_roots_certified' : ActorMethod<[Array<string>], CertifiedResolutionsResponse>,
     'pause_account_creation' : ActorMethod<[boolean], undefined>,
     'read_access_points' : ActorMethod<[], HTTPAccessPointResponse>,
     'read_applications' : ActorMethod<[], HTTPApplicationResponse>,
@@ -286,16 +421,19 @@ export interface _SERVICE {
         HTTPAccessPointResponse
     >,
     'remove_account' : ActorMethod<[], BoolHttpResponse>,
+    'cancel_remove_account' : ActorMethod<[], HTTPAccountResponse>,
     'remove_account_by_principal' : ActorMethod<[string], BoolHttpResponse>,
     'restore_accounts' : ActorMethod<[string], BoolHttpResponse>,
     'save_temp_stack_to_rebuild_device_index' : ActorMethod<[], string>,
+    'set_openid_provider' : ActorMethod<[OpenIdProvider], undefined>,
     'store_accounts' : ActorMethod<[Array<Account>], BoolHttpResponse>,
     'sync_controllers' : ActorMethod<[], Array<string>>,
     'sync_recovery_phrase_from_internet_identity' : ActorMethod<
         [bigint],
         HTTPAccountResponse
     >,
-    'update_2fa' : ActorMethod<[boolean], AccountResponse>,
+    'migrate_to_nfid' : ActorMethod<[], HTTPAccountResponse>,
+    'update_2fa' : ActorMethod<[boolean], HTTPAccountResponse>,
     'get_root_by_principal': ActorMethod<[string], [[] | [string]]>,
     'update_access_point' : ActorMethod<
         [AccessPointRequest],
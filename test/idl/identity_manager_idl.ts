@@ -1,9 +1,51 @@
 export const idlFactory = ({ IDL }) => {
     const Error = IDL.Text;
+    const IdentityManagerError = IDL.Variant({
+        'AccountNotFound' : IDL.Null,
+        'AnchorNotRegistered' : IDL.Null,
+        'PrincipalNotRegistered' : IDL.Null,
+        'AccountExists' : IDL.Null,
+        'AnonymousCaller' : IDL.Null,
+        'NfidOnly' : IDL.Null,
+        'AccessPointNotFound' : IDL.Null,
+        'AccessPointExists' : IDL.Null,
+        'NotAnAccessPoint' : IDL.Null,
+        'Forbidden' : IDL.Null,
+        'PasskeyRequired' : IDL.Null,
+        'RecoveryPhraseRequired' : IDL.Null,
+        'RecoveryPhraseProtected' : IDL.Null,
+        'DeviceDataRequired' : IDL.Null,
+        'NameRequired' : IDL.Null,
+        'EmailRequired' : IDL.Null,
+        'EmailNotValidated' : IDL.Null,
+        'ChallengeRequired' : IDL.Null,
+        'SolutionRequired' : IDL.Null,
+        'IncorrectCaptchaKey' : IDL.Null,
+        'IncorrectCaptchaSolution' : IDL.Null,
+        'RecoveryPending' : IDL.Null,
+        'NoPendingRecovery' : IDL.Null,
+        'RecoveryNotAllowed' : IDL.Null,
+        'NoRecoveryPhrase' : IDL.Null,
+        'ConfigurationVersionNotFound' : IDL.Null,
+        'IdTokenRequired' : IDL.Null,
+        'InvalidIdToken' : IDL.Null,
+        'AccessPointProofRequired' : IDL.Null,
+        'InvalidAccessPointProof' : IDL.Null,
+        'BackupChecksumMismatch' : IDL.Null,
+        'InvalidBackup' : IDL.Null,
+        'NoPendingRemoval' : IDL.Null,
+        'InvalidAnchorRange' : IDL.Null,
+        'AnchorRangeExhausted' : IDL.Null,
+        'InternetIdentityOnly' : IDL.Null,
+        'StepUpRequired' : IDL.Null,
+        'InvalidStepUpRule' : IDL.Null,
+        'InvalidPublicKey' : IDL.Null,
+    });
     const BoolHttpResponse = IDL.Record({
         'data' : IDL.Opt(IDL.Bool),
         'error' : IDL.Opt(Error),
         'status_code' : IDL.Nat16,
+        'error_code' : IDL.Opt(IdentityManagerError),
     });
     const ConfigurationRequest = IDL.Record({
         'env' : IDL.Opt(IDL.Text),
@@ -30,6 +72,16 @@ export const idlFactory = ({ IDL }) => {
         'Recovery' : IDL.Null,
         'Unknown' : IDL.Null,
     });
+    const ProofDelegation = IDL.Record({
+        'pubkey' : IDL.Vec(IDL.Nat8),
+        'expiration' : IDL.Nat64,
+        'targets' : IDL.Opt(IDL.Vec(IDL.Principal)),
+    });
+    const AccessPointProof = IDL.Record({
+        'public_key' : IDL.Vec(IDL.Nat8),
+        'signature' : IDL.Vec(IDL.Nat8),
+        'delegation' : IDL.Opt(ProofDelegation),
+    });
     const AccessPointRequest = IDL.Record({
         'icon' : IDL.Text,
         'device_type' : DeviceType,
@@ -37,6 +89,40 @@ export const idlFactory = ({ IDL }) => {
         'pub_key' : IDL.Text,
         'browser' : IDL.Text,
         'credential_id' : IDL.Opt(IDL.Text),
+        'proof' : IDL.Opt(AccessPointProof),
+    });
+    const AccessPointChallenge = IDL.Record({
+        'nonce' : IDL.Vec(IDL.Nat8),
+        'expires_at' : IDL.Nat64,
+    });
+    const BackupFormat = IDL.Variant({ 'Candid' : IDL.Null, 'Cbor' : IDL.Null });
+    const AccountsExportRequest = IDL.Record({
+        'cursor' : IDL.Opt(IDL.Text),
+        'limit' : IDL.Opt(IDL.Nat32),
+        'format' : BackupFormat,
+        'checksum' : IDL.Opt(IDL.Vec(IDL.Nat8)),
+    });
+    const AccountsChunk = IDL.Record({
+        'data' : IDL.Vec(IDL.Nat8),
+        'count' : IDL.Nat32,
+        'next_cursor' : IDL.Opt(IDL.Text),
+        'checksum' : IDL.Vec(IDL.Nat8),
+    });
+    const AccountsImportRequest = IDL.Record({
+        'data' : IDL.Vec(IDL.Nat8),
+        'format' : BackupFormat,
+        'checksum' : IDL.Vec(IDL.Nat8),
+        'first_chunk' : IDL.Opt(IDL.Bool),
+    });
+    const AccountsImportResponse = IDL.Record({
+        'imported' : IDL.Nat32,
+        'checksum' : IDL.Vec(IDL.Nat8),
+    });
+    const HTTPAccountsImportResponse = IDL.Record({
+        'data' : IDL.Opt(AccountsImportResponse),
+        'error' : IDL.Opt(Error),
+        'status_code' : IDL.Nat16,
+        'error_code' : IDL.Opt(IdentityManagerError),
     });
     const AccessPointResponse = IDL.Record({
         'icon' : IDL.Text,
@@ -51,8 +137,13 @@ export const idlFactory = ({ IDL }) => {
         'data' : IDL.Opt(IDL.Vec(AccessPointResponse)),
         'error' : IDL.Opt(Error),
         'status_code' : IDL.Nat16,
+        'error_code' : IDL.Opt(IdentityManagerError),
+    });
+    const WalletVariant = IDL.Variant({
+        'II' : IDL.Null,
+        'NFID' : IDL.Null,
+        'OpenID' : IDL.Null,
     });
-    const WalletVariant = IDL.Variant({ 'II' : IDL.Null, 'NFID' : IDL.Null });
     const ChallengeAttempt = IDL.Record({
         'chars' : IDL.Opt(IDL.Text),
         'challenge_key' : IDL.Text,
@@ -64,6 +155,17 @@ export const idlFactory = ({ IDL }) => {
         'access_point' : IDL.Opt(AccessPointRequest),
         'wallet' : IDL.Opt(WalletVariant),
         'challenge_attempt' : IDL.Opt(ChallengeAttempt),
+        'id_token' : IDL.Opt(IDL.Text),
+    });
+    const Jwk = IDL.Record({
+        'kid' : IDL.Text,
+        'n' : IDL.Text,
+        'e' : IDL.Text,
+    });
+    const OpenIdProvider = IDL.Record({
+        'issuer' : IDL.Text,
+        'client_ids' : IDL.Vec(IDL.Text),
+        'keys' : IDL.Vec(Jwk),
     });
     const PersonaResponse = IDL.Record({
         'domain' : IDL.Text,
@@ -85,6 +187,7 @@ export const idlFactory = ({ IDL }) => {
         'data' : IDL.Opt(AccountResponse),
         'error' : IDL.Opt(Error),
         'status_code' : IDL.Nat16,
+        'error_code' : IDL.Opt(IdentityManagerError),
     });
     const Challenge = IDL.Record({
         'png_base64' : IDL.Opt(IDL.Text),
@@ -113,6 +216,29 @@ export const idlFactory = ({ IDL }) => {
         'witness' : IDL.Vec(IDL.Nat8),
         'response' : IDL.Text,
     });
+    const PrincipalResolution = IDL.Record({
+        'principal' : IDL.Text,
+        'root' : IDL.Opt(IDL.Text),
+        'anchor' : IDL.Opt(IDL.Nat64),
+        'wallet' : IDL.Opt(WalletVariant),
+    });
+    const CertifiedResolutionsResponse = IDL.Record({
+        'certificate' : IDL.Vec(IDL.Nat8),
+        'witness' : IDL.Vec(IDL.Nat8),
+        'response' : IDL.Vec(PrincipalResolution),
+    });
+    const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
+    const HttpRequest = IDL.Record({
+        'url' : IDL.Text,
+        'method' : IDL.Text,
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+    });
+    const HttpResponse = IDL.Record({
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+        'status_code' : IDL.Nat16,
+    });
     const Application = IDL.Record({
         'img' : IDL.Opt(IDL.Text),
         'alias' : IDL.Opt(IDL.Vec(IDL.Text)),
@@ -127,11 +253,13 @@ export const idlFactory = ({ IDL }) => {
         'data' : IDL.Opt(IDL.Vec(Application)),
         'error' : IDL.Opt(Error),
         'status_code' : IDL.Nat16,
+        'error_code' : IDL.Opt(IdentityManagerError),
     });
     const HTTPPersonasResponse = IDL.Record({
         'data' : IDL.Opt(IDL.Vec(PersonaResponse)),
         'error' : IDL.Opt(Error),
         'status_code' : IDL.Nat16,
+        'error_code' : IDL.Opt(IdentityManagerError),
     });
     const AccessPointRemoveRequest = IDL.Record({ 'pub_key' : IDL.Text });
     const BasicEntity = IDL.Record({
@@ -153,6 +281,7 @@ export const idlFactory = ({ IDL }) => {
         'data' : IDL.Opt(AccessPointResponse),
         'error' : IDL.Opt(Error),
         'status_code' : IDL.Nat16,
+        'error_code' : IDL.Opt(IdentityManagerError),
     });
     return IDL.Service({
         'add_email_and_principal_for_create_account_validation' : IDL.Func(
@@ -188,7 +317,19 @@ export const idlFactory = ({ IDL }) => {
             [IDL.Text],
             ['query'],
         ),
+        'export_accounts' : IDL.Func(
+            [AccountsExportRequest],
+            [AccountsChunk],
+            ['query'],
+        ),
+        'import_accounts' : IDL.Func(
+            [AccountsImportRequest],
+            [HTTPAccountsImportResponse],
+            [],
+        ),
         'get_captcha' : IDL.Func([], [Challenge], []),
+        'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
+        'get_access_point_challenge' : IDL.Func([], [AccessPointChallenge], []),
         'get_config' : IDL.Func([], [ConfigurationResponse], ['query']),
         'get_remaining_size_after_rebuild_device_index_slice_from_temp_stack' : IDL.Func(
             [IDL.Opt(IDL.Nat64)],
@@ -196,6 +337,16 @@ export const idlFactory = ({ IDL }) => {
             [],
         ),
         'get_root_certified' : IDL.Func([], [CertifiedResponse], ['query']),
+        'get_roots_by_principals' : IDL.Func(
+            [IDL.Vec(IDL.Text)],
+            [IDL.Vec(PrincipalResolution)],
+            ['query'],
+        ),
+        'get_roots_certified' : IDL.Func(
+            [IDL.Vec(IDL.Text)],
+            [CertifiedResolutionsResponse],
+            ['query'],
+        ),
         'pause_account_creation' : IDL.Func([IDL.Bool], [], []),
         'read_access_points' : IDL.Func([], [HTTPAccessPointResponse], ['query']),
         'read_applications' : IDL.Func([], [HTTPApplicationResponse], ['query']),
@@ -206,6 +357,7 @@ export const idlFactory = ({ IDL }) => {
             [],
         ),
         'remove_account' : IDL.Func([], [BoolHttpResponse], []),
+        'cancel_remove_account' : IDL.Func([], [HTTPAccountResponse], []),
         'remove_account_by_principal' : IDL.Func(
             [IDL.Text],
             [BoolHttpResponse],
@@ -213,6 +365,7 @@ export const idlFactory = ({ IDL }) => {
         ),
         'restore_accounts' : IDL.Func([IDL.Text], [BoolHttpResponse], []),
         'save_temp_stack_to_rebuild_device_index' : IDL.Func([], [IDL.Text], []),
+        'set_openid_provider' : IDL.Func([OpenIdProvider], [], []),
         'store_accounts' : IDL.Func([IDL.Vec(Account)], [BoolHttpResponse], []),
         'sync_controllers' : IDL.Func([], [IDL.Vec(IDL.Text)], []),
         'sync_recovery_phrase_from_internet_identity' : IDL.Func(
@@ -220,7 +373,8 @@ export const idlFactory = ({ IDL }) => {
             [HTTPAccountResponse],
             [],
         ),
-        'update_2fa' : IDL.Func([IDL.Bool], [AccountResponse], []),
+        'migrate_to_nfid' : IDL.Func([], [HTTPAccountResponse], []),
+        'update_2fa' : IDL.Func([IDL.Bool], [HTTPAccountResponse], []),
         'update_access_point' : IDL.Func(
             [AccessPointRequest],
             [HTTPAccessPointResponse],
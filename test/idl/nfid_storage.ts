@@ -6,10 +6,23 @@ export interface InitArgs { 'im_canister' : Principal }
 export interface PassKeyData { 'key' : string, 'data' : string }
 export type Result = { 'Ok' : Principal } |
   { 'Err' : string };
+export type HeaderField = [string, string];
+export interface HttpRequest {
+    'url' : string,
+    'method' : string,
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+}
+export interface HttpResponse {
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+    'status_code' : number,
+}
 export interface _SERVICE {
     'create_canister' : ActorMethod<[], Result>,
     'get_passkey' : ActorMethod<[Array<string>], Array<PassKeyData>>,
     'get_passkey_by_anchor' : ActorMethod<[bigint], Array<PassKeyData>>,
+    'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
     'remove_passkey' : ActorMethod<[string, bigint], bigint>,
     'store_passkey' : ActorMethod<[string, string, bigint], bigint>,
 }
@@ -1,4 +1,16 @@
 export const idlFactory = ({ IDL }) => {
+    const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
+    const HttpRequest = IDL.Record({
+        'url' : IDL.Text,
+        'method' : IDL.Text,
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+    });
+    const HttpResponse = IDL.Record({
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+        'status_code' : IDL.Nat16,
+    });
     const InitArgs = IDL.Record({ 'im_canister' : IDL.Principal });
     const PassKeyData = IDL.Record({ 'key' : IDL.Text, 'data' : IDL.Text });
     const Result = IDL.Variant({ 'Ok' : IDL.Principal, 'Err' : IDL.Text });
@@ -14,6 +26,7 @@ export const idlFactory = ({ IDL }) => {
             [IDL.Vec(PassKeyData)],
             ['query'],
         ),
+        'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
         'remove_passkey' : IDL.Func([IDL.Text, IDL.Nat64], [IDL.Nat64], []),
         'store_passkey' : IDL.Func(
             [IDL.Text, IDL.Text, IDL.Nat64],
@@ -29,8 +29,21 @@ export interface SwapTransaction {
     'swap_provider' : SwapProvider,
 }
 export interface NoteEntry { 'key' : Uint8Array | number[], 'value' : string }
+export type HeaderField = [string, string];
+export interface HttpRequest {
+    'url' : string,
+    'method' : string,
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+}
+export interface HttpResponse {
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+    'status_code' : number,
+}
 export interface _SERVICE {
     'get_transactions' : ActorMethod<[string], Array<SwapTransaction>>,
+    'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
     'store_transaction' : ActorMethod<[SwapTransaction], undefined>,
     'get_notes' : ActorMethod<[Array<Uint8Array | number[]>], Array<NoteEntry>>,
     'store_note' : ActorMethod<[Uint8Array | number[], string], undefined>,
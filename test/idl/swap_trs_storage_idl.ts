@@ -1,4 +1,16 @@
 export const idlFactory = ({ IDL }) => {
+    const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
+    const HttpRequest = IDL.Record({
+        'url' : IDL.Text,
+        'method' : IDL.Text,
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+    });
+    const HttpResponse = IDL.Record({
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+        'status_code' : IDL.Nat16,
+    });
     const InitArgs = IDL.Record({ 'im_canister' : IDL.Principal });
     const Error = IDL.Record({ 'time' : IDL.Nat64, 'message' : IDL.Text });
     const SwapStage = IDL.Variant({
@@ -33,6 +45,7 @@ export const idlFactory = ({ IDL }) => {
             [IDL.Vec(SwapTransaction)],
             ['query'],
         ),
+        'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
         'store_transaction' : IDL.Func([SwapTransaction], [], []),
         'get_notes' : IDL.Func(
             [IDL.Vec(IDL.Vec(IDL.Nat8))],
@@ -24,7 +24,10 @@ export interface AddressBookUserAddress {
     'name' : string,
     'addresses' : Array<AddressBookAddress>,
 }
-export interface Conf { 'im_canister' : [] | [string] }
+export interface Conf {
+    'im_canister' : [] | [string],
+    'test_mode' : [] | [boolean],
+}
 export interface ICRC1 { 'state' : ICRC1State, 'ledger' : string, 'network' : number }
 export type ICRC1State = { 'Inactive' : null } |
     { 'Active' : null };
@@ -32,6 +35,18 @@ export type Result = { 'Ok' : null } |
     { 'Err' : AddressBookError };
 export type Result_1 = { 'Ok' : Array<AddressBookUserAddress> } |
     { 'Err' : AddressBookError };
+export type HeaderField = [string, string];
+export interface HttpRequest {
+    'url' : string,
+    'method' : string,
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+}
+export interface HttpResponse {
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+    'status_code' : number,
+}
 export interface _SERVICE {
     'address_book_delete' : ActorMethod<[string], Result_1>,
     'address_book_delete_all' : ActorMethod<[], Result>,
@@ -39,6 +54,7 @@ export interface _SERVICE {
     'address_book_get_config' : ActorMethod<[], AddressBookConf>,
     'address_book_save' : ActorMethod<[AddressBookUserAddress], Result_1>,
     'address_book_set_config' : ActorMethod<[AddressBookConf], Result>,
+    'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
     'get_canisters_by_root' : ActorMethod<[string], Array<ICRC1>>,
     'remove_icrc1_canister' : ActorMethod<[string], undefined>,
     'store_icrc1_canister' : ActorMethod<[string, ICRC1State, [] | [number]], undefined>,
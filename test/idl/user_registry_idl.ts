@@ -1,4 +1,16 @@
 export const idlFactory = ({ IDL }) => {
+    const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
+    const HttpRequest = IDL.Record({
+        'url' : IDL.Text,
+        'method' : IDL.Text,
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+    });
+    const HttpResponse = IDL.Record({
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+        'status_code' : IDL.Nat16,
+    });
     const AddressBookAddressType = IDL.Variant({
         'IcpAddress' : IDL.Null,
         'IcpPrincipal' : IDL.Null,
@@ -31,7 +43,10 @@ export const idlFactory = ({ IDL }) => {
         'max_user_addresses' : IDL.Nat32,
         'max_name_length' : IDL.Nat32,
     });
-    const Conf = IDL.Record({ 'im_canister' : IDL.Opt(IDL.Text) });
+    const Conf = IDL.Record({
+        'im_canister' : IDL.Opt(IDL.Text),
+        'test_mode' : IDL.Opt(IDL.Bool),
+    });
     const ICRC1State = IDL.Variant({
         'Inactive' : IDL.Null,
         'Active' : IDL.Null,
@@ -48,12 +63,16 @@ export const idlFactory = ({ IDL }) => {
         'address_book_get_config' : IDL.Func([], [AddressBookConf], ['query']),
         'address_book_save' : IDL.Func([AddressBookUserAddress], [Result_1], []),
         'address_book_set_config' : IDL.Func([AddressBookConf], [Result], []),
+        'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
         'get_canisters_by_root' : IDL.Func([IDL.Text], [IDL.Vec(ICRC1)], ['query']),
         'remove_icrc1_canister' : IDL.Func([IDL.Text], [], []),
         'store_icrc1_canister' : IDL.Func([IDL.Text, ICRC1State, IDL.Opt(IDL.Nat32)], [], []),
     });
 };
 export const init = ({ IDL }) => {
-    const Conf = IDL.Record({ 'im_canister' : IDL.Opt(IDL.Text) });
+    const Conf = IDL.Record({
+        'im_canister' : IDL.Opt(IDL.Text),
+        'test_mode' : IDL.Opt(IDL.Bool),
+    });
     return [Conf];
 };
@@ -107,10 +107,23 @@ export interface WalletRegisterRequest {
     'name' : [] | [string],
     'vault_id' : bigint,
 }
+export type HeaderField = [string, string];
+export interface HttpRequest {
+    'url' : string,
+    'method' : string,
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+}
+export interface HttpResponse {
+    'body' : Uint8Array | number[],
+    'headers' : Array<HeaderField>,
+    'status_code' : number,
+}
 export interface _SERVICE {
     'approve_transaction' : ActorMethod<[TransactionApproveRequest], Transaction>,
     'get_all_json' : ActorMethod<[number, number, Backup], string>,
     'count' : ActorMethod<[Backup], bigint>,
+    'http_request' : ActorMethod<[HttpRequest], HttpResponse>,
     'get_policies' : ActorMethod<[bigint], Array<Policy>>,
     'get_transactions' : ActorMethod<[], Array<Transaction>>,
     'get_vaults' : ActorMethod<[], Array<Vault>>,
@@ -1,4 +1,16 @@
 export const idlFactory = ({ IDL }) => {
+    const HeaderField = IDL.Tuple(IDL.Text, IDL.Text);
+    const HttpRequest = IDL.Record({
+        'url' : IDL.Text,
+        'method' : IDL.Text,
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+    });
+    const HttpResponse = IDL.Record({
+        'body' : IDL.Vec(IDL.Nat8),
+        'headers' : IDL.Vec(HeaderField),
+        'status_code' : IDL.Nat16,
+    });
     const Conf = IDL.Record({ 'ledger_canister_id' : IDL.Principal });
     const TransactionState = IDL.Variant({
         'Approved' : IDL.Null,
@@ -126,6 +138,7 @@ export const idlFactory = ({ IDL }) => {
             [IDL.Nat64],
             ['query'],
         ),
+        'http_request' : IDL.Func([HttpRequest], [HttpResponse], ['query']),
         'get_policies' : IDL.Func([IDL.Nat64], [IDL.Vec(Policy)], ['query']),
         'get_transactions' : IDL.Func([], [IDL.Vec(Transaction)], ['query']),
         'get_vaults' : IDL.Func([], [IDL.Vec(Vault)], ['query']),
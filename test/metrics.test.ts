@@ -0,0 +1,50 @@
+import {Dfx} from "./type/dfx";
+import {deploy} from "./util/deployment.util";
+import {App} from "./constanst/app.enum";
+import {expect} from "chai";
+import {HttpRequest} from "./idl/identity_manager";
+
+const request = (url: string): HttpRequest => ({
+    url,
+    method: "GET",
+    headers: [],
+    body: [],
+});
+
+const text = (body: Uint8Array | number[]): string => Buffer.from(body).toString("utf8");
+
+describe("Metrics", () => {
+    var dfx: Dfx;
+
+    before(async () => {
+        dfx = await deploy({apps: [App.IdentityManager, App.UserRegistry, App.ICRC1Oracle]});
+    });
+
+    it("Identity Manager serves metrics", async function () {
+        const response = await dfx.im.actor.http_request(request("/metrics"));
+        expect(response.status_code).eq(200);
+        expect(response.headers).deep.include(["Content-Type", "text/plain; version=0.0.4"]);
+        const body = text(response.body);
+        expect(body).contains("canister_cycles_balance");
+        expect(body).contains("canister_heap_memory_bytes");
+        expect(body).contains("accounts{wallet=\"NFID\"}");
+        expect(body).contains("captcha_challenges");
+    });
+
+    it("User Registry serves metrics", async function () {
+        const response = await dfx.user_registry.actor.http_request(request("/metrics"));
+        expect(response.status_code).eq(200);
+        expect(text(response.body)).contains("address_book_users");
+    });
+
+    it("ICRC1 Oracle serves metrics", async function () {
+        const response = await dfx.icrc1_oracle.actor.http_request(request("/metrics")) as any;
+        expect(response.status_code).eq(200);
+        expect(text(response.body)).contains("icrc1_canisters");
+    });
+
+    it("Other paths are not found", async function () {
+        const response = await dfx.im.actor.http_request(request("/accounts"));
+        expect(response.status_code).eq(404);
+    });
+});
@@ -28,6 +28,7 @@ describe("NFID Storage test", () => {
                 Email: null,
             },
             credential_id: [],
+            proof: [],
         };
         var accountRequest: HTTPAccountRequest = {
             access_point: [dd],
@@ -35,6 +36,7 @@ describe("NFID Storage test", () => {
             anchor: 0n,
             email: ["test@test.test"],
             name: [],
+            id_token: [],
                 challenge_attempt: [],
         };
         const actor = await getActor(dfx.im.id, identity, imIdl);
@@ -0,0 +1,92 @@
+import "mocha";
+import {expect} from "chai";
+import {createHash, generateKeyPairSync, KeyObject, sign} from "crypto";
+import {Ed25519KeyIdentity} from "@dfinity/identity";
+import {Dfx} from "./type/dfx";
+import {App} from "./constanst/app.enum";
+import {deploy, getActor, getIdentity} from "./util/deployment.util";
+import {
+    AccessPointRequest,
+    Challenge,
+    HTTPAccountRequest,
+    HTTPAccountResponse,
+    HTTPOneAccessPointResponse,
+} from "./idl/identity_manager";
+import {idlFactory as imIdl} from "./idl/identity_manager_idl";
+
+const ISSUER = "https://accounts.example.com";
+const CLIENT_ID = "nfid-client";
+const KID = "key-1";
+
+const base64url = (data: string | Buffer): string => Buffer.from(data).toString("base64url");
+
+// The nonce binds the ID token to the principal that presents it, see `expected_nonce`.
+const signIdToken = (key: KeyObject, identity: Ed25519KeyIdentity, sub: string): string => {
+    const header = base64url(JSON.stringify({alg: "RS256", kid: KID, typ: "JWT"}));
+    const claims = base64url(JSON.stringify({
+        iss: ISSUER,
+        sub,
+        aud: CLIENT_ID,
+        exp: Math.floor(Date.now() / 1000) + 600,
+        nonce: createHash("sha256").update(identity.getPrincipal().toUint8Array()).digest("hex"),
+    }));
+    const signature = sign("sha256", Buffer.from(`${header}.${claims}`), key);
+    return `${header}.${claims}.${base64url(signature)}`;
+};
+
+describe("OpenID", () => {
+    var dfx: Dfx;
+    var privateKey: KeyObject;
+
+    before(async () => {
+        dfx = await deploy({apps: [App.IdentityManager]});
+        const keys = generateKeyPairSync("rsa", {modulusLength: 2048});
+        privateKey = keys.privateKey;
+        const jwk = keys.publicKey.export({format: "jwk"});
+        await dfx.im.actor.set_openid_provider({
+            issuer: ISSUER,
+            client_ids: [CLIENT_ID],
+            keys: [{kid: KID, n: jwk.n as string, e: jwk.e as string}],
+        });
+    });
+
+    it("should sign in to an OpenID account created with a name", async function () {
+        const identity = getIdentity("87654321876543218765432187654391");
+        const actor = await getActor(dfx.im.id, identity, imIdl);
+        const deviceIdentity = getIdentity("87654321876543218765432187654392");
+
+        const dd: AccessPointRequest = {
+            icon: "Google",
+            device: "Google",
+            pub_key: deviceIdentity.getPrincipal().toText(),
+            browser: "",
+            device_type: {
+                Passkey: null,
+            },
+            credential_id: [],
+            proof: [],
+        };
+        const captcha = await actor.get_captcha() as Challenge;
+        const accountRequest: HTTPAccountRequest = {
+            access_point: [dd],
+            wallet: [{OpenID: null}],
+            anchor: 0n,
+            email: [],
+            name: ["OpenID Wallet"],
+            id_token: [signIdToken(privateKey, identity, "user-42")],
+            challenge_attempt: [{
+                chars: ["aaaaa"],
+                challenge_key: captcha.challenge_key
+            }]
+        };
+        const accountResponse = await actor.create_account(accountRequest) as HTTPAccountResponse;
+        expect(accountResponse.status_code).eq(200);
+        expect(accountResponse.data[0].name[0]).eq("OpenID Wallet");
+        expect(accountResponse.data[0].is2fa_enabled).eq(false);
+
+        const deviceActor = await getActor(dfx.im.id, deviceIdentity, imIdl);
+        const useResponse = await deviceActor.use_access_point([]) as HTTPOneAccessPointResponse;
+        expect(useResponse.status_code).eq(200);
+        expect(useResponse.data[0].principal_id).eq(deviceIdentity.getPrincipal().toText());
+    });
+});
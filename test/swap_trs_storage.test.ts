@@ -30,6 +30,7 @@ describe("Swap Trs Storage test", () => {
                 Email: null,
             },
             credential_id: [],
+            proof: [],
         };
         var accountRequest: HTTPAccountRequest = {
             access_point: [dd],
@@ -37,6 +38,7 @@ describe("Swap Trs Storage test", () => {
             anchor: 0n,
             email: ["test@test.test"],
             name: [],
+            id_token: [],
                 challenge_attempt: [],
         };
         const actor = await getActor(dfx.im.id, identity, imIdl);
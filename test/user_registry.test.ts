@@ -6,6 +6,7 @@ import {AddressBookUserAddress, AddressBookConf, _SERVICE as UserRegistry} from
 import {DFX} from "./constanst/dfx.const";
 import {AccessPointRequest} from "./idl/identity_manager";
 import {idlFactory as userRegistryIdl} from "./idl/user_registry_idl";
+import {getAccessPointProof} from "./util/access_point.util";
 
 describe("User Registry", () => {
     var dfx: Dfx;
@@ -54,6 +55,7 @@ describe("User Registry", () => {
                     Passkey: null,
                 },
                 credential_id: [],
+                proof: await getAccessPointProof(actor, dfx.user.identity),
             };
             await actor.create_access_point(addressBookAccessPoint);
         });
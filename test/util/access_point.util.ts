@@ -0,0 +1,25 @@
+import {Actor, ActorSubclass} from "@dfinity/agent";
+import {Ed25519KeyIdentity} from "@dfinity/identity";
+import {AccessPointProof} from "../idl/identity_manager";
+
+const CHALLENGE_DOMAIN = new TextEncoder().encode("\x1Bnfid-access-point-challenge");
+
+// Signs a challenge issued to the caller of the actor with the key of the access point being added.
+export const getAccessPointProof = async (
+    actor: ActorSubclass<any>,
+    accessPointIdentity: Ed25519KeyIdentity
+): Promise<[AccessPointProof]> => {
+    const caller = await Actor.agentOf(actor).getPrincipal();
+    const challenge = await actor.get_access_point_challenge();
+    const message = new Uint8Array([
+        ...CHALLENGE_DOMAIN,
+        ...challenge.nonce,
+        ...caller.toUint8Array(),
+    ]);
+    const signature = await accessPointIdentity.sign(message.buffer);
+    return [{
+        public_key: new Uint8Array(accessPointIdentity.getPublicKey().toDer()),
+        signature: new Uint8Array(signature),
+        delegation: [],
+    }];
+};
@@ -134,7 +134,7 @@ export const deploy = async ({clean = true, apps}: { clean?: boolean, apps: App[
 
         if (apps.includes(App.UserRegistry)) {
             DFX.USE_TEST_ADMIN();
-            DFX.DEPLOY(App.UserRegistry).withArgument("(record { })").run();
+            DFX.DEPLOY(App.UserRegistry).withArgument("(record { test_mode = opt true })").run();
 
             DFX.ADD_CONTROLLER(dfx.user.principal, App.UserRegistry);
             dfx.user_registry.id = DFX.GET_CANISTER_ID(App.UserRegistry);
@@ -143,7 +143,7 @@ export const deploy = async ({clean = true, apps}: { clean?: boolean, apps: App[
 
         if (apps.includes(App.ICRC1Oracle)) {
             DFX.USE_TEST_ADMIN();
-            DFX.DEPLOY(App.ICRC1Oracle).withArgument("(opt record { })").run();
+            DFX.DEPLOY(App.ICRC1Oracle).withArgument("(opt record { test_mode = opt true })").run();
             dfx.icrc1_oracle.id = DFX.GET_CANISTER_ID(App.ICRC1Oracle);
             dfx.icrc1_oracle.actor = await getActor(dfx.icrc1_oracle.id, dfx.user.identity, icrcOracle1Idl);
             DFX.ADD_CONTROLLER(dfx.user.identity.getPrincipal().toText(), App.ICRC1Oracle);
@@ -168,7 +168,7 @@ export const deploy = async ({clean = true, apps}: { clean?: boolean, apps: App[
             return dfx;
         }
         if (apps.includes(App.ECDSASigner)) {
-            DFX.DEPLOY(App.ECDSASigner).run();
+            DFX.DEPLOY(App.ECDSASigner).withArgument("(opt record { test_mode = opt true })").run();
 
             dfx.ic_signer.id = DFX.GET_CANISTER_ID(App.ECDSASigner);
             console.log(">> ", dfx.ic_signer.id);
@@ -249,6 +249,7 @@ export async function createIdentityManagerAccount(dfx: Dfx): Promise<Agent.Acto
             Email: null,
         },
         credential_id: [],
+        proof: [],
     };
     var accountRequest: HTTPAccountRequest = {
         access_point: [accessPoint],
@@ -256,6 +257,7 @@ export async function createIdentityManagerAccount(dfx: Dfx): Promise<Agent.Acto
         anchor: 0n,
         email: ["test@test.test"],
         name: [],
+        id_token: [],
         challenge_attempt: []
     };
     const actor = await getTypedActor<IdentityManagerType>(dfx.im.id, identity, imIdl);